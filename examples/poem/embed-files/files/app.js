@@ -0,0 +1,3 @@
+window.addEventListener("load", () => {
+    document.getElementById("app").textContent += " " + window.location.pathname;
+});
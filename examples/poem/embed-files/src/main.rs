@@ -0,0 +1,27 @@
+use poem::{
+    endpoint::{Embed, EmbeddedFiles},
+    listener::TcpListener,
+    Route, Server,
+};
+
+#[derive(Embed)]
+#[embed(folder = "files")]
+struct Assets;
+
+#[tokio::main]
+async fn main() -> Result<(), std::io::Error> {
+    if std::env::var_os("RUST_LOG").is_none() {
+        std::env::set_var("RUST_LOG", "poem=debug");
+    }
+    tracing_subscriber::fmt::init();
+
+    let app = Route::new().nest(
+        "/",
+        EmbeddedFiles::new(Assets)
+            .index_file("index.html")
+            .fallback_to_index("index.html"),
+    );
+    Server::new(TcpListener::bind("127.0.0.1:3000"))
+        .run(app)
+        .await
+}
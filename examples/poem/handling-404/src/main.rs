@@ -1,6 +1,6 @@
 use poem::{
-    get, handler, http::StatusCode, listener::TcpListener, web::Path, EndpointExt, Response, Route,
-    Server,
+    endpoint::make_sync, get, handler, http::StatusCode, listener::TcpListener, web::Path,
+    Response, Route, Server,
 };
 
 #[handler]
@@ -17,15 +17,11 @@ async fn main() -> Result<(), std::io::Error> {
 
     let app = Route::new()
         .at("/hello/:name", get(hello))
-        .after(|resp| async move {
-            if resp.status() == StatusCode::NOT_FOUND {
-                Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body("haha")
-            } else {
-                resp
-            }
-        });
+        .fallback(make_sync(|_| {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("haha")
+        }));
 
     Server::new(TcpListener::bind("127.0.0.1:3000"))
         .run(app)
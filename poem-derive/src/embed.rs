@@ -0,0 +1,145 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{DeriveInput, Error, Lit, Meta, NestedMeta, Result};
+
+use crate::utils::get_crate_name;
+
+fn get_folder(args: &DeriveInput) -> Result<String> {
+    for attr in &args.attrs {
+        if !attr.path.is_ident("embed") {
+            continue;
+        }
+        if let Meta::List(list) = attr.parse_meta()? {
+            if let Some(NestedMeta::Meta(Meta::NameValue(nv))) = list.nested.first() {
+                if let (true, Lit::Str(folder)) = (nv.path.is_ident("folder"), &nv.lit) {
+                    if list.nested.len() == 1 {
+                        return Ok(folder.value());
+                    }
+                }
+            }
+        }
+        return Err(Error::new_spanned(
+            attr,
+            "expected `#[embed(folder = \"path\")]`",
+        ));
+    }
+
+    Err(Error::new(
+        Span::call_site(),
+        "missing `#[embed(folder = \"...\")]` attribute",
+    ))
+}
+
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(name, path);
+        }
+    }
+    Ok(())
+}
+
+/// Computes the entity tag of the file from its content, with the 64-bit
+/// FNV-1a hash.
+fn compute_etag(data: &[u8]) -> String {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}-{:x}", hash, data.len())
+}
+
+fn include_file(path: &Path) -> TokenStream {
+    let path = path.to_string_lossy();
+    quote!(::std::borrow::Cow::Borrowed(&include_bytes!(#path)[..]))
+}
+
+pub(crate) fn generate(args: DeriveInput) -> Result<TokenStream> {
+    let crate_name = get_crate_name(false);
+    let ident = &args.ident;
+    if !args.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &args.generics,
+            "Embed can not be applied to generic types",
+        ));
+    }
+
+    let folder = get_folder(&args)?;
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let root = Path::new(&manifest_dir).join(&folder);
+    let mut files = BTreeMap::new();
+    collect_files(&root, &root, &mut files).map_err(|err| {
+        Error::new(
+            Span::call_site(),
+            format!("failed to read `{}`: {}", root.display(), err),
+        )
+    })?;
+
+    let mut arms = Vec::new();
+    for (name, path) in &files {
+        let is_variant = [".br", ".gz"].iter().any(|ext| {
+            name.strip_suffix(ext)
+                .map(|base| files.contains_key(base))
+                .unwrap_or_default()
+        });
+        if is_variant {
+            continue;
+        }
+
+        let data = std::fs::read(path).map_err(|err| {
+            Error::new(
+                Span::call_site(),
+                format!("failed to read `{}`: {}", path.display(), err),
+            )
+        })?;
+        let etag = compute_etag(&data);
+        let data = include_file(path);
+        let variant = |ext: &str| match files.get(&format!("{}{}", name, ext)) {
+            Some(path) => {
+                let data = include_file(path);
+                quote!(::std::option::Option::Some(#data))
+            }
+            None => quote!(::std::option::Option::None),
+        };
+        let br = variant(".br");
+        let gzip = variant(".gz");
+
+        arms.push(quote! {
+            #name => ::std::option::Option::Some(#crate_name::endpoint::EmbeddedFile {
+                data: #data,
+                etag: ::std::borrow::Cow::Borrowed(#etag),
+                br: #br,
+                gzip: #gzip,
+            }),
+        });
+    }
+
+    Ok(quote! {
+        impl #crate_name::endpoint::Embed for #ident {
+            fn get(&self, path: &str) -> ::std::option::Option<#crate_name::endpoint::EmbeddedFile> {
+                match path {
+                    #(#arms)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
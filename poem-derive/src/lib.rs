@@ -7,11 +7,15 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![warn(missing_docs)]
 
+mod embed;
+mod typed_path;
 mod utils;
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, AttributeArgs, FnArg, ItemFn, Member, Meta, NestedMeta, Result};
+use syn::{
+    parse_macro_input, AttributeArgs, DeriveInput, FnArg, ItemFn, Member, Meta, NestedMeta, Result,
+};
 
 /// Wrap an asynchronous function as an `Endpoint`.
 ///
@@ -94,6 +98,53 @@ fn generate_handler(internal: bool, input: TokenStream) -> Result<TokenStream> {
     Ok(expanded.into())
 }
 
+/// Derive a type-safe path.
+///
+/// The struct is bound to a path pattern with the `#[typed_path("...")]`
+/// attribute, which is not named `#[path]` because it would be ambiguous with
+/// the built-in `#[path]` attribute. Each field must correspond to a
+/// parameter of the pattern, and each parameter must correspond to a field.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(TypedPath)]
+/// #[typed_path("/users/:id/posts/:post_id")]
+/// struct UserPost {
+///     id: u64,
+///     post_id: u64,
+/// }
+/// ```
+#[proc_macro_derive(TypedPath, attributes(typed_path))]
+pub fn derive_typed_path(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as DeriveInput);
+    match typed_path::generate(args) {
+        Ok(stream) => stream.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Embed the files of a directory into the binary.
+///
+/// The directory is relative to the directory containing the manifest of the
+/// crate.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Embed)]
+/// #[embed(folder = "assets")]
+/// struct Assets;
+/// ```
+#[proc_macro_derive(Embed, attributes(embed))]
+pub fn derive_embed(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as DeriveInput);
+    match embed::generate(args) {
+        Ok(stream) => stream.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
 #[doc(hidden)]
 #[proc_macro]
 pub fn generate_implement_middlewares(_: TokenStream) -> TokenStream {
@@ -0,0 +1,195 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Result};
+
+use crate::utils::get_crate_name;
+
+enum Segment {
+    Static(String),
+    Param { name: String, catch_all: bool },
+}
+
+fn parse_pattern(pattern: &str) -> std::result::Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut s = pattern;
+
+    while !s.is_empty() {
+        if let Some(tail) = s.strip_prefix(':') {
+            let end = tail.find(&[':', '*', '<', '/'][..]).unwrap_or(tail.len());
+            let name = &tail[..end];
+            if name.is_empty() {
+                return Err(format!("missing parameter name in `{}`", pattern));
+            }
+            s = &tail[end..];
+            if let Some(tail) = s.strip_prefix('<') {
+                let end = tail
+                    .find('>')
+                    .ok_or_else(|| format!("unclosed regex in `{}`", pattern))?;
+                s = &tail[end + 1..];
+            }
+            segments.push(Segment::Param {
+                name: name.to_string(),
+                catch_all: false,
+            });
+        } else if let Some(name) = s.strip_prefix('*') {
+            if name.is_empty() {
+                return Err(format!("missing parameter name in `{}`", pattern));
+            }
+            segments.push(Segment::Param {
+                name: name.to_string(),
+                catch_all: true,
+            });
+            s = "";
+        } else if s.starts_with('<') {
+            return Err(format!(
+                "unnamed regex can not be used in a typed path `{}`",
+                pattern
+            ));
+        } else {
+            let end = s.find(&[':', '*', '<'][..]).unwrap_or(s.len());
+            segments.push(Segment::Static(s[..end].to_string()));
+            s = &s[end..];
+        }
+    }
+
+    Ok(segments)
+}
+
+fn get_pattern(args: &DeriveInput) -> Result<String> {
+    for attr in &args.attrs {
+        if !attr.path.is_ident("typed_path") {
+            continue;
+        }
+        if let Meta::List(list) = attr.parse_meta()? {
+            if let Some(NestedMeta::Lit(Lit::Str(pattern))) = list.nested.first() {
+                if list.nested.len() == 1 {
+                    return Ok(pattern.value());
+                }
+            }
+        }
+        return Err(Error::new_spanned(
+            attr,
+            "expected `#[typed_path(\"/path/:param\")]`",
+        ));
+    }
+
+    Err(Error::new(
+        Span::call_site(),
+        "missing `#[typed_path(\"...\")]` attribute",
+    ))
+}
+
+pub(crate) fn generate(args: DeriveInput) -> Result<TokenStream> {
+    let crate_name = get_crate_name(false);
+    let ident = &args.ident;
+    if !args.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &args.generics,
+            "TypedPath can not be applied to generic structs",
+        ));
+    }
+    let pattern = get_pattern(&args)?;
+    let segments = parse_pattern(&pattern).map_err(|err| Error::new(Span::call_site(), err))?;
+
+    let fields =
+        match &args.data {
+            Data::Struct(s) => match &s.fields {
+                Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+                Fields::Unit => Vec::new(),
+                Fields::Unnamed(_) => return Err(Error::new_spanned(
+                    ident,
+                    "TypedPath can only be applied to structs with named fields or unit structs",
+                )),
+            },
+            _ => {
+                return Err(Error::new_spanned(
+                    ident,
+                    "TypedPath can only be applied to structs",
+                ))
+            }
+        };
+
+    let mut params = Vec::new();
+    for segment in &segments {
+        if let Segment::Param { name, .. } = segment {
+            if params.contains(&name) {
+                return Err(Error::new(
+                    Span::call_site(),
+                    format!("duplicate parameter `{}` in `{}`", name, pattern),
+                ));
+            }
+            if !fields
+                .iter()
+                .any(|field| field.ident.as_ref().unwrap() == name)
+            {
+                return Err(Error::new(
+                    Span::call_site(),
+                    format!("parameter `{}` has no corresponding field", name),
+                ));
+            }
+            params.push(name);
+        }
+    }
+    for field in &fields {
+        let field_ident = field.ident.as_ref().unwrap();
+        if !params.iter().any(|name| field_ident == name) {
+            return Err(Error::new_spanned(
+                field_ident,
+                format!(
+                    "field `{}` is not a parameter of `{}`",
+                    field_ident, pattern
+                ),
+            ));
+        }
+    }
+
+    let mut display = Vec::new();
+    for segment in &segments {
+        match segment {
+            Segment::Static(value) => display.push(quote! { f.write_str(#value)?; }),
+            Segment::Param { name, catch_all } => {
+                let field_ident = syn::Ident::new(name, Span::call_site());
+                display.push(quote! {
+                    #crate_name::__private::write_path_param(f, &self.#field_ident, #catch_all)?;
+                });
+            }
+        }
+    }
+
+    let extract_fields = fields.iter().map(|field| {
+        let field_ident = field.ident.as_ref().unwrap();
+        let name = field_ident.to_string();
+        quote! { #field_ident: #crate_name::__private::parse_path_param(req, #name)? }
+    });
+    let construct = match fields.is_empty() {
+        true => quote! { Self },
+        false => quote! { Self { #(#extract_fields),* } },
+    };
+
+    let expanded = quote! {
+        impl #crate_name::web::TypedPath for #ident {
+            const PATH: &'static str = #pattern;
+        }
+
+        impl ::std::fmt::Display for #ident {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                #(#display)*
+                ::std::result::Result::Ok(())
+            }
+        }
+
+        #[#crate_name::async_trait]
+        impl<'a> #crate_name::FromRequest<'a> for #ident {
+            type Error = #crate_name::error::ErrorInvalidPathParams;
+
+            async fn from_request(
+                req: &'a #crate_name::Request,
+                _body: &mut #crate_name::RequestBody,
+            ) -> ::std::result::Result<Self, Self::Error> {
+                ::std::result::Result::Ok(#construct)
+            }
+        }
+    };
+
+    Ok(expanded)
+}
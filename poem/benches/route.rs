@@ -0,0 +1,58 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use futures_util::FutureExt;
+use poem::{endpoint::make_sync, http::Uri, Endpoint, Request, Route};
+
+fn create_route() -> Route {
+    Route::new()
+        .at("/", make_sync(|_| ()))
+        .at("/users", make_sync(|_| ()))
+        .at("/users/:id", make_sync(|_| ()))
+        .at("/users/:id/posts/:post_id", make_sync(|_| ()))
+        .at("/items/:id<\\d+>", make_sync(|_| ()))
+        .at("/orders/:id<u64>", make_sync(|_| ()))
+        .at("/static/*path", make_sync(|_| ()))
+        .nest(
+            "/api/v1",
+            Route::new().nest(
+                "/admin",
+                Route::new().at("/users/:id/settings", make_sync(|_| ())),
+            ),
+        )
+}
+
+const PATHS: &[(&str, &str)] = &[
+    ("static", "/users"),
+    ("one_param", "/users/42"),
+    ("two_params", "/users/42/posts/hello"),
+    ("regex", "/items/42"),
+    ("param_type", "/orders/42"),
+    ("catch_all", "/static/css/main.css"),
+    ("nested", "/api/v1/admin/users/42/settings"),
+    ("not_found", "/not/found"),
+];
+
+/// Calls the router with endpoints that complete immediately, the requests
+/// are created outside of the measurement.
+///
+/// To compare with another revision, run `cargo bench --bench route --
+/// --save-baseline <name>` on that revision, then `cargo bench --bench route
+/// -- --baseline <name>` on this one.
+fn bench_call(c: &mut Criterion) {
+    let app = create_route();
+
+    let mut group = c.benchmark_group("call");
+    for (name, path) in PATHS {
+        let uri = Uri::from_static(path);
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || Request::builder().uri(uri.clone()).finish(),
+                |req| app.call(req).now_or_never().unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_call);
+criterion_main!(benches);
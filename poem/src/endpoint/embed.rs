@@ -0,0 +1,433 @@
+use std::{borrow::Cow, cmp::Reverse, path::Path};
+
+use bytes::Bytes;
+use headers::{Allow, ContentLength, ETag, HeaderMapExt, IfNoneMatch};
+
+use super::static_common::{encoding_quality, guess_content_type};
+use crate::{
+    http::{header, HeaderValue, Method, StatusCode},
+    Endpoint, Request, Response,
+};
+
+/// A file compiled into the binary.
+#[cfg_attr(docsrs, doc(cfg(feature = "embed")))]
+#[derive(Debug, Clone)]
+pub struct EmbeddedFile {
+    /// The content of the file.
+    pub data: Cow<'static, [u8]>,
+
+    /// The entity tag of the file, without quotes.
+    ///
+    /// It identifies `data`, so it is sent as a weak entity tag with the
+    /// compressed variants.
+    pub etag: Cow<'static, str>,
+
+    /// The brotli compressed content of the file.
+    pub br: Option<Cow<'static, [u8]>>,
+
+    /// The gzip compressed content of the file.
+    pub gzip: Option<Cow<'static, [u8]>>,
+}
+
+/// Represents a set of files compiled into the binary.
+///
+/// This trait can be derived with `#[derive(Embed)]`, which embeds all the
+/// files of a directory, relative to the directory containing the manifest of
+/// the crate. The entity tags are computed at build time from the file
+/// contents, and the `<file>.br` and `<file>.gz` siblings of a file are
+/// embedded as its precompressed variants.
+///
+/// Files added to the directory are only picked up when the crate is
+/// rebuilt, changes to existing files are detected automatically.
+///
+/// # Example
+///
+/// ```ignore
+/// use poem::{
+///     endpoint::{Embed, EmbeddedFiles},
+///     Route,
+/// };
+///
+/// #[derive(Embed)]
+/// #[embed(folder = "assets")]
+/// struct Assets;
+///
+/// let app = Route::new().nest("/", EmbeddedFiles::new(Assets).index_file("index.html"));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "embed")))]
+pub trait Embed: Send + Sync + 'static {
+    /// Returns the file with the specified path, using `/` as the separator
+    /// and without a leading slash.
+    fn get(&self, path: &str) -> Option<EmbeddedFile>;
+}
+
+/// An endpoint that serves files compiled into the binary.
+///
+/// `GET` and `HEAD` requests are supported, other methods are answered with
+/// `405 Method Not Allowed`. Responses contain the `ETag` header, and
+/// requests with a matching `If-None-Match` header are answered with
+/// `304 Not Modified`. The precompressed variants of a file are served if the
+/// client accepts them, with a weak `ETag`.
+///
+/// # Example
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// use poem::{
+///     endpoint::{Embed, EmbeddedFile, EmbeddedFiles},
+///     http::StatusCode,
+///     Endpoint, Request,
+/// };
+///
+/// struct Assets;
+///
+/// impl Embed for Assets {
+///     fn get(&self, path: &str) -> Option<EmbeddedFile> {
+///         match path {
+///             "hello.txt" => Some(EmbeddedFile {
+///                 data: Cow::Borrowed(b"hello"),
+///                 etag: Cow::Borrowed("1"),
+///                 br: None,
+///                 gzip: None,
+///             }),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let app = EmbeddedFiles::new(Assets);
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = app
+///     .call(
+///         Request::builder()
+///             .uri("/hello.txt".parse().unwrap())
+///             .finish(),
+///     )
+///     .await;
+/// assert_eq!(resp.status(), StatusCode::OK);
+/// assert_eq!(resp.into_body().into_string().await.unwrap(), "hello");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "embed")))]
+pub struct EmbeddedFiles<E> {
+    embed: E,
+    index_file: Option<String>,
+    fallback_to_index: Option<String>,
+    prefer_utf8: bool,
+}
+
+impl<E: Embed> EmbeddedFiles<E> {
+    /// Create new `EmbeddedFiles` endpoint for the specified files.
+    pub fn new(embed: E) -> Self {
+        Self {
+            embed,
+            index_file: None,
+            fallback_to_index: None,
+            prefer_utf8: true,
+        }
+    }
+
+    /// Set index file
+    ///
+    /// Shows specific index file for directories.
+    pub fn index_file(self, index: impl Into<String>) -> Self {
+        Self {
+            index_file: Some(index.into()),
+            ..self
+        }
+    }
+
+    /// Serves the specified file for the paths that do not exist and have no
+    /// file extension.
+    ///
+    /// This is useful for single-page applications with client-side routing.
+    pub fn fallback_to_index(self, index: impl Into<String>) -> Self {
+        Self {
+            fallback_to_index: Some(index.into()),
+            ..self
+        }
+    }
+
+    /// Specifies whether text responses should signal a UTF-8 encoding.
+    ///
+    /// Default is `true`.
+    pub fn prefer_utf8(self, value: bool) -> Self {
+        Self {
+            prefer_utf8: value,
+            ..self
+        }
+    }
+
+    fn lookup(&self, path: &str) -> Option<(String, EmbeddedFile)> {
+        if !path.is_empty() {
+            if let Some(file) = self.embed.get(path) {
+                return Some((path.to_string(), file));
+            }
+        }
+
+        if let Some(index_file) = &self.index_file {
+            let index_path = if path.is_empty() {
+                index_file.clone()
+            } else {
+                format!("{}/{}", path, index_file)
+            };
+            if let Some(file) = self.embed.get(&index_path) {
+                return Some((index_path, file));
+            }
+        }
+
+        if let Some(index) = &self.fallback_to_index {
+            if Path::new(path).extension().is_none() {
+                if let Some(file) = self.embed.get(index) {
+                    return Some((index.clone(), file));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Embed> Endpoint for EmbeddedFiles<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let is_head = match *req.method() {
+            Method::GET => false,
+            Method::HEAD => true,
+            _ => {
+                return Response::builder()
+                    .status(StatusCode::METHOD_NOT_ALLOWED)
+                    .typed_header(
+                        vec![Method::GET, Method::HEAD]
+                            .into_iter()
+                            .collect::<Allow>(),
+                    )
+                    .finish()
+            }
+        };
+
+        let path = req
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .trim_end_matches('/');
+        let path = match percent_encoding::percent_decode_str(path).decode_utf8() {
+            Ok(path) => path,
+            Err(_) => return StatusCode::BAD_REQUEST.into(),
+        };
+
+        let (path, file) = match self.lookup(&path) {
+            Some(res) => res,
+            None => return StatusCode::NOT_FOUND.into(),
+        };
+
+        let has_variants = file.br.is_some() || file.gzip.is_some();
+        let mut candidates = vec![
+            ("br", file.br, encoding_quality(req.headers(), "br")),
+            ("gzip", file.gzip, encoding_quality(req.headers(), "gzip")),
+        ];
+        candidates.sort_by_key(|(_, _, quality)| Reverse(*quality));
+        let mut encoding = None;
+        let mut data = file.data;
+        for (name, compressed, quality) in candidates {
+            if let Some(compressed) = compressed.filter(|_| quality > 0) {
+                encoding = Some(name);
+                data = compressed;
+                break;
+            }
+        }
+
+        // the entity tag is computed from the identity content, so it is only
+        // a weak validator of the compressed variants
+        let etag = match encoding {
+            Some(_) => format!("W/\"{}\"", file.etag),
+            None => format!("\"{}\"", file.etag),
+        }
+        .parse::<ETag>()
+        .ok();
+        let mut resp = Response::builder().finish();
+        if let Some(etag) = &etag {
+            if let Some(if_none_match) = req.headers().typed_get::<IfNoneMatch>() {
+                if !if_none_match.precondition_passes(etag) {
+                    resp.set_status(StatusCode::NOT_MODIFIED);
+                }
+            }
+            resp.headers_mut().typed_insert(etag.clone());
+        }
+
+        if has_variants {
+            resp.headers_mut()
+                .insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        }
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return resp;
+        }
+
+        if let Some(content_type) = guess_content_type(Path::new(&path), self.prefer_utf8) {
+            resp.headers_mut()
+                .insert(header::CONTENT_TYPE, content_type);
+        }
+        if let Some(encoding) = encoding {
+            resp.headers_mut()
+                .insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+
+        resp.headers_mut()
+            .typed_insert(ContentLength(data.len() as u64));
+        if !is_head {
+            resp.set_body(match data {
+                Cow::Borrowed(data) => Bytes::from_static(data),
+                Cow::Owned(data) => Bytes::from(data),
+            });
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Uri;
+
+    struct TestAssets;
+
+    impl Embed for TestAssets {
+        fn get(&self, path: &str) -> Option<EmbeddedFile> {
+            let file = |data: &'static [u8], etag: &'static str| EmbeddedFile {
+                data: Cow::Borrowed(data),
+                etag: Cow::Borrowed(etag),
+                br: None,
+                gzip: None,
+            };
+            match path {
+                "index.html" => Some(file(b"index", "1")),
+                "docs/index.html" => Some(file(b"docs", "2")),
+                "app.css" => Some(EmbeddedFile {
+                    br: Some(Cow::Borrowed(b"br")),
+                    gzip: Some(Cow::Borrowed(b"gz")),
+                    ..file(b"app", "3")
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    async fn request(
+        ep: &EmbeddedFiles<TestAssets>,
+        uri: &'static str,
+        headers: &[(&str, &str)],
+    ) -> Response {
+        let mut req = Request::builder().uri(Uri::from_static(uri));
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        ep.call(req.finish()).await
+    }
+
+    #[tokio::test]
+    async fn serve() {
+        let ep = EmbeddedFiles::new(TestAssets).index_file("index.html");
+
+        let resp = request(&ep, "/index.html", &[]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.content_type(), Some("text/html; charset=utf-8"));
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), "\"1\"");
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "index");
+
+        let resp = request(&ep, "/", &[]).await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "index");
+
+        let resp = request(&ep, "/docs/", &[]).await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "docs");
+
+        let resp = request(&ep, "/users/1", &[]).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = request(&ep, "/index.html", &[("if-none-match", "\"1\"")]).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert!(resp.into_body().into_vec().await.unwrap().is_empty());
+
+        let resp = request(&ep, "/index.html", &[("if-none-match", "\"2\"")]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn head_and_fallback() {
+        let ep = EmbeddedFiles::new(TestAssets).fallback_to_index("index.html");
+
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::HEAD)
+                    .uri(Uri::from_static("/app.css"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_LENGTH).unwrap(), "3");
+        assert!(resp.into_body().into_vec().await.unwrap().is_empty());
+
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(Uri::from_static("/app.css"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let resp = request(&ep, "/users/1", &[]).await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "index");
+
+        let resp = request(&ep, "/missing.js", &[]).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn precompressed() {
+        let ep = EmbeddedFiles::new(TestAssets);
+
+        async fn check(
+            ep: &EmbeddedFiles<TestAssets>,
+            accept_encoding: &str,
+            encoding: Option<&str>,
+            body: &str,
+        ) {
+            let resp = request(ep, "/app.css", &[("accept-encoding", accept_encoding)]).await;
+            assert_eq!(
+                resp.headers()
+                    .get(header::CONTENT_ENCODING)
+                    .map(|value| value.to_str().unwrap()),
+                encoding
+            );
+            assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept-encoding");
+            assert_eq!(
+                resp.headers().get(header::ETAG).unwrap(),
+                if encoding.is_some() {
+                    "W/\"3\""
+                } else {
+                    "\"3\""
+                }
+            );
+            assert_eq!(resp.content_type(), Some("text/css; charset=utf-8"));
+            assert_eq!(resp.into_body().into_string().await.unwrap(), body);
+        }
+
+        check(&ep, "gzip, br", Some("br"), "br").await;
+        check(&ep, "gzip, br;q=0.5", Some("gzip"), "gz").await;
+        check(&ep, "deflate", None, "app").await;
+
+        let resp = request(
+            &ep,
+            "/app.css",
+            &[("accept-encoding", "br"), ("if-none-match", "\"3\"")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), "W/\"3\"");
+    }
+}
@@ -1,17 +1,35 @@
 use std::{
+    cmp::Reverse,
     ffi::OsStr,
+    io::SeekFrom,
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use askama::Template;
-use mime::Mime;
-use tokio::fs::File;
+use bytes::Bytes;
+use futures_util::stream;
+use headers::{
+    AcceptRanges, Allow, ContentLength, ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch,
+    IfRange, IfUnmodifiedSince, LastModified,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize, Serializer};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
 
+use super::static_common::{encoding_quality, guess_content_type, media_type_quality};
 use crate::{
-    http::{header, HeaderValue, Method, StatusCode},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
     Body, Endpoint, Request, Response,
 };
 
+/// Requests with more ranges than this are answered with the whole file.
+const MAX_RANGES: usize = 16;
+
 #[derive(Template)]
 #[template(
     ext = "html",
@@ -22,39 +40,142 @@ use crate::{
     </head>
     <body>
         <h1>Index of /{{ path }}</h1>
-        <ul>
-            {% for file in files %}
-            <li>
-                {% if file.is_dir %} 
-                <a href="{{ file.url }}">{{ file.filename | e }}/</a>
-                {% else %}
-                <a href="{{ file.url }}">{{ file.filename | e }}</a>
-                {% endif %}
-            </li>
-            {% endfor %}
-        </ul>
+        <table>
+            <thead>
+                <tr>
+                    <th><a href="?sort=name&order={{ next_order }}">Name</a></th>
+                    <th><a href="?sort=size&order={{ next_order }}">Size</a></th>
+                    <th><a href="?sort=modified&order={{ next_order }}">Modified</a></th>
+                </tr>
+            </thead>
+            <tbody>
+                {% for row in rows %}
+                <tr>
+                    {% if row.is_dir %}
+                    <td><a href="{{ row.url }}">{{ row.name }}/</a></td>
+                    {% else %}
+                    <td><a href="{{ row.url }}">{{ row.name }}</a></td>
+                    {% endif %}
+                    <td>{{ row.size }}</td>
+                    <td>{{ row.modified }}</td>
+                </tr>
+                {% endfor %}
+            </tbody>
+        </table>
     </body>
     </html>
 "#
 )]
 struct DirectoryTemplate<'a> {
     path: &'a str,
-    files: Vec<FileRef>,
+    next_order: &'a str,
+    rows: Vec<DirectoryRow<'a>>,
 }
 
-struct FileRef {
-    url: String,
-    filename: String,
+struct DirectoryRow<'a> {
+    url: &'a str,
+    name: &'a str,
     is_dir: bool,
+    size: String,
+    modified: String,
+}
+
+/// The characters that are percent-encoded in the URLs of a directory
+/// listing.
+const URL_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+type ListingTemplateFn = Box<dyn Fn(&DirectoryListing) -> String + Send + Sync>;
+
+/// The content of a directory, used to render a files listing.
+///
+/// It is serialized as the JSON listing, where the modification times are
+/// the number of seconds since the Unix epoch.
+#[cfg_attr(docsrs, doc(cfg(feature = "staticfiles")))]
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryListing {
+    /// The path of the directory, relative to the base directory.
+    pub path: String,
+
+    /// The entries of the directory, in the requested order.
+    pub entries: Vec<DirectoryEntry>,
+}
+
+/// An entry of a [`DirectoryListing`].
+#[cfg_attr(docsrs, doc(cfg(feature = "staticfiles")))]
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryEntry {
+    /// The name of the entry.
+    pub name: String,
+
+    /// The URL of the entry.
+    pub url: String,
+
+    /// Whether the entry is a directory.
+    pub is_dir: bool,
+
+    /// The size of the file in bytes, or `None` for directories.
+    pub size: Option<u64>,
+
+    /// The last modification time of the entry.
+    #[serde(serialize_with = "serialize_unix_time")]
+    pub modified: Option<SystemTime>,
+}
+
+fn serialize_unix_time<S: Serializer>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .serialize(serializer)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize)]
+struct ListingQuery {
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
 }
 
 /// Static files handling service.
+///
+/// `GET` and `HEAD` requests are supported, other methods are answered with
+/// `405 Method Not Allowed`.
+///
+/// Responses contain the `ETag`, `Last-Modified` and `Accept-Ranges` headers,
+/// conditional requests are answered with `304 Not Modified` or
+/// `412 Precondition Failed`, and `Range` requests are answered with
+/// `206 Partial Content`, using a `multipart/byteranges` body for multiple
+/// ranges.
 #[cfg_attr(docsrs, doc(cfg(feature = "staticfiles")))]
 pub struct Files {
     path: PathBuf,
     show_files_listing: bool,
     index_file: Option<String>,
     prefer_utf8: bool,
+    precompressed_br: bool,
+    precompressed_gzip: bool,
+    fallback_to_index: Option<PathBuf>,
+    show_hidden_files: bool,
+    listing_template: Option<ListingTemplateFn>,
 }
 
 impl Files {
@@ -78,6 +199,11 @@ impl Files {
             show_files_listing: false,
             index_file: None,
             prefer_utf8: true,
+            precompressed_br: false,
+            precompressed_gzip: false,
+            fallback_to_index: None,
+            show_hidden_files: true,
+            listing_template: None,
         }
     }
 
@@ -91,6 +217,63 @@ impl Files {
         }
     }
 
+    /// Hides the files and directories whose names start with a `.` from the
+    /// files listing.
+    ///
+    /// They can still be requested directly. By default hidden files are
+    /// listed.
+    pub fn hide_hidden_files(self) -> Self {
+        Self {
+            show_hidden_files: false,
+            ..self
+        }
+    }
+
+    /// Renders the HTML files listing with the specified function instead of
+    /// the built-in template.
+    ///
+    /// The JSON listing, which is returned when the client prefers
+    /// `application/json` in the `Accept` header, is not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::endpoint::Files;
+    ///
+    /// // the names of the files must be escaped
+    /// fn escape(s: &str) -> String {
+    ///     s.replace('&', "&amp;")
+    ///         .replace('<', "&lt;")
+    ///         .replace('>', "&gt;")
+    ///         .replace('"', "&quot;")
+    /// }
+    ///
+    /// let files = Files::new("/etc/www")
+    ///     .show_files_listing()
+    ///     .listing_template(|listing| {
+    ///         listing
+    ///             .entries
+    ///             .iter()
+    ///             .map(|entry| {
+    ///                 format!(
+    ///                     "<a href=\"{}\">{}</a>",
+    ///                     escape(&entry.url),
+    ///                     escape(&entry.name)
+    ///                 )
+    ///             })
+    ///             .collect()
+    ///     });
+    /// ```
+    pub fn listing_template(
+        self,
+        f: impl Fn(&DirectoryListing) -> String + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            listing_template: Some(Box::new(f)),
+            ..self
+        }
+    }
+
     /// Set index file
     ///
     /// Shows specific index file for directories instead of showing files
@@ -114,6 +297,204 @@ impl Files {
             ..self
         }
     }
+
+    /// Serves a brotli compressed `<file>.br` file instead of `<file>` if it
+    /// exists and the client accepts the `br` encoding.
+    ///
+    /// By default precompressed files are not served.
+    pub fn precompressed_br(self) -> Self {
+        Self {
+            precompressed_br: true,
+            ..self
+        }
+    }
+
+    /// Serves a gzip compressed `<file>.gz` file instead of `<file>` if it
+    /// exists and the client accepts the `gzip` encoding.
+    ///
+    /// If both brotli and gzip are enabled and accepted with the same quality,
+    /// brotli is preferred. By default precompressed files are not served.
+    pub fn precompressed_gzip(self) -> Self {
+        Self {
+            precompressed_gzip: true,
+            ..self
+        }
+    }
+
+    /// Serves the specified file, relative to the base directory, for the
+    /// paths that do not exist and have no file extension.
+    ///
+    /// This is useful for single-page applications with client-side routing,
+    /// where `/users/1` must be served with `index.html`, while a missing
+    /// `/app.js` is still answered with `404 Not Found`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::Files, Route};
+    ///
+    /// let app = Route::new().nest(
+    ///     "/",
+    ///     Files::new("/etc/www")
+    ///         .index_file("index.html")
+    ///         .fallback_to_index("index.html"),
+    /// );
+    /// ```
+    pub fn fallback_to_index(self, index: impl Into<PathBuf>) -> Self {
+        Self {
+            fallback_to_index: Some(index.into()),
+            ..self
+        }
+    }
+
+    fn list_directory(&self, req: &Request, dir: &Path, path: &str) -> Response {
+        let read_dir = match dir.read_dir() {
+            Ok(d) => d,
+            Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+        };
+
+        let mut base_url = req.original_uri().path().to_string();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+
+        let mut entries = Vec::new();
+        for res in read_dir {
+            let entry = match res {
+                Ok(entry) => entry,
+                Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+            };
+
+            if let Some(filename) = entry.file_name().to_str() {
+                if !self.show_hidden_files && filename.starts_with('.') {
+                    continue;
+                }
+                let metadata = entry.path().metadata().ok();
+                let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or_default();
+                entries.push(DirectoryEntry {
+                    name: filename.to_string(),
+                    url: format!(
+                        "{}{}",
+                        base_url,
+                        utf8_percent_encode(filename, URL_ENCODE_SET)
+                    ),
+                    is_dir,
+                    size: metadata.as_ref().filter(|_| !is_dir).map(|m| m.len()),
+                    modified: metadata.and_then(|m| m.modified().ok()),
+                });
+            }
+        }
+
+        let query = req
+            .uri()
+            .query()
+            .and_then(|query| serde_urlencoded::from_str::<ListingQuery>(query).ok());
+        let sort = query.as_ref().and_then(|q| q.sort).unwrap_or(SortKey::Name);
+        let order = query.and_then(|q| q.order).unwrap_or(SortOrder::Asc);
+        entries.sort_by(|a, b| {
+            let ordering = match sort {
+                SortKey::Name => a.name.cmp(&b.name),
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+            };
+            let ordering = match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            };
+            b.is_dir.cmp(&a.is_dir).then(ordering)
+        });
+
+        let listing = DirectoryListing {
+            path: path.to_string(),
+            entries,
+        };
+
+        if media_type_quality(req.headers(), &mime::APPLICATION_JSON)
+            > media_type_quality(req.headers(), &mime::TEXT_HTML)
+        {
+            return match serde_json::to_vec(&listing) {
+                Ok(data) => Response::builder()
+                    .content_type("application/json")
+                    .body(data),
+                Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+            };
+        }
+
+        let html = match &self.listing_template {
+            Some(template) => template(&listing),
+            None => {
+                let template = DirectoryTemplate {
+                    path,
+                    next_order: match order {
+                        SortOrder::Asc => "desc",
+                        SortOrder::Desc => "asc",
+                    },
+                    rows: listing
+                        .entries
+                        .iter()
+                        .map(|entry| DirectoryRow {
+                            url: &entry.url,
+                            name: &entry.name,
+                            is_dir: entry.is_dir,
+                            size: entry.size.map(format_size).unwrap_or_default(),
+                            modified: entry.modified.map(format_time).unwrap_or_default(),
+                        })
+                        .collect(),
+                };
+                match template.render() {
+                    Ok(html) => html,
+                    Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+                }
+            }
+        };
+        Response::builder()
+            .header(header::CONTENT_TYPE, mime::TEXT_HTML_UTF_8.as_ref())
+            .body(Body::from_string(html))
+    }
+
+    async fn serve_file(&self, path: &Path, headers: &HeaderMap) -> Response {
+        let content_type = guess_content_type(path, self.prefer_utf8);
+        if !self.precompressed_br && !self.precompressed_gzip {
+            return create_file_response(path, content_type, headers).await;
+        }
+
+        let mut candidates = Vec::new();
+        if self.precompressed_br {
+            candidates.push(("br", "br", encoding_quality(headers, "br")));
+        }
+        if self.precompressed_gzip {
+            candidates.push(("gzip", "gz", encoding_quality(headers, "gzip")));
+        }
+        candidates.sort_by_key(|(_, _, quality)| Reverse(*quality));
+
+        let mut resp = None;
+        for (encoding, extension, quality) in candidates {
+            if quality == 0 {
+                continue;
+            }
+            let mut compressed_path = path.as_os_str().to_os_string();
+            compressed_path.push(".");
+            compressed_path.push(extension);
+            let compressed_path = PathBuf::from(compressed_path);
+            if compressed_path.is_file() {
+                let mut compressed_resp =
+                    create_file_response(&compressed_path, content_type.clone(), headers).await;
+                compressed_resp
+                    .headers_mut()
+                    .insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+                resp = Some(compressed_resp);
+                break;
+            }
+        }
+
+        let mut resp = match resp {
+            Some(resp) => resp,
+            None => create_file_response(path, content_type, headers).await,
+        };
+        resp.headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+        resp
+    }
 }
 
 #[async_trait::async_trait]
@@ -121,10 +502,31 @@ impl Endpoint for Files {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Self::Output {
-        if req.method() != Method::GET {
-            return StatusCode::METHOD_NOT_ALLOWED.into();
+        let is_head = match *req.method() {
+            Method::GET => false,
+            Method::HEAD => true,
+            _ => {
+                return Response::builder()
+                    .status(StatusCode::METHOD_NOT_ALLOWED)
+                    .typed_header(
+                        vec![Method::GET, Method::HEAD]
+                            .into_iter()
+                            .collect::<Allow>(),
+                    )
+                    .finish()
+            }
+        };
+
+        let mut resp = self.serve(&req).await;
+        if is_head {
+            resp.set_body(());
         }
+        resp
+    }
+}
 
+impl Files {
+    async fn serve(&self, req: &Request) -> Response {
         let path = req
             .uri()
             .path()
@@ -152,57 +554,27 @@ impl Endpoint for Files {
         }
 
         if !file_path.exists() {
+            if let Some(index) = &self.fallback_to_index {
+                let index_path = self.path.join(index);
+                if Path::new(&*path).extension().is_none() && index_path.is_file() {
+                    return self.serve_file(&index_path, req.headers()).await;
+                }
+            }
             return StatusCode::NOT_FOUND.into();
         }
 
         if file_path.is_file() {
-            create_file_response(&file_path, self.prefer_utf8).await
+            self.serve_file(&file_path, req.headers()).await
         } else {
             if let Some(index_file) = &self.index_file {
                 let index_path = file_path.join(index_file);
                 if index_path.is_file() {
-                    return create_file_response(&index_path, self.prefer_utf8).await;
+                    return self.serve_file(&index_path, req.headers()).await;
                 }
             }
 
             if self.show_files_listing {
-                let read_dir = match file_path.read_dir() {
-                    Ok(d) => d,
-                    Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
-                };
-                let mut template = DirectoryTemplate {
-                    path: &*path,
-                    files: Vec::new(),
-                };
-
-                for res in read_dir {
-                    let entry = match res {
-                        Ok(entry) => entry,
-                        Err(err) => {
-                            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into()
-                        }
-                    };
-
-                    if let Some(filename) = entry.file_name().to_str() {
-                        let mut base_url = req.original_uri().path().to_string();
-                        if !base_url.ends_with('/') {
-                            base_url.push('/');
-                        }
-                        template.files.push(FileRef {
-                            url: format!("{}{}", base_url, filename),
-                            filename: filename.to_string(),
-                            is_dir: entry.path().is_dir(),
-                        });
-                    }
-                }
-
-                let html = match template.render() {
-                    Ok(html) => html,
-                    Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
-                };
-                Response::builder()
-                    .header(header::CONTENT_TYPE, mime::TEXT_HTML_UTF_8.as_ref())
-                    .body(Body::from_string(html))
+                self.list_directory(req, &file_path, &path)
             } else {
                 StatusCode::NOT_FOUND.into()
             }
@@ -210,73 +582,644 @@ impl Endpoint for Files {
     }
 }
 
-async fn create_file_response(path: &Path, prefer_utf8: bool) -> Response {
-    let guess = mime_guess::from_path(path);
-    let file = match File::open(path).await {
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Formats the time as `YYYY-MM-DD HH:MM:SS` in UTC.
+fn format_time(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => return String::new(),
+    };
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Converts days since the Unix epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+async fn create_file_response(
+    path: &Path,
+    content_type: Option<HeaderValue>,
+    headers: &HeaderMap,
+) -> Response {
+    let mut file = match File::open(path).await {
         Ok(file) => file,
         Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
     };
-    let mut resp = Response::builder().body(Body::from_async_read(file));
-    if let Some(mut mime) = guess.first() {
-        if prefer_utf8 {
-            mime = equiv_utf8_text(mime);
+    let metadata = match file.metadata().await {
+        Ok(metadata) => metadata,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+    };
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = modified.and_then(|modified| create_etag(len, modified));
+    let last_modified = modified.map(LastModified::from);
+
+    let mut resp = Response::builder().finish();
+    if let Some(etag) = &etag {
+        resp.headers_mut().typed_insert(etag.clone());
+    }
+    if let Some(last_modified) = last_modified {
+        resp.headers_mut().typed_insert(last_modified);
+    }
+
+    if let Some(status) = check_preconditions(headers, etag.as_ref(), modified) {
+        resp.set_status(status);
+        return resp;
+    }
+
+    resp.headers_mut().typed_insert(AcceptRanges::bytes());
+
+    let ranges = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| match headers.typed_get::<IfRange>() {
+            Some(if_range) => !if_range.is_modified(etag.as_ref(), last_modified.as_ref()),
+            None => true,
+        })
+        .and_then(|value| parse_range(value, len))
+        .filter(|ranges| ranges.len() <= MAX_RANGES);
+
+    match ranges.as_deref() {
+        None => {
+            if let Some(content_type) = content_type {
+                resp.headers_mut()
+                    .insert(header::CONTENT_TYPE, content_type);
+            }
+            resp.headers_mut().typed_insert(ContentLength(len));
+            resp.set_body(Body::from_async_read(file));
         }
-        if let Ok(header_value) = HeaderValue::from_str(&mime.to_string()) {
+        Some([]) => {
+            resp.set_status(StatusCode::RANGE_NOT_SATISFIABLE);
+            resp.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{}", len)).unwrap(),
+            );
+        }
+        Some([(start, end)]) => {
+            if let Err(err) = file.seek(SeekFrom::Start(*start)).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into();
+            }
+            resp.set_status(StatusCode::PARTIAL_CONTENT);
+            if let Some(content_type) = content_type {
+                resp.headers_mut()
+                    .insert(header::CONTENT_TYPE, content_type);
+            }
+            resp.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)).unwrap(),
+            );
             resp.headers_mut()
-                .insert(header::CONTENT_TYPE, header_value);
+                .typed_insert(ContentLength(end - start + 1));
+            resp.set_body(Body::from_async_read(file.take(end - start + 1)));
+        }
+        Some(ranges) => {
+            let boundary = format!("{:016x}", thread_rng().gen::<u64>());
+            let content_type = content_type
+                .as_ref()
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("application/octet-stream");
+
+            let mut parts = Vec::with_capacity(ranges.len());
+            let mut content_len = 0;
+            for (start, end) in ranges {
+                let part_header = format!(
+                    "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                    boundary, content_type, start, end, len
+                );
+                content_len += part_header.len() as u64 + end - start + 1;
+                parts.push((part_header, *start, end - start + 1));
+            }
+            let end = format!("\r\n--{}--\r\n", boundary);
+            content_len += end.len() as u64;
+            let body = multipart_ranges_body(file, parts, end);
+
+            resp.set_status(StatusCode::PARTIAL_CONTENT);
+            resp.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_str(&format!("multipart/byteranges; boundary={}", boundary))
+                    .unwrap(),
+            );
+            resp.headers_mut().typed_insert(ContentLength(content_len));
+            resp.set_body(body);
         }
     }
+
     resp
 }
 
-fn equiv_utf8_text(ct: Mime) -> Mime {
-    if ct == mime::APPLICATION_JAVASCRIPT {
-        return mime::APPLICATION_JAVASCRIPT_UTF_8;
-    }
+/// Creates a `multipart/byteranges` body, which reads the ranges from the open
+/// file.
+///
+/// Each part is the header of the part, the offset and the length of the
+/// range.
+fn multipart_ranges_body(file: File, parts: Vec<(String, u64, u64)>, end: String) -> Body {
+    const CHUNK_SIZE: u64 = 64 * 1024;
 
-    if ct == mime::TEXT_HTML {
-        return mime::TEXT_HTML_UTF_8;
-    }
+    Body(hyper::Body::wrap_stream(stream::try_unfold(
+        (file, parts.into_iter(), 0, Some(end)),
+        |(mut file, mut parts, remaining, end)| async move {
+            if remaining > 0 {
+                let mut buf = vec![0; remaining.min(CHUNK_SIZE) as usize];
+                file.read_exact(&mut buf).await?;
+                let remaining = remaining - buf.len() as u64;
+                return Ok::<_, std::io::Error>(Some((
+                    Bytes::from(buf),
+                    (file, parts, remaining, end),
+                )));
+            }
 
-    if ct == mime::TEXT_CSS {
-        return mime::TEXT_CSS_UTF_8;
-    }
+            match parts.next() {
+                Some((part_header, start, len)) => {
+                    file.seek(SeekFrom::Start(start)).await?;
+                    Ok(Some((Bytes::from(part_header), (file, parts, len, end))))
+                }
+                None => Ok(end.map(|end| (Bytes::from(end), (file, parts, 0, None)))),
+            }
+        },
+    )))
+}
+
+fn create_etag(len: u64, modified: SystemTime) -> Option<ETag> {
+    etag_value(len, modified)?.parse().ok()
+}
 
-    if ct == mime::TEXT_PLAIN {
-        return mime::TEXT_PLAIN_UTF_8;
+/// Returns the quoted entity tag of a file with the specified length and
+/// modification time.
+pub(crate) fn etag_value(len: u64, modified: SystemTime) -> Option<String> {
+    let modified = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!("\"{:x}-{:x}\"", modified.as_nanos(), len))
+}
+
+/// Evaluates the conditional request headers, and returns the status code of
+/// the response if a precondition applies.
+fn check_preconditions(
+    headers: &HeaderMap,
+    etag: Option<&ETag>,
+    modified: Option<SystemTime>,
+) -> Option<StatusCode> {
+    if let Some(if_match) = headers.typed_get::<IfMatch>() {
+        if !etag.map_or(if_match.is_any(), |etag| if_match.precondition_passes(etag)) {
+            return Some(StatusCode::PRECONDITION_FAILED);
+        }
+    } else if let (Some(if_unmodified_since), Some(modified)) =
+        (headers.typed_get::<IfUnmodifiedSince>(), modified)
+    {
+        if !if_unmodified_since.precondition_passes(modified) {
+            return Some(StatusCode::PRECONDITION_FAILED);
+        }
     }
 
-    if ct == mime::TEXT_CSV {
-        return mime::TEXT_CSV_UTF_8;
+    if let Some(if_none_match) = headers.typed_get::<IfNoneMatch>() {
+        if let Some(etag) = etag {
+            if !if_none_match.precondition_passes(etag) {
+                return Some(StatusCode::NOT_MODIFIED);
+            }
+        }
+    } else if let (Some(if_modified_since), Some(modified)) =
+        (headers.typed_get::<IfModifiedSince>(), modified)
+    {
+        if !if_modified_since.is_modified(modified) {
+            return Some(StatusCode::NOT_MODIFIED);
+        }
     }
 
-    if ct == mime::TEXT_TAB_SEPARATED_VALUES {
-        return mime::TEXT_TAB_SEPARATED_VALUES_UTF_8;
+    None
+}
+
+/// Parses a `Range` header, and returns the satisfiable ranges as inclusive
+/// bounds.
+///
+/// Returns `None` if the header is invalid and must be ignored.
+fn parse_range(value: &str, len: u64) -> Option<Vec<(u64, u64)>> {
+    let specs = value.trim().strip_prefix("bytes=")?;
+    let mut num_specs = 0;
+    let mut ranges = Vec::new();
+
+    for spec in specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+    {
+        num_specs += 1;
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+
+        if start.is_empty() {
+            let suffix_len = end.parse::<u64>().ok()?;
+            if suffix_len > 0 && len > 0 {
+                ranges.push((len.saturating_sub(suffix_len), len - 1));
+            }
+        } else {
+            let start = start.parse::<u64>().ok()?;
+            let end = match end {
+                "" => len.saturating_sub(1),
+                end => {
+                    let end = end.parse::<u64>().ok()?;
+                    if end < start {
+                        return None;
+                    }
+                    end.min(len.saturating_sub(1))
+                }
+            };
+            if start < len {
+                ranges.push((start, end));
+            }
+        }
     }
 
-    ct
+    if num_specs == 0 {
+        return None;
+    }
+    Some(ranges)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::Uri;
+
+    fn create_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("poem-files-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("test.txt"), "0123456789abcdef").unwrap();
+        dir
+    }
+
+    async fn request(files: &Files, headers: &[(&str, &str)]) -> Response {
+        let mut req = Request::builder().uri(Uri::from_static("/test.txt"));
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        files.call(req.finish()).await
+    }
+
+    #[tokio::test]
+    async fn precompressed() {
+        let dir = create_test_dir("precompressed");
+        std::fs::write(dir.join("test.txt.br"), "br").unwrap();
+        std::fs::write(dir.join("test.txt.gz"), "gz").unwrap();
+
+        async fn check(files: &Files, accept_encoding: &str, encoding: Option<&str>, body: &str) {
+            let resp = request(files, &[("accept-encoding", accept_encoding)]).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers()
+                    .get(header::CONTENT_ENCODING)
+                    .map(|value| value.to_str().unwrap()),
+                encoding
+            );
+            assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept-encoding");
+            assert_eq!(resp.content_type(), Some("text/plain; charset=utf-8"));
+            assert_eq!(resp.into_body().into_string().await.unwrap(), body);
+        }
+
+        let files = Files::new(&dir).precompressed_br().precompressed_gzip();
+        check(&files, "gzip, br", Some("br"), "br").await;
+        check(&files, "gzip, br;q=0.5", Some("gzip"), "gz").await;
+        check(&files, "gzip", Some("gzip"), "gz").await;
+        check(&files, "identity", None, "0123456789abcdef").await;
+
+        let files = Files::new(&dir).precompressed_gzip();
+        check(&files, "gzip, br", Some("gzip"), "gz").await;
+
+        std::fs::remove_file(dir.join("test.txt.gz")).unwrap();
+        check(&files, "gzip, br", None, "0123456789abcdef").await;
+
+        let resp = request(&Files::new(&dir), &[("accept-encoding", "br")]).await;
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        assert!(resp.headers().get(header::VARY).is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 
     #[test]
-    fn test_equiv_utf8_text() {
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-4", 10), Some(vec![(0, 4)]));
+        assert_eq!(parse_range("bytes=5-", 10), Some(vec![(5, 9)]));
+        assert_eq!(parse_range("bytes=-3", 10), Some(vec![(7, 9)]));
+        assert_eq!(parse_range("bytes=-30", 10), Some(vec![(0, 9)]));
+        assert_eq!(parse_range("bytes=8-20", 10), Some(vec![(8, 9)]));
+        assert_eq!(
+            parse_range("bytes=0-1, 4-5", 10),
+            Some(vec![(0, 1), (4, 5)])
+        );
+        assert_eq!(parse_range("bytes=10-", 10), Some(vec![]));
+        assert_eq!(parse_range("bytes=-0", 10), Some(vec![]));
+        assert_eq!(parse_range("bytes=5-4", 10), None);
+        assert_eq!(parse_range("bytes=", 10), None);
+        assert_eq!(parse_range("bytes=a-b", 10), None);
+        assert_eq!(parse_range("items=0-1", 10), None);
+    }
+
+    #[tokio::test]
+    async fn conditional_get() {
+        let dir = create_test_dir("conditional");
+        let files = Files::new(&dir);
+
+        let resp = request(&files, &[]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(resp.headers().get(header::CONTENT_LENGTH).unwrap(), "16");
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let last_modified = resp
+            .headers()
+            .get(header::LAST_MODIFIED)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let resp = request(&files, &[("if-none-match", &etag)]).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), etag.as_str());
+        assert!(resp.into_body().into_vec().await.unwrap().is_empty());
+
+        let resp = request(&files, &[("if-none-match", "\"other\"")]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = request(&files, &[("if-modified-since", &last_modified)]).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let resp = request(
+            &files,
+            &[
+                ("if-none-match", "\"other\""),
+                ("if-modified-since", &last_modified),
+            ],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = request(&files, &[("if-match", "\"other\"")]).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let resp = request(&files, &[("if-match", &etag)]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = request(
+            &files,
+            &[("if-unmodified-since", "Thu, 01 Jan 1970 00:00:00 GMT")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn range_requests() {
+        let dir = create_test_dir("range");
+        let files = Files::new(&dir);
+
+        let resp = request(&files, &[("range", "bytes=2-5")]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
         assert_eq!(
-            equiv_utf8_text(mime::APPLICATION_JAVASCRIPT),
-            mime::APPLICATION_JAVASCRIPT_UTF_8
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 2-5/16"
         );
-        assert_eq!(equiv_utf8_text(mime::TEXT_HTML), mime::TEXT_HTML_UTF_8);
-        assert_eq!(equiv_utf8_text(mime::TEXT_CSS), mime::TEXT_CSS_UTF_8);
-        assert_eq!(equiv_utf8_text(mime::TEXT_PLAIN), mime::TEXT_PLAIN_UTF_8);
-        assert_eq!(equiv_utf8_text(mime::TEXT_CSV), mime::TEXT_CSV_UTF_8);
+        assert_eq!(resp.headers().get(header::CONTENT_LENGTH).unwrap(), "4");
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "2345");
+
+        let resp = request(&files, &[("range", "bytes=-3")]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "def");
+
+        let resp = request(&files, &[("range", "bytes=16-")]).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
         assert_eq!(
-            equiv_utf8_text(mime::TEXT_TAB_SEPARATED_VALUES),
-            mime::TEXT_TAB_SEPARATED_VALUES_UTF_8
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */16"
         );
 
-        assert_eq!(equiv_utf8_text(mime::TEXT_XML), mime::TEXT_XML);
-        assert_eq!(equiv_utf8_text(mime::IMAGE_PNG), mime::IMAGE_PNG);
+        let resp = request(&files, &[("range", "bytes=5-2")]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "0123456789abcdef"
+        );
+
+        let resp = request(&files, &[("range", "bytes=0-1"), ("if-range", "\"other\"")]).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = request(&files, &[("range", "bytes=0-1,-2")]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = resp.content_type().unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let content_length: usize = resp
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = resp.into_body().into_string().await.unwrap();
+        assert_eq!(body.len(), content_length);
+        assert_eq!(
+            body,
+            format!(
+                "\r\n--{0}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-1/16\r\n\r\n01\
+                 \r\n--{0}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 14-15/16\r\n\r\nef\
+                 \r\n--{0}--\r\n",
+                boundary
+            )
+        );
+
+        // the ranges are read in the requested order from the same file
+        let resp = request(&files, &[("range", "bytes=10-12,2-3")]).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.find("\r\n\r\nabc").unwrap() < body.find("\r\n\r\n23").unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn head_and_methods() {
+        let dir = create_test_dir("head");
+        let files = Files::new(&dir);
+
+        let resp = files
+            .call(
+                Request::builder()
+                    .method(Method::HEAD)
+                    .uri(Uri::from_static("/test.txt"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_LENGTH).unwrap(), "16");
+        assert!(resp.headers().contains_key(header::ETAG));
+        assert!(resp.into_body().into_vec().await.unwrap().is_empty());
+
+        let resp = files
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(Uri::from_static("/test.txt"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, HEAD");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fallback_to_index() {
+        let dir = create_test_dir("fallback-to-index");
+        std::fs::write(dir.join("index.html"), "index").unwrap();
+
+        async fn get(files: &Files, uri: &'static str) -> Response {
+            files
+                .call(Request::builder().uri(Uri::from_static(uri)).finish())
+                .await
+        }
+
+        let files = Files::new(&dir);
+        assert_eq!(
+            get(&files, "/users/1").await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        let files = Files::new(&dir).fallback_to_index("index.html");
+        let resp = get(&files, "/users/1").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.content_type(), Some("text/html; charset=utf-8"));
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "index");
+
+        let resp = get(&files, "/test.txt").await;
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "0123456789abcdef"
+        );
+
+        assert_eq!(get(&files, "/app.js").await.status(), StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_listing() {
+        let dir = create_test_dir("listing");
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+        std::fs::create_dir(dir.join("sub dir")).unwrap();
+
+        async fn list(files: &Files, uri: &'static str, accept: &str) -> Response {
+            files
+                .call(
+                    Request::builder()
+                        .uri(Uri::from_static(uri))
+                        .header(header::ACCEPT, accept)
+                        .finish(),
+                )
+                .await
+        }
+
+        async fn names(files: &Files, uri: &'static str) -> Vec<String> {
+            let resp = list(files, uri, "application/json").await;
+            assert_eq!(resp.content_type(), Some("application/json"));
+            let listing: serde_json::Value =
+                serde_json::from_str(&resp.into_body().into_string().await.unwrap()).unwrap();
+            listing["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["name"].as_str().unwrap().to_string())
+                .collect()
+        }
+
+        let files = Files::new(&dir).show_files_listing();
+        assert_eq!(
+            names(&files, "/").await,
+            ["sub dir", ".hidden", "a.txt", "test.txt"]
+        );
+        assert_eq!(
+            names(&files, "/?sort=size&order=desc").await,
+            ["sub dir", "test.txt", "a.txt", ".hidden"]
+        );
+
+        let resp = list(&files, "/", "application/json").await;
+        let listing: serde_json::Value =
+            serde_json::from_str(&resp.into_body().into_string().await.unwrap()).unwrap();
+        assert_eq!(listing["entries"][0]["url"], "/sub%20dir");
+        assert_eq!(listing["entries"][0]["is_dir"], true);
+        assert_eq!(listing["entries"][0]["size"], serde_json::Value::Null);
+        assert_eq!(listing["entries"][3]["size"], 16);
+        assert!(listing["entries"][3]["modified"].is_u64());
+
+        let resp = list(&files, "/", "text/html, */*;q=0.8").await;
+        assert_eq!(resp.content_type(), Some("text/html; charset=utf-8"));
+        let html = resp.into_body().into_string().await.unwrap();
+        assert!(html.contains(r#"<a href="/test.txt">test.txt</a>"#));
+        assert!(html.contains("<td>16 B</td>"));
+
+        let files = Files::new(&dir).show_files_listing().hide_hidden_files();
+        assert_eq!(names(&files, "/").await, ["sub dir", "a.txt", "test.txt"]);
+
+        let files = Files::new(&dir)
+            .show_files_listing()
+            .listing_template(|listing| format!("{} entries", listing.entries.len()));
+        let resp = list(&files, "/", "text/html").await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "4 entries");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+
+        assert_eq!(format_time(UNIX_EPOCH), "1970-01-01 00:00:00");
+        assert_eq!(
+            format_time(UNIX_EPOCH + std::time::Duration::from_secs(951_827_696)),
+            "2000-02-29 12:34:56"
+        );
     }
 }
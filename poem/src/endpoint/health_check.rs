@@ -0,0 +1,425 @@
+use std::{fmt::Display, future::Future, time::Duration};
+
+use futures_util::future::{join_all, BoxFuture, FutureExt};
+use headers::{Allow, ContentLength};
+use serde::Serialize;
+
+use crate::{
+    http::{Method, StatusCode},
+    server::GracefulShutdown,
+    Endpoint, Request, Response,
+};
+
+type CheckFn = Box<dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// A check run by [`HealthCheck`].
+///
+/// By default a check only affects the readiness report and times out after
+/// the default timeout of the [`HealthCheck`].
+pub struct Check {
+    name: String,
+    timeout: Option<Duration>,
+    liveness: bool,
+    f: CheckFn,
+}
+
+impl Check {
+    /// Create a check with the specified name that runs the specified
+    /// function, which fails by returning an error.
+    pub fn new<F, Fut, E>(name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        Self {
+            name: name.into(),
+            timeout: None,
+            liveness: false,
+            f: Box::new(move || f().map(|res| res.map_err(|err| err.to_string())).boxed()),
+        }
+    }
+
+    /// Sets the timeout of this check.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Also runs this check for the liveness report.
+    pub fn liveness(self) -> Self {
+        Self {
+            liveness: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    name: &'a str,
+    status: Status,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    status: Status,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    shutting_down: bool,
+    checks: Vec<CheckReport<'a>>,
+}
+
+/// An endpoint that reports the health of the service.
+///
+/// It serves two routes, relative to the path it is nested at:
+///
+/// - `/live` runs the checks added with [`Check::liveness`].
+/// - `/ready` runs all the checks, and fails when the server started a graceful
+///   shutdown, so that load balancers stop sending requests to it. Use
+///   [`Server::drain_delay`](crate::Server::drain_delay) to keep accepting new
+///   connections while they do.
+///
+/// The checks run concurrently, the response is a JSON report with the status
+/// `200 OK` if all the checks passed, or `503 Service Unavailable` otherwise.
+/// `HEAD` requests run the checks too but get only the status and headers,
+/// other methods than `GET` and `HEAD` get `405 Method Not Allowed`.
+///
+/// ```json
+/// {
+///     "status": "down",
+///     "checks": [
+///         { "name": "db", "status": "up", "duration_ms": 2 },
+///         { "name": "disk", "status": "down", "duration_ms": 0, "error": "disk is full" }
+///     ]
+/// }
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     endpoint::{Check, HealthCheck},
+///     http::StatusCode,
+///     Endpoint, Request, Route,
+/// };
+///
+/// let app = Route::new().nest(
+///     "/health",
+///     HealthCheck::new()
+///         .check(
+///             Check::new("db", || async { Ok::<_, String>(()) }).timeout(Duration::from_secs(1)),
+///         )
+///         .check(Check::new("disk", || async { Err("disk is full") })),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = app
+///     .call(
+///         Request::builder()
+///             .uri("/health/live".parse().unwrap())
+///             .finish(),
+///     )
+///     .await;
+/// assert_eq!(resp.status(), StatusCode::OK);
+///
+/// let resp = app
+///     .call(
+///         Request::builder()
+///             .uri("/health/ready".parse().unwrap())
+///             .finish(),
+///     )
+///     .await;
+/// assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+/// # });
+/// ```
+pub struct HealthCheck {
+    checks: Vec<Check>,
+    timeout: Duration,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Create a `HealthCheck` endpoint without checks.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a check.
+    pub fn check(mut self, check: Check) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Sets the timeout of the checks without their own timeout.
+    ///
+    /// Default is 5 seconds.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    async fn run(&self, readiness: bool, shutting_down: bool, head: bool) -> Response {
+        let checks = self
+            .checks
+            .iter()
+            .filter(|check| readiness || check.liveness)
+            .map(|check| async move {
+                let start = tokio::time::Instant::now();
+                let timeout = check.timeout.unwrap_or(self.timeout);
+                let res = match tokio::time::timeout(timeout, (check.f)()).await {
+                    Ok(res) => res,
+                    Err(_) => Err(format!("timed out after {:?}", timeout)),
+                };
+                CheckReport {
+                    name: &check.name,
+                    status: if res.is_ok() {
+                        Status::Up
+                    } else {
+                        Status::Down
+                    },
+                    duration_ms: start.elapsed().as_millis() as u64,
+                    error: res.err(),
+                }
+            });
+        let checks = join_all(checks).await;
+
+        let healthy = !shutting_down && checks.iter().all(|check| check.error.is_none());
+        let report = Report {
+            status: if healthy { Status::Up } else { Status::Down },
+            shutting_down,
+            checks,
+        };
+        let status = if healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        match serde_json::to_vec(&report) {
+            Ok(data) => {
+                let builder = Response::builder()
+                    .status(status)
+                    .content_type("application/json");
+                if head {
+                    builder
+                        .typed_header(ContentLength(data.len() as u64))
+                        .finish()
+                } else {
+                    builder.body(data)
+                }
+            }
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for HealthCheck {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let readiness = match req.uri().path().trim_matches('/') {
+            "live" => false,
+            "ready" => true,
+            _ => return StatusCode::NOT_FOUND.into(),
+        };
+        let head = match *req.method() {
+            Method::GET => false,
+            Method::HEAD => true,
+            _ => {
+                return Response::builder()
+                    .status(StatusCode::METHOD_NOT_ALLOWED)
+                    .typed_header(
+                        vec![Method::GET, Method::HEAD]
+                            .into_iter()
+                            .collect::<Allow>(),
+                    )
+                    .finish()
+            }
+        };
+
+        let shutting_down = readiness
+            && req
+                .extensions()
+                .get::<GracefulShutdown>()
+                .map(GracefulShutdown::is_started)
+                .unwrap_or_default();
+        self.run(readiness, shutting_down, head).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use headers::HeaderMapExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::http::Uri;
+
+    async fn get(ep: &HealthCheck, uri: &'static str) -> (StatusCode, serde_json::Value) {
+        let resp = ep
+            .call(Request::builder().uri(Uri::from_static(uri)).finish())
+            .await;
+        let status = resp.status();
+        let body = resp.into_body().into_string().await.unwrap();
+        (status, serde_json::from_str(&body).unwrap_or_default())
+    }
+
+    fn without_durations(mut value: serde_json::Value) -> serde_json::Value {
+        for check in value["checks"].as_array_mut().unwrap() {
+            check.as_object_mut().unwrap().remove("duration_ms");
+        }
+        value
+    }
+
+    #[tokio::test]
+    async fn reports() {
+        let healthy = Arc::new(AtomicBool::new(true));
+        let ep = HealthCheck::new()
+            .check(
+                Check::new("ping", || async { Ok::<_, String>(()) })
+                    .liveness()
+                    .timeout(Duration::from_millis(50)),
+            )
+            .check(Check::new("db", {
+                let healthy = healthy.clone();
+                move || {
+                    let healthy = healthy.load(Ordering::SeqCst);
+                    async move {
+                        if healthy {
+                            Ok(())
+                        } else {
+                            Err("connection refused")
+                        }
+                    }
+                }
+            }));
+
+        let (status, report) = get(&ep, "/live").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            without_durations(report),
+            json!({ "status": "up", "checks": [{ "name": "ping", "status": "up" }] })
+        );
+
+        let (status, report) = get(&ep, "/ready").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["checks"].as_array().unwrap().len(), 2);
+
+        healthy.store(false, Ordering::SeqCst);
+        let (status, report) = get(&ep, "/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            without_durations(report),
+            json!({
+                "status": "down",
+                "checks": [
+                    { "name": "ping", "status": "up" },
+                    { "name": "db", "status": "down", "error": "connection refused" },
+                ]
+            })
+        );
+
+        assert_eq!(get(&ep, "/live").await.0, StatusCode::OK);
+        assert_eq!(get(&ep, "/other").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let ep = HealthCheck::new()
+            .timeout(Duration::from_millis(10))
+            .check(Check::new("slow", || async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok::<_, String>(())
+            }));
+
+        let (status, report) = get(&ep, "/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report["checks"][0]["error"], "timed out after 10ms");
+    }
+
+    #[tokio::test]
+    async fn graceful_shutdown() {
+        let ep = HealthCheck::new();
+        let shutdown = GracefulShutdown::default();
+
+        let request = |shutdown: &GracefulShutdown, uri| {
+            let mut req = Request::builder().uri(Uri::from_static(uri)).finish();
+            req.extensions_mut().insert(shutdown.clone());
+            req
+        };
+
+        let resp = ep.call(request(&shutdown, "/ready")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        shutdown.start();
+        let resp = ep.call(request(&shutdown, "/ready")).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            r#"{"status":"down","shutting_down":true,"checks":[]}"#
+        );
+
+        let resp = ep.call(request(&shutdown, "/live")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn methods() {
+        let ep = HealthCheck::new().check(Check::new("db", || async { Err("connection refused") }));
+
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::HEAD)
+                    .uri(Uri::from_static("/ready"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.content_type(), Some("application/json"));
+        let len = resp.headers().typed_get::<ContentLength>().unwrap().0;
+        assert!(len > 0);
+        assert!(resp.into_body().into_vec().await.unwrap().is_empty());
+
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(Uri::from_static("/live"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let allow = resp.headers().typed_get::<Allow>().unwrap();
+        assert_eq!(
+            allow.iter().collect::<Vec<_>>(),
+            vec![Method::GET, Method::HEAD]
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use futures_util::future::{join_all, BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    http::{Method, StatusCode},
+    Endpoint, IntoResponse, Request, Response,
+};
+
+type MethodFn = Arc<dyn Fn(Value) -> BoxFuture<'static, Result<Value, JsonRpcError>> + Send + Sync>;
+
+/// A JSON-RPC 2.0 error object.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    /// The error code.
+    pub code: i64,
+
+    /// A short description of the error.
+    pub message: String,
+
+    /// Additional information about the error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    /// Create an error with the specified code and message.
+    ///
+    /// The codes from `-32768` to `-32000` are reserved by the specification.
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Sets the additional information about the error.
+    pub fn with_data(self, data: impl Into<Value>) -> Self {
+        Self {
+            data: Some(data.into()),
+            ..self
+        }
+    }
+
+    /// Invalid JSON was received (`-32700`).
+    pub fn parse_error() -> Self {
+        Self::new(-32700, "Parse error")
+    }
+
+    /// The JSON sent is not a valid request object (`-32600`).
+    pub fn invalid_request() -> Self {
+        Self::new(-32600, "Invalid Request")
+    }
+
+    /// The method does not exist (`-32601`).
+    pub fn method_not_found() -> Self {
+        Self::new(-32601, "Method not found")
+    }
+
+    /// Invalid method parameters (`-32602`).
+    pub fn invalid_params() -> Self {
+        Self::new(-32602, "Invalid params")
+    }
+
+    /// Internal JSON-RPC error (`-32603`).
+    pub fn internal_error() -> Self {
+        Self::new(-32603, "Internal error")
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRpcResponse {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<JsonRpcError>,
+    id: Value,
+}
+
+impl JsonRpcResponse {
+    fn new(id: Value, res: Result<Value, JsonRpcError>) -> Self {
+        let (result, error) = match res {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+/// An endpoint that dispatches JSON-RPC 2.0 requests to the registered
+/// methods.
+///
+/// It accepts `POST` requests with a single request or a batch of requests,
+/// and responds with `204 No Content` if all of them are notifications.
+///
+/// The parameters of a method are deserialized from the `params` member, or
+/// from `null` if it is absent, and a method fails by returning a
+/// [`JsonRpcError`].
+///
+/// The same dispatcher can serve a WebSocket connection with
+/// [`JsonRpc::serve_websocket`], or any other transport with
+/// [`JsonRpc::handle`].
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     endpoint::{JsonRpc, JsonRpcError},
+///     http::Method,
+///     Endpoint, Request,
+/// };
+///
+/// let rpc = JsonRpc::new()
+///     .method("add", |(a, b): (i32, i32)| async move { Ok(a + b) })
+///     .method("div", |(a, b): (i32, i32)| async move {
+///         a.checked_div(b)
+///             .ok_or_else(|| JsonRpcError::new(1, "division by zero"))
+///     });
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = rpc
+///     .call(
+///         Request::builder()
+///             .method(Method::POST)
+///             .body(r#"{"jsonrpc":"2.0","method":"add","params":[1,2],"id":1}"#),
+///     )
+///     .await;
+/// assert_eq!(
+///     resp.into_body().into_string().await.unwrap(),
+///     r#"{"jsonrpc":"2.0","result":3,"id":1}"#
+/// );
+/// # });
+/// ```
+#[derive(Clone, Default)]
+pub struct JsonRpc {
+    methods: Arc<HashMap<String, MethodFn>>,
+}
+
+impl JsonRpc {
+    /// Create a `JsonRpc` endpoint without methods.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers a method.
+    ///
+    /// The methods registered after the endpoint has been cloned are not added
+    /// to the clones.
+    pub fn method<F, Fut, P, R>(mut self, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(P) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, JsonRpcError>> + Send + 'static,
+        P: DeserializeOwned,
+        R: Serialize,
+    {
+        let f: MethodFn = Arc::new(move |params| {
+            let params = match serde_json::from_value::<P>(params) {
+                Ok(params) => params,
+                Err(err) => {
+                    let err = JsonRpcError::invalid_params().with_data(err.to_string());
+                    return futures_util::future::ready(Err(err)).boxed();
+                }
+            };
+            f(params)
+                .map(|res| {
+                    res.and_then(|value| {
+                        serde_json::to_value(value).map_err(|err| {
+                            JsonRpcError::internal_error().with_data(err.to_string())
+                        })
+                    })
+                })
+                .boxed()
+        });
+        Arc::make_mut(&mut self.methods).insert(name.into(), f);
+        self
+    }
+
+    /// Handles a JSON-RPC message, which is a single request or a batch of
+    /// requests, and returns the response, or `None` if there is nothing to
+    /// respond.
+    pub async fn handle(&self, message: &[u8]) -> Option<String> {
+        let value = match serde_json::from_slice::<Value>(message) {
+            Ok(value) => value,
+            Err(_) => {
+                return serde_json::to_string(&JsonRpcResponse::new(
+                    Value::Null,
+                    Err(JsonRpcError::parse_error()),
+                ))
+                .ok();
+            }
+        };
+
+        match value {
+            Value::Array(requests) if !requests.is_empty() => {
+                let responses = join_all(requests.into_iter().map(|req| self.handle_request(req)))
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                if responses.is_empty() {
+                    None
+                } else {
+                    serde_json::to_string(&responses).ok()
+                }
+            }
+            value => {
+                let resp = self.handle_request(value).await?;
+                serde_json::to_string(&resp).ok()
+            }
+        }
+    }
+
+    async fn handle_request(&self, req: Value) -> Option<JsonRpcResponse> {
+        let mut req = match req {
+            Value::Object(req) => req,
+            _ => {
+                return Some(JsonRpcResponse::new(
+                    Value::Null,
+                    Err(JsonRpcError::invalid_request()),
+                ))
+            }
+        };
+
+        let id = req.remove("id");
+        let valid_id = matches!(
+            id,
+            None | Some(Value::Null | Value::Number(_) | Value::String(_))
+        );
+        let params = req.remove("params");
+        let valid_params = matches!(params, None | Some(Value::Array(_) | Value::Object(_)));
+        let method = match req.remove("method") {
+            Some(Value::String(method)) => Some(method),
+            _ => None,
+        };
+        let method = match method {
+            Some(method)
+                if valid_id && valid_params && req.get("jsonrpc") == Some(&"2.0".into()) =>
+            {
+                method
+            }
+            _ => {
+                return Some(JsonRpcResponse::new(
+                    id.filter(|_| valid_id).unwrap_or_default(),
+                    Err(JsonRpcError::invalid_request()),
+                ))
+            }
+        };
+
+        let res = match self.methods.get(&method) {
+            Some(f) => f(params.unwrap_or_default()).await,
+            None => Err(JsonRpcError::method_not_found()),
+        };
+        id.map(|id| JsonRpcResponse::new(id, res))
+    }
+
+    /// Serves JSON-RPC messages received from a WebSocket connection until it
+    /// is closed.
+    ///
+    /// The responses are sent as text messages.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::JsonRpc,
+    ///     get, handler,
+    ///     web::{websocket::WebSocket, Data},
+    ///     EndpointExt, IntoResponse, Route,
+    /// };
+    ///
+    /// #[handler]
+    /// fn index(ws: WebSocket, rpc: Data<&JsonRpc>) -> impl IntoResponse {
+    ///     let rpc = rpc.clone();
+    ///     ws.on_upgrade(move |socket| async move { rpc.serve_websocket(socket).await })
+    /// }
+    ///
+    /// let rpc = JsonRpc::new().method("ping", |_: ()| async { Ok("pong") });
+    /// let app = Route::new().at("/ws", get(index)).data(rpc);
+    /// ```
+    #[cfg(feature = "websocket")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+    pub async fn serve_websocket(&self, socket: crate::web::websocket::WebSocketStream) {
+        use futures_util::{SinkExt, StreamExt};
+
+        use crate::web::websocket::Message;
+
+        let (mut sink, mut stream) = socket.split();
+        while let Some(Ok(msg)) = stream.next().await {
+            let resp = match msg {
+                Message::Text(text) => self.handle(text.as_bytes()).await,
+                Message::Binary(data) => self.handle(&data).await,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            if let Some(resp) = resp {
+                if sink.send(Message::Text(resp)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for JsonRpc {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        if req.method() != Method::POST {
+            return StatusCode::METHOD_NOT_ALLOWED.into();
+        }
+
+        let data = match req.into_body().into_bytes().await {
+            Ok(data) => data,
+            Err(err) => return err.into_response(),
+        };
+        match self.handle(&data).await {
+            Some(resp) => Response::builder()
+                .content_type("application/json")
+                .body(resp),
+            None => StatusCode::NO_CONTENT.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rpc() -> JsonRpc {
+        JsonRpc::new()
+            .method("subtract", |(a, b): (i64, i64)| async move { Ok(a - b) })
+            .method("notify", |_: Value| async { Ok(()) })
+            .method("fail", |_: ()| async {
+                Err::<(), _>(JsonRpcError::new(1, "failed").with_data("reason"))
+            })
+    }
+
+    async fn call(rpc: &JsonRpc, body: &'static str) -> (StatusCode, Value) {
+        let resp = rpc
+            .call(Request::builder().method(Method::POST).body(body))
+            .await;
+        let status = resp.status();
+        let body = resp.into_body().into_string().await.unwrap();
+        (status, serde_json::from_str(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn method_after_clone() {
+        let rpc = rpc();
+        let other = rpc
+            .clone()
+            .method("add", |(a, b): (i64, i64)| async move { Ok(a + b) });
+
+        let body = r#"{"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1}"#;
+        let (_, resp) = call(&other, body).await;
+        assert_eq!(resp, json!({"jsonrpc": "2.0", "result": 3, "id": 1}));
+        let (_, resp) = call(&rpc, body).await;
+        assert_eq!(resp["error"]["code"], -32601);
+
+        let (_, resp) = call(
+            &other,
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": [3, 2], "id": 2}"#,
+        )
+        .await;
+        assert_eq!(resp, json!({"jsonrpc": "2.0", "result": 1, "id": 2}));
+    }
+
+    #[tokio::test]
+    async fn single() {
+        let rpc = rpc();
+
+        let (status, resp) = call(
+            &rpc,
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(resp, json!({"jsonrpc": "2.0", "result": 19, "id": 1}));
+
+        let (_, resp) = call(&rpc, r#"{"jsonrpc": "2.0", "method": "fail", "id": "a"}"#).await;
+        assert_eq!(
+            resp,
+            json!({
+                "jsonrpc": "2.0",
+                "error": {"code": 1, "message": "failed", "data": "reason"},
+                "id": "a"
+            })
+        );
+
+        let (_, resp) = call(&rpc, r#"{"jsonrpc": "2.0", "method": "foobar", "id": 1}"#).await;
+        assert_eq!(resp["error"]["code"], -32601);
+
+        let (_, resp) = call(
+            &rpc,
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": {"a": 1}, "id": 1}"#,
+        )
+        .await;
+        assert_eq!(resp["error"]["code"], -32602);
+        assert_eq!(resp["id"], 1);
+
+        let (_, resp) = call(
+            &rpc,
+            r#"{"jsonrpc": "2.0", "method": "foobar, "params": "bar""#,
+        )
+        .await;
+        assert_eq!(
+            resp,
+            json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32700, "message": "Parse error"},
+                "id": null
+            })
+        );
+
+        let (_, resp) = call(&rpc, r#"{"jsonrpc": "2.0", "method": 1, "params": "bar"}"#).await;
+        assert_eq!(resp["error"]["code"], -32600);
+        assert_eq!(resp["id"], Value::Null);
+
+        let (_, resp) = call(&rpc, r#"{"method": "subtract", "params": [1, 2], "id": 1}"#).await;
+        assert_eq!(resp["error"]["code"], -32600);
+        assert_eq!(resp["id"], 1);
+
+        let (status, _) = call(
+            &rpc,
+            r#"{"jsonrpc": "2.0", "method": "notify", "params": [1]}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let resp = rpc.call(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn batch() {
+        let rpc = rpc();
+
+        let (_, resp) = call(
+            &rpc,
+            r#"[
+                {"jsonrpc": "2.0", "method": "subtract", "params": [1, 2], "id": "1"},
+                {"jsonrpc": "2.0", "method": "notify", "params": [7]},
+                {"foo": "boo"},
+                {"jsonrpc": "2.0", "method": "foo.get", "params": {"name": "myself"}, "id": "5"}
+            ]"#,
+        )
+        .await;
+        assert_eq!(
+            resp,
+            json!([
+                {"jsonrpc": "2.0", "result": -1, "id": "1"},
+                {"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null},
+                {"jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}, "id": "5"},
+            ])
+        );
+
+        let (_, resp) = call(&rpc, "[]").await;
+        assert_eq!(resp["error"]["code"], -32600);
+
+        let (_, resp) = call(&rpc, "[1, 2]").await;
+        assert_eq!(resp.as_array().unwrap().len(), 2);
+        assert_eq!(resp[1]["error"]["code"], -32600);
+
+        let (status, _) = call(
+            &rpc,
+            r#"[
+                {"jsonrpc": "2.0", "method": "notify", "params": [1]},
+                {"jsonrpc": "2.0", "method": "notify", "params": [2]}
+            ]"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn websocket() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        use crate::{
+            handler,
+            listener::{Acceptor, Listener, TcpListener},
+            web::{websocket::WebSocket, Data},
+            EndpointExt, IntoResponse, Server,
+        };
+
+        #[handler(internal)]
+        fn index(ws: WebSocket, rpc: Data<&JsonRpc>) -> impl IntoResponse {
+            let rpc = rpc.clone();
+            ws.on_upgrade(move |socket| async move { rpc.serve_websocket(socket).await })
+        }
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let handle = tokio::spawn(async move {
+            let _ = Server::new_with_acceptor(acceptor)
+                .run(index.data(rpc()))
+                .await;
+        });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        client
+            .send(Message::Text(
+                r#"{"jsonrpc": "2.0", "method": "notify"}"#.to_string(),
+            ))
+            .await
+            .unwrap();
+        client
+            .send(Message::Text(
+                r#"{"jsonrpc": "2.0", "method": "subtract", "params": [3, 1], "id": 1}"#
+                    .to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Message::Text(r#"{"jsonrpc":"2.0","result":2,"id":1}"#.to_string())
+        );
+
+        handle.abort();
+    }
+}
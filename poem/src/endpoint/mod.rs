@@ -4,31 +4,47 @@ mod after;
 mod and_then;
 mod around;
 mod before;
+#[cfg(feature = "embed")]
+mod embed;
 #[allow(clippy::module_inception)]
 mod endpoint;
 #[cfg(feature = "staticfiles")]
 mod files;
+mod health_check;
+mod json_rpc;
 mod map_err;
 mod map_ok;
 mod map_to_response;
 mod map_to_result;
 #[cfg(feature = "prometheus")]
 mod prometheus_exporter;
+#[cfg(any(feature = "staticfiles", feature = "embed"))]
+mod static_common;
 #[cfg(feature = "tower-compat")]
 mod tower_compat;
+#[cfg(feature = "webdav")]
+mod webdav;
 
 pub use after::After;
 pub use and_then::AndThen;
 pub use around::Around;
 pub use before::Before;
+#[cfg(feature = "embed")]
+pub use embed::{Embed, EmbeddedFile, EmbeddedFiles};
 pub use endpoint::{make, make_sync, BoxEndpoint, Endpoint, EndpointExt, IntoEndpoint};
 #[cfg(feature = "staticfiles")]
-pub use files::Files;
+pub use files::{DirectoryEntry, DirectoryListing, Files};
+pub use health_check::{Check, HealthCheck};
+pub use json_rpc::{JsonRpc, JsonRpcError};
 pub use map_err::MapErr;
 pub use map_ok::MapOk;
 pub use map_to_response::MapToResponse;
 pub use map_to_result::MapToResult;
+#[cfg(feature = "embed")]
+pub use poem_derive::Embed;
 #[cfg(feature = "prometheus")]
 pub use prometheus_exporter::PrometheusExporter;
 #[cfg(feature = "tower-compat")]
 pub use tower_compat::TowerCompatExt;
+#[cfg(feature = "webdav")]
+pub use webdav::WebDav;
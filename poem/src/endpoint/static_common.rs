@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use mime::Mime;
+
+#[cfg(feature = "staticfiles")]
+use crate::route::internal::media_type::{accept_quality, parse_accept};
+use crate::{
+    http::{header, header::HeaderName, HeaderMap, HeaderValue},
+    route::internal::media_type::parse_quality_values,
+};
+
+pub(crate) fn guess_content_type(path: &Path, prefer_utf8: bool) -> Option<HeaderValue> {
+    let mut mime = mime_guess::from_path(path).first()?;
+    if prefer_utf8 {
+        mime = equiv_utf8_text(mime);
+    }
+    HeaderValue::from_str(mime.as_ref()).ok()
+}
+
+/// Returns the quality of the specified content coding in the
+/// `Accept-Encoding` header, between `0` and `1000`.
+pub(crate) fn encoding_quality(headers: &HeaderMap, encoding: &str) -> u16 {
+    header_values(headers, header::ACCEPT_ENCODING)
+        .flat_map(parse_quality_values)
+        .filter_map(|(coding, quality)| {
+            if coding.eq_ignore_ascii_case(encoding) {
+                Some((1, quality))
+            } else if coding == "*" {
+                Some((0, quality))
+            } else {
+                None
+            }
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, quality)| quality)
+        .unwrap_or_default()
+}
+
+/// Returns the quality of the specified media type in the `Accept` header,
+/// between `0` and `1000`.
+#[cfg(feature = "staticfiles")]
+pub(crate) fn media_type_quality(headers: &HeaderMap, media_type: &Mime) -> u16 {
+    let accept = header_values(headers, header::ACCEPT)
+        .flat_map(parse_accept)
+        .collect::<Vec<_>>();
+    accept_quality(&accept, media_type)
+}
+
+fn header_values(headers: &HeaderMap, name: HeaderName) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+}
+
+fn equiv_utf8_text(ct: Mime) -> Mime {
+    if ct == mime::APPLICATION_JAVASCRIPT {
+        return mime::APPLICATION_JAVASCRIPT_UTF_8;
+    }
+
+    if ct == mime::TEXT_HTML {
+        return mime::TEXT_HTML_UTF_8;
+    }
+
+    if ct == mime::TEXT_CSS {
+        return mime::TEXT_CSS_UTF_8;
+    }
+
+    if ct == mime::TEXT_PLAIN {
+        return mime::TEXT_PLAIN_UTF_8;
+    }
+
+    if ct == mime::TEXT_CSV {
+        return mime::TEXT_CSV_UTF_8;
+    }
+
+    if ct == mime::TEXT_TAB_SEPARATED_VALUES {
+        return mime::TEXT_TAB_SEPARATED_VALUES_UTF_8;
+    }
+
+    ct
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_quality() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(encoding_quality(&headers, "gzip"), 0);
+
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("gzip;q=0.5, BR, deflate;q=0"),
+        );
+        assert_eq!(encoding_quality(&headers, "gzip"), 500);
+        assert_eq!(encoding_quality(&headers, "br"), 1000);
+        assert_eq!(encoding_quality(&headers, "deflate"), 0);
+        assert_eq!(encoding_quality(&headers, "zstd"), 0);
+
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("br;q=0, *;q=0.8"),
+        );
+        assert_eq!(encoding_quality(&headers, "br"), 0);
+        assert_eq!(encoding_quality(&headers, "gzip"), 800);
+    }
+
+    #[cfg(feature = "staticfiles")]
+    #[test]
+    fn test_media_type_quality() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(media_type_quality(&headers, &mime::TEXT_HTML), 0);
+
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("text/*;q=0.5, application/json, */*;q=0.1"),
+        );
+        assert_eq!(media_type_quality(&headers, &mime::APPLICATION_JSON), 1000);
+        assert_eq!(media_type_quality(&headers, &mime::TEXT_HTML), 500);
+        assert_eq!(media_type_quality(&headers, &mime::IMAGE_PNG), 100);
+    }
+
+    #[test]
+    fn test_equiv_utf8_text() {
+        assert_eq!(
+            equiv_utf8_text(mime::APPLICATION_JAVASCRIPT),
+            mime::APPLICATION_JAVASCRIPT_UTF_8
+        );
+        assert_eq!(equiv_utf8_text(mime::TEXT_HTML), mime::TEXT_HTML_UTF_8);
+        assert_eq!(equiv_utf8_text(mime::TEXT_CSS), mime::TEXT_CSS_UTF_8);
+        assert_eq!(equiv_utf8_text(mime::TEXT_PLAIN), mime::TEXT_PLAIN_UTF_8);
+        assert_eq!(equiv_utf8_text(mime::TEXT_CSV), mime::TEXT_CSV_UTF_8);
+        assert_eq!(
+            equiv_utf8_text(mime::TEXT_TAB_SEPARATED_VALUES),
+            mime::TEXT_TAB_SEPARATED_VALUES_UTF_8
+        );
+
+        assert_eq!(equiv_utf8_text(mime::TEXT_XML), mime::TEXT_XML);
+        assert_eq!(equiv_utf8_text(mime::IMAGE_PNG), mime::IMAGE_PNG);
+    }
+}
@@ -4,7 +4,7 @@ use bytes::Bytes;
 use hyper::body::HttpBody;
 use tower::{Service, ServiceExt};
 
-use crate::{body::BodyStream, Endpoint, Request, Response, Result};
+use crate::{Endpoint, Request, Response, Result};
 
 /// Extension trait for tower service compat.
 #[cfg_attr(docsrs, doc(cfg(feature = "tower-compat")))]
@@ -66,12 +66,42 @@ where
         let hyper_req: http::Request<hyper::Body> = req.into();
         let hyper_resp = svc.call(hyper_req.map(Into::into)).await?;
 
-        Ok(hyper_resp
-            .map(|body| hyper::Body::wrap_stream(BodyStream::new(body)))
-            .into())
+        Ok(hyper_resp.map(into_hyper_body).into())
     }
 }
 
+/// Converts the response body of a tower service to a [`hyper::Body`],
+/// including the trailers, which are required by gRPC services.
+fn into_hyper_body<B>(body: B) -> hyper::Body
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Into<Bytes> + Send + 'static,
+    B::Error: StdError + Send + Sync + 'static,
+{
+    let (mut sender, hyper_body) = hyper::Body::channel();
+    tokio::spawn(async move {
+        let mut body = Box::pin(body);
+        while let Some(data) = body.data().await {
+            match data {
+                Ok(data) => {
+                    if sender.send_data(data.into()).await.is_err() {
+                        return;
+                    }
+                }
+                Err(_) => return sender.abort(),
+            }
+        }
+        match body.trailers().await {
+            Ok(Some(trailers)) => {
+                let _ = sender.send_trailers(trailers).await;
+            }
+            Ok(None) => {}
+            Err(_) => sender.abort(),
+        }
+    });
+    hyper_body
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -0,0 +1,148 @@
+/// A list of the `If` header, which is satisfied if all of its conditions
+/// are, as described in [RFC 4918](https://www.rfc-editor.org/rfc/rfc4918#section-10.4).
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct IfList<'a> {
+    /// The resource tag of the list, the list applies to the requested
+    /// resource if it is `None`.
+    pub(crate) resource: Option<&'a str>,
+    pub(crate) conditions: Vec<Condition<'a>>,
+}
+
+/// A condition of a list, which is negated by `Not`.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Condition<'a> {
+    pub(crate) not: bool,
+    pub(crate) state: State<'a>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum State<'a> {
+    /// A state token, such as a lock token.
+    Token(&'a str),
+    /// An entity tag, including its quotes.
+    ETag(&'a str),
+}
+
+/// Parses the value of an `If` header, the lists that follow a resource tag
+/// apply to that resource.
+pub(crate) fn parse_if_header(value: &str) -> Option<Vec<IfList<'_>>> {
+    let mut lists = Vec::new();
+    let mut resource = None;
+    let mut s = value.trim_start();
+
+    while !s.is_empty() {
+        if let Some(rest) = s.strip_prefix('<') {
+            let (tag, rest) = rest.split_once('>')?;
+            resource = Some(tag);
+            s = rest.trim_start();
+            if !s.starts_with('(') {
+                return None;
+            }
+            continue;
+        }
+
+        let mut rest = s.strip_prefix('(')?.trim_start();
+        let mut conditions = Vec::new();
+        loop {
+            if let Some(end) = rest.strip_prefix(')') {
+                if conditions.is_empty() {
+                    return None;
+                }
+                s = end.trim_start();
+                break;
+            }
+
+            let not = match rest.strip_prefix("Not") {
+                Some(after) => {
+                    rest = after.trim_start();
+                    true
+                }
+                None => false,
+            };
+            let (state, after) = if let Some(after) = rest.strip_prefix('<') {
+                let (token, after) = after.split_once('>')?;
+                (State::Token(token), after)
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let (etag, after) = after.split_once(']')?;
+                (State::ETag(etag.trim()), after)
+            } else {
+                return None;
+            };
+            conditions.push(Condition { not, state });
+            rest = after.trim_start();
+        }
+        lists.push(IfList {
+            resource,
+            conditions,
+        });
+    }
+
+    if lists.is_empty() {
+        None
+    } else {
+        Some(lists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_if_header() {
+        assert_eq!(
+            parse_if_header("(<urn:uuid:1> [\"a\"]) (Not <DAV:no-lock>)").unwrap(),
+            vec![
+                IfList {
+                    resource: None,
+                    conditions: vec![
+                        Condition {
+                            not: false,
+                            state: State::Token("urn:uuid:1"),
+                        },
+                        Condition {
+                            not: false,
+                            state: State::ETag("\"a\""),
+                        },
+                    ],
+                },
+                IfList {
+                    resource: None,
+                    conditions: vec![Condition {
+                        not: true,
+                        state: State::Token("DAV:no-lock"),
+                    }],
+                },
+            ]
+        );
+
+        assert_eq!(
+            parse_if_header("</a> ([W/\"a\"]) </b> (Not<urn:uuid:2>)").unwrap(),
+            vec![
+                IfList {
+                    resource: Some("/a"),
+                    conditions: vec![Condition {
+                        not: false,
+                        state: State::ETag("W/\"a\""),
+                    }],
+                },
+                IfList {
+                    resource: Some("/b"),
+                    conditions: vec![Condition {
+                        not: true,
+                        state: State::Token("urn:uuid:2"),
+                    }],
+                },
+            ]
+        );
+
+        assert_eq!(parse_if_header(""), None);
+        assert_eq!(parse_if_header("</a>"), None);
+        assert_eq!(parse_if_header("()"), None);
+        assert_eq!(parse_if_header("(Not)"), None);
+        assert_eq!(parse_if_header("(<urn:uuid:1>"), None);
+        assert_eq!(parse_if_header("(<urn:uuid:1>) </a>"), None);
+        assert_eq!(parse_if_header("<urn:uuid:1>"), None);
+        assert_eq!(parse_if_header("urn:uuid:1"), None);
+    }
+}
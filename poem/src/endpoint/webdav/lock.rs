@@ -0,0 +1,229 @@
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use rand::rngs::OsRng;
+
+use super::xml::escape;
+use crate::uuid::uuid_v4;
+
+/// A lock on a resource, identified by its path relative to the root
+/// directory.
+#[derive(Debug, Clone)]
+pub(crate) struct Lock {
+    pub(crate) token: String,
+    pub(crate) path: String,
+    pub(crate) exclusive: bool,
+    pub(crate) depth_infinity: bool,
+    pub(crate) owner: Option<String>,
+    pub(crate) timeout: Duration,
+    expires_at: Instant,
+}
+
+impl Lock {
+    /// Returns `true` if this lock applies to the specified path.
+    fn covers(&self, path: &str) -> bool {
+        self.path == path || (self.depth_infinity && is_descendant(path, &self.path))
+    }
+
+    /// Writes the `activelock` element of this lock.
+    pub(crate) fn write_active_lock(&self, output: &mut String, root_href: &str) {
+        let _ = write!(
+            output,
+            "<D:activelock>\
+             <D:locktype><D:write/></D:locktype>\
+             <D:lockscope><D:{}/></D:lockscope>\
+             <D:depth>{}</D:depth>",
+            if self.exclusive {
+                "exclusive"
+            } else {
+                "shared"
+            },
+            if self.depth_infinity { "infinity" } else { "0" },
+        );
+        if let Some(owner) = &self.owner {
+            let _ = write!(output, "<D:owner>{}</D:owner>", owner);
+        }
+        let _ = write!(
+            output,
+            "<D:timeout>Second-{}</D:timeout>\
+             <D:locktoken><D:href>{}</D:href></D:locktoken>\
+             <D:lockroot><D:href>{}</D:href></D:lockroot>\
+             </D:activelock>",
+            self.timeout.as_secs(),
+            escape(&self.token),
+            escape(root_href),
+        );
+    }
+}
+
+/// Returns `true` if `path` is inside the directory `parent`.
+pub(crate) fn is_descendant(path: &str, parent: &str) -> bool {
+    if parent.is_empty() {
+        return !path.is_empty();
+    }
+    path.strip_prefix(parent)
+        .map(|rest| rest.starts_with('/'))
+        .unwrap_or_default()
+}
+
+/// Generates a random `urn:uuid:` lock token.
+fn generate_token() -> String {
+    format!("urn:uuid:{}", uuid_v4(&mut OsRng))
+}
+
+/// Stores the locks of a [`WebDav`](super::WebDav) endpoint in memory.
+#[derive(Default)]
+pub(crate) struct LockManager {
+    locks: Mutex<Vec<Lock>>,
+}
+
+impl LockManager {
+    fn active_locks(&self) -> parking_lot::MutexGuard<'_, Vec<Lock>> {
+        let mut locks = self.locks.lock();
+        let now = Instant::now();
+        locks.retain(|lock| lock.expires_at > now);
+        locks
+    }
+
+    /// Creates a lock, or returns `None` if it conflicts with an existing
+    /// lock.
+    pub(crate) fn lock(
+        &self,
+        path: &str,
+        exclusive: bool,
+        depth_infinity: bool,
+        owner: Option<String>,
+        timeout: Duration,
+    ) -> Option<Lock> {
+        let mut locks = self.active_locks();
+        let conflict = locks.iter().any(|lock| {
+            (exclusive || lock.exclusive)
+                && (lock.covers(path) || (depth_infinity && is_descendant(&lock.path, path)))
+        });
+        if conflict {
+            return None;
+        }
+
+        let lock = Lock {
+            token: generate_token(),
+            path: path.to_string(),
+            exclusive,
+            depth_infinity,
+            owner,
+            timeout,
+            expires_at: Instant::now() + timeout,
+        };
+        locks.push(lock.clone());
+        Some(lock)
+    }
+
+    /// Refreshes a lock that applies to the specified path with one of the
+    /// specified tokens.
+    pub(crate) fn refresh(&self, path: &str, tokens: &[String], timeout: Duration) -> Option<Lock> {
+        let mut locks = self.active_locks();
+        let lock = locks
+            .iter_mut()
+            .find(|lock| lock.covers(path) && tokens.contains(&lock.token))?;
+        lock.timeout = timeout;
+        lock.expires_at = Instant::now() + timeout;
+        Some(lock.clone())
+    }
+
+    /// Removes the lock with the specified token that applies to the
+    /// specified path.
+    pub(crate) fn unlock(&self, path: &str, token: &str) -> bool {
+        let mut locks = self.active_locks();
+        let len = locks.len();
+        locks.retain(|lock| !(lock.token == token && lock.covers(path)));
+        locks.len() != len
+    }
+
+    /// Returns `true` if the lock with the specified token applies to the
+    /// specified path.
+    pub(crate) fn has_token(&self, path: &str, token: &str) -> bool {
+        self.active_locks()
+            .iter()
+            .any(|lock| lock.token == token && lock.covers(path))
+    }
+
+    /// Returns `true` if the resource can be modified with the specified
+    /// tokens, including its descendants if `subtree` is `true`.
+    pub(crate) fn check(&self, path: &str, tokens: &[String], subtree: bool) -> bool {
+        self.active_locks().iter().all(|lock| {
+            let applies = lock.covers(path) || (subtree && is_descendant(&lock.path, path));
+            !applies || tokens.contains(&lock.token)
+        })
+    }
+
+    /// Returns the locks that apply to the specified path.
+    pub(crate) fn discover(&self, path: &str) -> Vec<Lock> {
+        self.active_locks()
+            .iter()
+            .filter(|lock| lock.covers(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the locks of the resource and its descendants.
+    pub(crate) fn remove_subtree(&self, path: &str) {
+        self.active_locks()
+            .retain(|lock| lock.path != path && !is_descendant(&lock.path, path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_descendant() {
+        assert!(is_descendant("a/b", "a"));
+        assert!(is_descendant("a", ""));
+        assert!(!is_descendant("", ""));
+        assert!(!is_descendant("ab", "a"));
+        assert!(!is_descendant("a", "a"));
+    }
+
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 45);
+        assert!(token.starts_with("urn:uuid:"));
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn test_locks() {
+        const HOUR: Duration = Duration::from_secs(3600);
+
+        let manager = LockManager::default();
+        let lock = manager.lock("a", true, true, None, HOUR).unwrap();
+        assert!(manager.lock("a/b", false, false, None, HOUR).is_none());
+        assert!(manager.lock("", false, true, None, HOUR).is_none());
+        assert!(manager.lock("b", true, false, None, HOUR).is_some());
+
+        assert!(!manager.check("a/b", &[], false));
+        assert!(manager.check("a/b", std::slice::from_ref(&lock.token), false));
+        assert!(!manager.check("", &[], true));
+        assert!(manager.check("", &[], false));
+        assert_eq!(manager.discover("a/b").len(), 1);
+
+        assert!(!manager.unlock("a", "urn:uuid:other"));
+        assert!(manager.unlock("a/b", &lock.token));
+        assert!(manager.check("a/b", &[], false));
+
+        let shared = manager.lock("c", false, false, None, HOUR).unwrap();
+        assert!(manager.lock("c", false, false, None, HOUR).is_some());
+        assert!(manager.lock("c", true, false, None, HOUR).is_none());
+        assert!(manager
+            .refresh("c", &[shared.token], Duration::from_secs(0))
+            .is_some());
+        assert_eq!(manager.discover("c").len(), 1);
+
+        manager.remove_subtree("");
+        assert!(manager.discover("b").is_empty());
+    }
+}
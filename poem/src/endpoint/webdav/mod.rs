@@ -0,0 +1,1339 @@
+mod condition;
+mod lock;
+mod xml;
+
+use std::{
+    fmt::Write,
+    fs::Metadata,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use self::{
+    condition::{parse_if_header, Condition, State},
+    lock::{is_descendant, Lock, LockManager},
+    xml::{escape, PropFind, PropName, DAV_NS},
+};
+use super::{files::etag_value, static_common::guess_content_type, Files};
+use crate::{
+    http::{header, uri::Authority, HeaderMap, HeaderValue, StatusCode, Uri},
+    Endpoint, IntoResponse, Request, Response,
+};
+
+/// The characters that are percent-encoded in the path segments of a `href`.
+const HREF_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// The default maximum timeout of a lock.
+const DEFAULT_MAX_LOCK_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+const ALLOW: &str =
+    "OPTIONS, GET, HEAD, PUT, DELETE, MKCOL, COPY, MOVE, PROPFIND, PROPPATCH, LOCK, UNLOCK";
+
+/// The live properties returned for `allprop` and `propname` requests.
+const LIVE_PROPS: &[&str] = &[
+    "resourcetype",
+    "displayname",
+    "getcontentlength",
+    "getcontenttype",
+    "getlastmodified",
+    "getetag",
+    "supportedlock",
+    "lockdiscovery",
+];
+
+/// A resource of the directory, identified by its path relative to the root
+/// directory, using `/` as the separator.
+struct Resource {
+    path: String,
+    file_path: PathBuf,
+}
+
+/// WebDAV service backed by a local directory, with class 1 and 2
+/// compliance.
+///
+/// It supports the `GET`, `HEAD`, `PUT`, `DELETE`, `MKCOL`, `COPY`, `MOVE`,
+/// `PROPFIND`, `PROPPATCH`, `LOCK` and `UNLOCK` methods. `GET` and `HEAD`
+/// requests are served like [`Files`].
+///
+/// Only the live properties are supported, so `PROPPATCH` requests are always
+/// answered with `403 Forbidden` for each property. Locks are kept in memory
+/// and expire after [`WebDav::max_lock_timeout`] at most, and `PROPFIND`
+/// requests with an infinite depth are rejected.
+///
+/// The conditions of the `If` header are evaluated for every request against
+/// the locks and the entity tags of the files, and the request is answered
+/// with `412 Precondition Failed` if none of its lists is satisfied.
+///
+/// # Example
+///
+/// ```
+/// use poem::{endpoint::WebDav, Route};
+///
+/// let app = Route::new().nest("/dav", WebDav::new("/srv/shared"));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "webdav")))]
+pub struct WebDav {
+    path: PathBuf,
+    files: Files,
+    locks: LockManager,
+    max_lock_timeout: Duration,
+}
+
+impl WebDav {
+    /// Create new WebDav service for a specified base directory.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            files: Files::new(&path),
+            path,
+            locks: LockManager::default(),
+            max_lock_timeout: DEFAULT_MAX_LOCK_TIMEOUT,
+        }
+    }
+
+    /// Specifies the maximum timeout of a lock, which is also used for the
+    /// locks requested with an `Infinite` timeout or without a timeout.
+    ///
+    /// Default is one hour.
+    pub fn max_lock_timeout(self, timeout: Duration) -> Self {
+        Self {
+            max_lock_timeout: timeout,
+            ..self
+        }
+    }
+
+    fn resource(&self, path: &str) -> Result<Resource, StatusCode> {
+        let path = percent_encoding::percent_decode_str(path)
+            .decode_utf8()
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return Err(StatusCode::FORBIDDEN),
+                segment if segment.contains('\\') => return Err(StatusCode::FORBIDDEN),
+                segment => segments.push(segment),
+            }
+        }
+
+        let mut file_path = self.path.clone();
+        file_path.extend(&segments);
+        Ok(Resource {
+            path: segments.join("/"),
+            file_path,
+        })
+    }
+
+    fn check_locks(
+        &self,
+        headers: &HeaderMap,
+        path: &str,
+        subtree: bool,
+    ) -> Result<(), StatusCode> {
+        if self.locks.check(path, &submitted_tokens(headers), subtree) {
+            Ok(())
+        } else {
+            Err(StatusCode::LOCKED)
+        }
+    }
+
+    /// Evaluates the `If` header, which is satisfied if one of its lists is.
+    /// The resources of tagged lists that this endpoint does not serve are
+    /// treated like unmapped URLs, without locks or entity tags.
+    fn check_if_header(
+        &self,
+        req: &Request,
+        res: &Resource,
+        prefix: &str,
+    ) -> Result<(), StatusCode> {
+        let value = match req.headers().get("if") {
+            Some(value) => value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?,
+            None => return Ok(()),
+        };
+        let lists = parse_if_header(value).ok_or(StatusCode::BAD_REQUEST)?;
+
+        let satisfied = lists.iter().any(|list| {
+            let tagged = list.resource.map(|tag| {
+                let uri = tag.parse::<Uri>().ok()?;
+                let path = local_path(&uri, req.original_uri(), req.headers(), prefix)?;
+                self.resource(path).ok()
+            });
+            let res = match &tagged {
+                Some(tagged) => tagged.as_ref(),
+                None => Some(res),
+            };
+            list.conditions
+                .iter()
+                .all(|condition| self.matches(condition, res) != condition.not)
+        });
+        if satisfied {
+            Ok(())
+        } else {
+            Err(StatusCode::PRECONDITION_FAILED)
+        }
+    }
+
+    /// Returns `true` if the resource has the state of the condition, ignoring
+    /// `Not`.
+    fn matches(&self, condition: &Condition, res: Option<&Resource>) -> bool {
+        let res = match res {
+            Some(res) => res,
+            None => return false,
+        };
+        match condition.state {
+            State::Token(token) => self.locks.has_token(&res.path, token),
+            State::ETag(etag) => file_etag(&res.file_path)
+                .map(|value| value.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Checks the locks of the parent collection, which are required to add
+    /// or remove a member even if they only have a depth of `0`.
+    fn check_parent_locks(&self, headers: &HeaderMap, path: &str) -> Result<(), StatusCode> {
+        match path.rsplit_once('/') {
+            Some((parent, _)) => self.check_locks(headers, parent, false),
+            None if !path.is_empty() => self.check_locks(headers, "", false),
+            None => Ok(()),
+        }
+    }
+
+    fn propfind_response(
+        &self,
+        output: &mut String,
+        res: &Resource,
+        metadata: &Metadata,
+        propfind: &PropFind,
+        prefix: &str,
+    ) {
+        let href = href(prefix, &res.path, metadata.is_dir());
+        let _ = write!(output, "<D:response><D:href>{}</D:href>", escape(&href));
+
+        let mut found = String::new();
+        let mut not_found = String::new();
+        match propfind {
+            PropFind::PropName => {
+                for name in LIVE_PROPS {
+                    if metadata.is_dir() && is_file_prop(name) {
+                        continue;
+                    }
+                    PropName::dav(name).write_element(&mut found, "");
+                }
+            }
+            PropFind::AllProp => {
+                for name in LIVE_PROPS {
+                    if let Some(value) = self.live_prop(name, res, metadata, prefix) {
+                        PropName::dav(name).write_element(&mut found, &value);
+                    }
+                }
+            }
+            PropFind::Prop(names) => {
+                for name in names {
+                    let value = Some(name)
+                        .filter(|name| name.ns == DAV_NS)
+                        .and_then(|name| self.live_prop(&name.name, res, metadata, prefix));
+                    match value {
+                        Some(value) => name.write_element(&mut found, &value),
+                        None => name.write_element(&mut not_found, ""),
+                    }
+                }
+            }
+        }
+
+        for (props, status) in [(found, "200 OK"), (not_found, "404 Not Found")] {
+            if !props.is_empty() {
+                write_propstat(output, &props, status);
+            }
+        }
+        output.push_str("</D:response>");
+    }
+
+    /// Returns the value of a live property, as XML.
+    fn live_prop(
+        &self,
+        name: &str,
+        res: &Resource,
+        metadata: &Metadata,
+        prefix: &str,
+    ) -> Option<String> {
+        let is_dir = metadata.is_dir();
+        if is_dir && is_file_prop(name) {
+            return None;
+        }
+
+        match name {
+            "resourcetype" if is_dir => Some("<D:collection/>".to_string()),
+            "resourcetype" => Some(String::new()),
+            "displayname" => {
+                Some(escape(res.path.rsplit('/').next().unwrap_or_default()).into_owned())
+            }
+            "getcontentlength" => Some(metadata.len().to_string()),
+            "getcontenttype" => Some(
+                guess_content_type(&res.file_path, false)
+                    .and_then(|value| value.to_str().ok().map(|value| escape(value).into_owned()))
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            ),
+            "getlastmodified" => metadata.modified().ok().map(httpdate::fmt_http_date),
+            "getetag" => metadata
+                .modified()
+                .ok()
+                .and_then(|modified| etag_value(metadata.len(), modified))
+                .map(|etag| escape(&etag).into_owned()),
+            "supportedlock" => Some(
+                ["exclusive", "shared"]
+                    .iter()
+                    .map(|scope| {
+                        format!(
+                            "<D:lockentry><D:lockscope><D:{}/></D:lockscope>\
+                             <D:locktype><D:write/></D:locktype></D:lockentry>",
+                            scope
+                        )
+                    })
+                    .collect(),
+            ),
+            "lockdiscovery" => {
+                let mut output = String::new();
+                for lock in self.locks.discover(&res.path) {
+                    lock.write_active_lock(&mut output, &href(prefix, &lock.path, false));
+                }
+                Some(output)
+            }
+            _ => None,
+        }
+    }
+
+    async fn propfind(&self, req: Request, res: Resource, prefix: &str) -> Response {
+        let metadata = match res.file_path.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return StatusCode::NOT_FOUND.into(),
+        };
+        let depth_one = match req.headers().get("depth").map(HeaderValue::as_bytes) {
+            Some(b"0") => false,
+            Some(b"1") => true,
+            _ => {
+                return xml_response(
+                    StatusCode::FORBIDDEN,
+                    "<D:error xmlns:D=\"DAV:\"><D:propfind-finite-depth/></D:error>",
+                )
+            }
+        };
+        let body = match req.into_body().into_string().await {
+            Ok(body) => body,
+            Err(err) => return err.into_response(),
+        };
+        let propfind = match xml::parse_propfind(&body) {
+            Some(propfind) => propfind,
+            None => return StatusCode::BAD_REQUEST.into(),
+        };
+
+        let mut output = String::from("<D:multistatus xmlns:D=\"DAV:\">");
+        self.propfind_response(&mut output, &res, &metadata, &propfind, prefix);
+        if depth_one && metadata.is_dir() {
+            let read_dir = match res.file_path.read_dir() {
+                Ok(read_dir) => read_dir,
+                Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+            };
+            for entry in read_dir.flatten() {
+                let (name, metadata) = match (entry.file_name().into_string(), entry.metadata()) {
+                    (Ok(name), Ok(metadata)) => (name, metadata),
+                    _ => continue,
+                };
+                let child = Resource {
+                    path: join_path(&res.path, &name),
+                    file_path: entry.path(),
+                };
+                self.propfind_response(&mut output, &child, &metadata, &propfind, prefix);
+            }
+        }
+        output.push_str("</D:multistatus>");
+        xml_response(StatusCode::MULTI_STATUS, &output)
+    }
+
+    async fn proppatch(&self, req: Request, res: Resource, prefix: &str) -> Response {
+        let metadata = match res.file_path.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return StatusCode::NOT_FOUND.into(),
+        };
+        if let Err(status) = self.check_locks(req.headers(), &res.path, false) {
+            return status.into();
+        }
+        let body = match req.into_body().into_string().await {
+            Ok(body) => body,
+            Err(err) => return err.into_response(),
+        };
+        let names = match xml::parse_proppatch(&body) {
+            Some(names) => names,
+            None => return StatusCode::BAD_REQUEST.into(),
+        };
+
+        let mut props = String::new();
+        for name in names {
+            name.write_element(&mut props, "");
+        }
+        let mut output = format!(
+            "<D:multistatus xmlns:D=\"DAV:\"><D:response><D:href>{}</D:href>",
+            escape(&href(prefix, &res.path, metadata.is_dir()))
+        );
+        write_propstat(&mut output, &props, "403 Forbidden");
+        output.push_str("</D:response></D:multistatus>");
+        xml_response(StatusCode::MULTI_STATUS, &output)
+    }
+
+    async fn mkcol(&self, req: Request, res: Resource) -> Response {
+        if let Err(status) = self
+            .check_locks(req.headers(), &res.path, false)
+            .and_then(|_| self.check_parent_locks(req.headers(), &res.path))
+        {
+            return status.into();
+        }
+        match req.into_body().into_bytes().await {
+            Ok(body) if body.is_empty() => {}
+            Ok(_) => return StatusCode::UNSUPPORTED_MEDIA_TYPE.into(),
+            Err(err) => return err.into_response(),
+        }
+
+        if res.file_path.exists() {
+            return StatusCode::METHOD_NOT_ALLOWED.into();
+        }
+        match tokio::fs::create_dir(&res.file_path).await {
+            Ok(()) => StatusCode::CREATED.into(),
+            Err(err) if err.kind() == ErrorKind::NotFound => StatusCode::CONFLICT.into(),
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+        }
+    }
+
+    async fn put(&self, req: Request, res: Resource) -> Response {
+        if res.file_path.is_dir() {
+            return StatusCode::METHOD_NOT_ALLOWED.into();
+        }
+        if !is_parent_dir(&res.file_path) {
+            return StatusCode::CONFLICT.into();
+        }
+        if let Err(status) = self.check_locks(req.headers(), &res.path, false) {
+            return status.into();
+        }
+        let exists = res.file_path.exists();
+        if !exists {
+            if let Err(status) = self.check_parent_locks(req.headers(), &res.path) {
+                return status.into();
+            }
+        }
+
+        let mut file = match tokio::fs::File::create(&res.file_path).await {
+            Ok(file) => file,
+            Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+        };
+        let mut body = req.into_body().into_async_read();
+        if let Err(err) = tokio::io::copy(&mut body, &mut file).await {
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into();
+        }
+
+        if exists {
+            StatusCode::NO_CONTENT.into()
+        } else {
+            StatusCode::CREATED.into()
+        }
+    }
+
+    async fn delete(&self, req: Request, res: Resource) -> Response {
+        if res.path.is_empty() {
+            return StatusCode::FORBIDDEN.into();
+        }
+        let metadata = match res.file_path.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return StatusCode::NOT_FOUND.into(),
+        };
+        if let Err(status) = self
+            .check_locks(req.headers(), &res.path, true)
+            .and_then(|_| self.check_parent_locks(req.headers(), &res.path))
+        {
+            return status.into();
+        }
+
+        if let Err(err) = remove(&res.file_path, &metadata).await {
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into();
+        }
+        self.locks.remove_subtree(&res.path);
+        StatusCode::NO_CONTENT.into()
+    }
+
+    async fn copy_or_move(
+        &self,
+        req: Request,
+        res: Resource,
+        prefix: &str,
+        is_move: bool,
+    ) -> Response {
+        let metadata = match res.file_path.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return StatusCode::NOT_FOUND.into(),
+        };
+        let dest = match destination(&req, prefix) {
+            Ok(dest) => dest,
+            Err(status) => return status.into(),
+        };
+        let dest = match self.resource(&dest) {
+            Ok(dest) => dest,
+            Err(status) => return status.into(),
+        };
+        if dest.path.is_empty()
+            || dest.path == res.path
+            || is_descendant(&dest.path, &res.path)
+            || (is_move && res.path.is_empty())
+        {
+            return StatusCode::FORBIDDEN.into();
+        }
+        let depth_infinity = match req.headers().get("depth").map(HeaderValue::as_bytes) {
+            None | Some(b"infinity") => true,
+            Some(b"0") if !is_move => false,
+            _ => return StatusCode::BAD_REQUEST.into(),
+        };
+        let overwrite = req.headers().get("overwrite").map(HeaderValue::as_bytes) != Some(b"F");
+
+        if is_move {
+            if let Err(status) = self
+                .check_locks(req.headers(), &res.path, true)
+                .and_then(|_| self.check_parent_locks(req.headers(), &res.path))
+            {
+                return status.into();
+            }
+        }
+        if let Err(status) = self.check_locks(req.headers(), &dest.path, true) {
+            return status.into();
+        }
+        if !dest.file_path.exists() {
+            if let Err(status) = self.check_parent_locks(req.headers(), &dest.path) {
+                return status.into();
+            }
+        }
+
+        let dest_metadata = dest.file_path.metadata().ok();
+        if let Some(dest_metadata) = &dest_metadata {
+            if !overwrite {
+                return StatusCode::PRECONDITION_FAILED.into();
+            }
+            if let Err(err) = remove(&dest.file_path, dest_metadata).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into();
+            }
+            self.locks.remove_subtree(&dest.path);
+        }
+        if !is_parent_dir(&dest.file_path) {
+            return StatusCode::CONFLICT.into();
+        }
+
+        let result = if is_move {
+            let result = tokio::fs::rename(&res.file_path, &dest.file_path).await;
+            if result.is_ok() {
+                self.locks.remove_subtree(&res.path);
+            }
+            result
+        } else {
+            let (src, dst) = (res.file_path, dest.file_path);
+            let is_dir = metadata.is_dir();
+            match tokio::task::spawn_blocking(move || copy(&src, &dst, is_dir, depth_infinity))
+                .await
+            {
+                Ok(result) => result,
+                Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+            }
+        };
+
+        match result {
+            Ok(()) if dest_metadata.is_some() => StatusCode::NO_CONTENT.into(),
+            Ok(()) => StatusCode::CREATED.into(),
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+        }
+    }
+
+    async fn lock(&self, mut req: Request, res: Resource, prefix: &str) -> Response {
+        let tokens = submitted_tokens(req.headers());
+        let timeout = parse_timeout(req.headers()).map_or(self.max_lock_timeout, |timeout| {
+            timeout.min(self.max_lock_timeout)
+        });
+        let depth_infinity = match req.headers().get("depth").map(HeaderValue::as_bytes) {
+            None | Some(b"infinity") => true,
+            Some(b"0") => false,
+            _ => return StatusCode::BAD_REQUEST.into(),
+        };
+        let body = match req.take_body().into_string().await {
+            Ok(body) => body,
+            Err(err) => return err.into_response(),
+        };
+
+        // a request without body refreshes an existing lock
+        if body.trim().is_empty() {
+            return match self.locks.refresh(&res.path, &tokens, timeout) {
+                Some(lock) => self.lock_response(StatusCode::OK, &lock, prefix),
+                None => StatusCode::PRECONDITION_FAILED.into(),
+            };
+        }
+
+        let info = match xml::parse_lockinfo(&body) {
+            Some(info) => info,
+            None => return StatusCode::BAD_REQUEST.into(),
+        };
+        let exists = res.file_path.exists();
+        if !exists {
+            if !is_parent_dir(&res.file_path) {
+                return StatusCode::CONFLICT.into();
+            }
+            if let Err(status) = self.check_parent_locks(req.headers(), &res.path) {
+                return status.into();
+            }
+        }
+        let lock = match self.locks.lock(
+            &res.path,
+            info.exclusive,
+            depth_infinity,
+            info.owner,
+            timeout,
+        ) {
+            Some(lock) => lock,
+            None => return StatusCode::LOCKED.into(),
+        };
+
+        // locking an unmapped URL creates an empty resource
+        if !exists {
+            if let Err(err) = tokio::fs::File::create(&res.file_path).await {
+                self.locks.unlock(&res.path, &lock.token);
+                return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into();
+            }
+        }
+
+        let mut resp = self.lock_response(
+            if exists {
+                StatusCode::OK
+            } else {
+                StatusCode::CREATED
+            },
+            &lock,
+            prefix,
+        );
+        if let Ok(value) = HeaderValue::from_str(&format!("<{}>", lock.token)) {
+            resp.headers_mut().insert("lock-token", value);
+        }
+        resp
+    }
+
+    fn lock_response(&self, status: StatusCode, lock: &Lock, prefix: &str) -> Response {
+        let mut output = String::from("<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery>");
+        lock.write_active_lock(&mut output, &self.lock_root(lock, prefix));
+        output.push_str("</D:lockdiscovery></D:prop>");
+        xml_response(status, &output)
+    }
+
+    fn lock_root(&self, lock: &Lock, prefix: &str) -> String {
+        let mut file_path = self.path.clone();
+        file_path.extend(lock.path.split('/').filter(|segment| !segment.is_empty()));
+        href(prefix, &lock.path, file_path.is_dir())
+    }
+
+    fn unlock(&self, req: Request, res: Resource) -> Response {
+        let token = req
+            .headers()
+            .get("lock-token")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().strip_prefix('<')?.strip_suffix('>'));
+        match token {
+            Some(token) if self.locks.unlock(&res.path, token) => StatusCode::NO_CONTENT.into(),
+            Some(_) => StatusCode::CONFLICT.into(),
+            None => StatusCode::BAD_REQUEST.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for WebDav {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let res = match self.resource(req.uri().path()) {
+            Ok(res) => res,
+            Err(status) => return status.into(),
+        };
+        let prefix = href_prefix(req.original_uri(), req.uri());
+        if let Err(status) = self.check_if_header(&req, &res, &prefix) {
+            return status.into();
+        }
+
+        match req.method().as_str() {
+            "OPTIONS" => Response::builder()
+                .header("dav", "1, 2")
+                .header(header::ALLOW, ALLOW)
+                .header("ms-author-via", "DAV")
+                .finish(),
+            "GET" | "HEAD" => self.files.call(req).await,
+            "PROPFIND" => self.propfind(req, res, &prefix).await,
+            "PROPPATCH" => self.proppatch(req, res, &prefix).await,
+            "MKCOL" => self.mkcol(req, res).await,
+            "PUT" => self.put(req, res).await,
+            "DELETE" => self.delete(req, res).await,
+            "COPY" => self.copy_or_move(req, res, &prefix, false).await,
+            "MOVE" => self.copy_or_move(req, res, &prefix, true).await,
+            "LOCK" => self.lock(req, res, &prefix).await,
+            "UNLOCK" => self.unlock(req, res),
+            _ => Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, ALLOW)
+                .finish(),
+        }
+    }
+}
+
+/// Returns the path that the endpoint is nested at, which is the prefix of the
+/// `href` of every resource.
+fn href_prefix(original_uri: &Uri, uri: &Uri) -> String {
+    original_uri
+        .path()
+        .trim_end_matches('/')
+        .strip_suffix(uri.path().trim_end_matches('/'))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn href(prefix: &str, path: &str, is_dir: bool) -> String {
+    let mut href = prefix.to_string();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        href.push('/');
+        href.extend(utf8_percent_encode(segment, HREF_ENCODE_SET));
+    }
+    if is_dir || href.is_empty() {
+        href.push('/');
+    }
+    href
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Returns `true` if the property is only defined for files.
+fn is_file_prop(name: &str) -> bool {
+    matches!(name, "getcontentlength" | "getcontenttype" | "getetag")
+}
+
+fn is_parent_dir(path: &Path) -> bool {
+    path.parent().map(Path::is_dir).unwrap_or_default()
+}
+
+fn write_propstat(output: &mut String, props: &str, status: &str) {
+    let _ = write!(
+        output,
+        "<D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 {}</D:status></D:propstat>",
+        props, status
+    );
+}
+
+fn xml_response(status: StatusCode, body: &str) -> Response {
+    Response::builder()
+        .status(status)
+        .content_type("application/xml; charset=utf-8")
+        .body(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>{}",
+            body
+        ))
+}
+
+/// Returns the lock tokens submitted in the `If` header.
+fn submitted_tokens(headers: &HeaderMap) -> Vec<String> {
+    let lists = match headers
+        .get("if")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_if_header)
+    {
+        Some(lists) => lists,
+        None => return Vec::new(),
+    };
+    lists
+        .iter()
+        .flat_map(|list| &list.conditions)
+        .filter_map(|condition| match condition.state {
+            State::Token(token) => Some(token.to_string()),
+            State::ETag(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the quoted entity tag of a file, directories have no entity tag.
+fn file_etag(path: &Path) -> Option<String> {
+    let metadata = path.metadata().ok().filter(Metadata::is_file)?;
+    etag_value(metadata.len(), metadata.modified().ok()?)
+}
+
+/// Parses the `Timeout` header of a `LOCK` request, `None` means that the
+/// client did not request a timeout or requested an `Infinite` one.
+fn parse_timeout(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("timeout")?.to_str().ok()?;
+    value
+        .split(',')
+        .map(str::trim)
+        .find_map(|value| match value {
+            "Infinite" => Some(None),
+            _ => value
+                .strip_prefix("Second-")
+                .and_then(|secs| secs.parse().ok())
+                .map(|secs| Some(Duration::from_secs(secs))),
+        })
+        .flatten()
+}
+
+/// Returns the path of the `Destination` header relative to the prefix, a
+/// destination on another host or outside of the prefix is on another server.
+fn destination(req: &Request, prefix: &str) -> Result<String, StatusCode> {
+    let uri = req
+        .headers()
+        .get("destination")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Uri>().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    local_path(&uri, req.original_uri(), req.headers(), prefix)
+        .map(ToString::to_string)
+        .ok_or(StatusCode::BAD_GATEWAY)
+}
+
+/// Returns the path of a URI relative to the prefix, or `None` if the URI is
+/// on another server or outside of the prefix.
+fn local_path<'a>(
+    uri: &'a Uri,
+    req_uri: &Uri,
+    headers: &HeaderMap,
+    prefix: &str,
+) -> Option<&'a str> {
+    if !is_same_server(uri, req_uri, headers) {
+        return None;
+    }
+    match uri.path().strip_prefix(prefix) {
+        Some(path) if path.is_empty() || path.starts_with('/') => Some(path),
+        _ => None,
+    }
+}
+
+/// Returns `true` if a URI is relative or has the scheme and authority of the
+/// request, which are taken from the `Host` header if the request URI is not
+/// absolute. Both `http` and `https` are accepted when the scheme of the
+/// request is unknown.
+fn is_same_server(uri: &Uri, req_uri: &Uri, headers: &HeaderMap) -> bool {
+    let authority = match uri.authority() {
+        Some(authority) => authority,
+        None => return uri.scheme().is_none(),
+    };
+    let scheme = match (uri.scheme_str(), req_uri.scheme_str()) {
+        (Some(scheme), Some(req_scheme)) if scheme.eq_ignore_ascii_case(req_scheme) => scheme,
+        (Some(scheme), None) if scheme == "http" || scheme == "https" => scheme,
+        _ => return false,
+    };
+    let req_authority = match req_uri.authority() {
+        Some(req_authority) => req_authority.clone(),
+        None => match headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Authority>().ok())
+        {
+            Some(req_authority) => req_authority,
+            None => return false,
+        },
+    };
+    let port = |authority: &Authority| {
+        authority
+            .port_u16()
+            .or_else(|| match scheme.to_ascii_lowercase().as_str() {
+                "http" => Some(80),
+                "https" => Some(443),
+                _ => None,
+            })
+    };
+    authority.host().eq_ignore_ascii_case(req_authority.host())
+        && port(authority) == port(&req_authority)
+}
+
+async fn remove(path: &Path, metadata: &Metadata) -> std::io::Result<()> {
+    if metadata.is_dir() {
+        tokio::fs::remove_dir_all(path).await
+    } else {
+        tokio::fs::remove_file(path).await
+    }
+}
+
+fn copy(src: &Path, dst: &Path, is_dir: bool, recursive: bool) -> std::io::Result<()> {
+    if !is_dir {
+        return std::fs::copy(src, dst).map(|_| ());
+    }
+
+    std::fs::create_dir(dst)?;
+    if recursive {
+        for entry in src.read_dir()? {
+            let entry = entry?;
+            let is_dir = entry.file_type()?.is_dir();
+            copy(&entry.path(), &dst.join(entry.file_name()), is_dir, true)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    fn create_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("poem-webdav-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/a b.txt"), "hello").unwrap();
+        dir
+    }
+
+    async fn request(
+        ep: &impl Endpoint<Output = Response>,
+        method: &str,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> Response {
+        let mut req = Request::builder()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri(uri.parse().unwrap());
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        ep.call(req.body(body.to_string())).await
+    }
+
+    #[tokio::test]
+    async fn options() {
+        let dir = create_test_dir("options");
+        let app = WebDav::new(&dir);
+        let resp = request(&app, "OPTIONS", "/", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("dav").unwrap(), "1, 2");
+
+        let resp = request(&app, "PATCH", "/", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let resp = request(&app, "GET", "/../secret", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn propfind() {
+        let dir = create_test_dir("propfind");
+        let app = WebDav::new(&dir);
+
+        let resp = request(&app, "PROPFIND", "/docs", &[("depth", "1")], "").await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+        assert_eq!(resp.content_type(), Some("application/xml; charset=utf-8"));
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.contains("<D:href>/docs/</D:href>"));
+        assert!(body.contains("<D:resourcetype><D:collection/></D:resourcetype>"));
+        assert!(body.contains("<D:href>/docs/a%20b.txt</D:href>"));
+        assert!(body.contains("<D:getcontentlength>5</D:getcontentlength>"));
+        assert!(body.contains("<D:getcontenttype>text/plain</D:getcontenttype>"));
+
+        let resp = request(
+            &app,
+            "PROPFIND",
+            "/docs/a%20b.txt",
+            &[("depth", "0")],
+            r#"<D:propfind xmlns:D="DAV:"><D:prop><D:getcontentlength/><R:author xmlns:R="urn:r"/></D:prop></D:propfind>"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.contains(
+            "<D:propstat><D:prop><D:getcontentlength>5</D:getcontentlength></D:prop>\
+             <D:status>HTTP/1.1 200 OK</D:status></D:propstat>"
+        ));
+        assert!(body.contains(
+            "<D:propstat><D:prop><author xmlns=\"urn:r\"/></D:prop>\
+             <D:status>HTTP/1.1 404 Not Found</D:status></D:propstat>"
+        ));
+
+        let resp = request(&app, "PROPFIND", "/", &[("depth", "infinity")], "").await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = request(&app, "PROPFIND", "/missing", &[("depth", "0")], "").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = request(&app, "PROPFIND", "/", &[("depth", "0")], "<invalid").await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn write_methods() {
+        let dir = create_test_dir("write");
+        let app = WebDav::new(&dir);
+
+        let resp = request(&app, "MKCOL", "/new", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = request(&app, "MKCOL", "/new", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let resp = request(&app, "MKCOL", "/missing/new", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let resp = request(&app, "PUT", "/new/file.txt", &[], "abc").await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = request(&app, "PUT", "/new/file.txt", &[], "abcd").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = request(&app, "GET", "/new/file.txt", &[], "").await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "abcd");
+
+        let resp = request(
+            &app,
+            "COPY",
+            "/new",
+            &[
+                ("destination", "http://localhost/copy"),
+                ("host", "localhost"),
+            ],
+            "",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("copy/file.txt")).unwrap(),
+            "abcd"
+        );
+
+        let resp = request(
+            &app,
+            "MOVE",
+            "/copy/file.txt",
+            &[("destination", "/docs/a%20b.txt"), ("overwrite", "F")],
+            "",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        let resp = request(
+            &app,
+            "MOVE",
+            "/copy/file.txt",
+            &[("destination", "/docs/a%20b.txt")],
+            "",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(!dir.join("copy/file.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("docs/a b.txt")).unwrap(),
+            "abcd"
+        );
+        let resp = request(&app, "MOVE", "/new", &[("destination", "/new/a")], "").await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = request(&app, "MOVE", "/new", &[("destination", "/../a")], "").await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = request(&app, "MOVE", "/new", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = request(
+            &app,
+            "MOVE",
+            "/new",
+            &[("destination", "http://other/a"), ("host", "localhost")],
+            "",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+
+        let resp = request(&app, "DELETE", "/new", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(!dir.join("new").exists());
+        let resp = request(&app, "DELETE", "/new", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = request(
+            &app,
+            "PROPPATCH",
+            "/docs",
+            &[],
+            r#"<D:propertyupdate xmlns:D="DAV:"><D:set><D:prop><D:displayname>x</D:displayname></D:prop></D:set></D:propertyupdate>"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.contains("<D:status>HTTP/1.1 403 Forbidden</D:status>"));
+    }
+
+    #[tokio::test]
+    async fn locks() {
+        let dir = create_test_dir("locks");
+        let app = WebDav::new(&dir);
+        let lockinfo = r#"<D:lockinfo xmlns:D="DAV:"><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype><D:owner>me</D:owner></D:lockinfo>"#;
+
+        let resp = request(&app, "LOCK", "/docs", &[("timeout", "Second-60")], lockinfo).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let token = resp
+            .headers()
+            .get("lock-token")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.contains("<D:depth>infinity</D:depth>"));
+        assert!(body.contains("<D:owner>me</D:owner>"));
+        assert!(body.contains("<D:timeout>Second-60</D:timeout>"));
+        assert!(body.contains("<D:lockroot><D:href>/docs/</D:href></D:lockroot>"));
+
+        let resp = request(&app, "LOCK", "/docs/a%20b.txt", &[], lockinfo).await;
+        assert_eq!(resp.status(), StatusCode::LOCKED);
+        let resp = request(&app, "PUT", "/docs/a%20b.txt", &[], "abc").await;
+        assert_eq!(resp.status(), StatusCode::LOCKED);
+        let if_header = format!("({})", token);
+        let resp = request(&app, "PUT", "/docs/a%20b.txt", &[("if", &if_header)], "abc").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = request(
+            &app,
+            "LOCK",
+            "/docs",
+            &[("if", &if_header), ("timeout", "Infinite")],
+            "",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.contains("<D:timeout>Second-3600</D:timeout>"));
+        let resp = request(&app, "LOCK", "/docs", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let resp = request(
+            &app,
+            "UNLOCK",
+            "/docs",
+            &[("lock-token", "<urn:uuid:x>")],
+            "",
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = request(&app, "UNLOCK", "/docs", &[("lock-token", &token)], "").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = request(&app, "DELETE", "/docs/a%20b.txt", &[], "").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = request(&app, "LOCK", "/new.txt", &[("depth", "0")], lockinfo).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert_eq!(std::fs::read(dir.join("new.txt")).unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn if_header() {
+        let dir = create_test_dir("if-header");
+        let app = WebDav::new(&dir);
+        let etag = file_etag(&dir.join("docs/a b.txt")).unwrap();
+        let weak_etag = format!("W/{}", etag);
+        let propfind = |if_header: String| {
+            let app = &app;
+            async move {
+                let headers = [
+                    ("if", if_header.as_str()),
+                    ("host", "localhost"),
+                    ("depth", "0"),
+                ];
+                request(app, "PROPFIND", "/docs/a%20b.txt", &headers, "")
+                    .await
+                    .status()
+            }
+        };
+
+        for if_header in [
+            format!("([{}])", etag),
+            format!("([{}])", weak_etag),
+            format!("([\"x\"]) ([{}])", etag),
+            "(Not [\"x\"])".to_string(),
+            "(Not <DAV:no-lock>)".to_string(),
+            format!("</docs/a%20b.txt> ([{}])", etag),
+            format!("<http://localhost/docs/a%20b.txt> ([{}])", etag),
+            format!(
+                "</docs/a%20b.txt> ([\"x\"]) </docs/missing.txt> (Not [{}])",
+                etag
+            ),
+            format!("<http://other/docs/a%20b.txt> (Not [{}])", etag),
+        ] {
+            assert_eq!(
+                propfind(if_header.clone()).await,
+                StatusCode::MULTI_STATUS,
+                "{}",
+                if_header
+            );
+        }
+
+        for if_header in [
+            "([\"x\"])".to_string(),
+            format!("(Not [{}])", etag),
+            format!("([{}] <DAV:no-lock>)", etag),
+            format!("</docs/missing.txt> ([{}])", etag),
+            format!("<http://other/docs/a%20b.txt> ([{}])", etag),
+            format!("</docs> ([{}])", etag),
+        ] {
+            assert_eq!(
+                propfind(if_header.clone()).await,
+                StatusCode::PRECONDITION_FAILED,
+                "{}",
+                if_header
+            );
+        }
+        assert_eq!(
+            propfind("<urn:uuid:1>".to_string()).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        // the state token of a lock matches the resources in its scope
+        let lockinfo = r#"<D:lockinfo xmlns:D="DAV:"><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockinfo>"#;
+        let resp = request(&app, "LOCK", "/docs", &[], lockinfo).await;
+        let token = resp
+            .headers()
+            .get("lock-token")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            propfind("(<urn:uuid:x>)".to_string()).await,
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(
+            propfind(format!("(Not {})", token)).await,
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(
+            propfind(format!("({})", token)).await,
+            StatusCode::MULTI_STATUS
+        );
+        assert_eq!(
+            propfind(format!("</docs> ({} [{}])", token, etag)).await,
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(
+            propfind(format!("</docs> ({})", token)).await,
+            StatusCode::MULTI_STATUS
+        );
+
+        // a failed condition does not change the resource
+        std::fs::write(dir.join("docs/a b.txt"), "hi").unwrap();
+        let if_header = format!("({} [{}])", token, etag);
+        let resp = request(&app, "PUT", "/docs/a%20b.txt", &[("if", &if_header)], "abc").await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(std::fs::read(dir.join("docs/a b.txt")).unwrap(), b"hi");
+    }
+
+    #[tokio::test]
+    async fn parent_locks() {
+        let dir = create_test_dir("parent-locks");
+        let app = WebDav::new(&dir).max_lock_timeout(Duration::from_secs(30));
+        let lockinfo = r#"<D:lockinfo xmlns:D="DAV:"><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockinfo>"#;
+
+        let resp = request(
+            &app,
+            "LOCK",
+            "/docs",
+            &[("depth", "0"), ("timeout", "Second-60")],
+            lockinfo,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let if_header = format!(
+            "</docs> ({})",
+            resp.headers().get("lock-token").unwrap().to_str().unwrap()
+        );
+        let body = resp.into_body().into_string().await.unwrap();
+        assert!(body.contains("<D:timeout>Second-30</D:timeout>"));
+
+        // the members of the collection are not locked
+        let resp = request(&app, "PUT", "/docs/a%20b.txt", &[], "abc").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        // adding or removing a member requires the lock of the collection
+        for (method, uri, headers) in [
+            ("PUT", "/docs/new.txt", &[][..]),
+            ("MKCOL", "/docs/new", &[]),
+            ("DELETE", "/docs/a%20b.txt", &[]),
+            ("MOVE", "/docs/a%20b.txt", &[("destination", "/c.txt")]),
+            ("COPY", "/docs/a%20b.txt", &[("destination", "/docs/c.txt")]),
+        ] {
+            let resp = request(&app, method, uri, headers, "").await;
+            assert_eq!(resp.status(), StatusCode::LOCKED, "{} {}", method, uri);
+        }
+
+        let headers = [("if", if_header.as_str())];
+        let resp = request(&app, "PUT", "/docs/new.txt", &headers, "abc").await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = request(&app, "MKCOL", "/docs/new", &headers, "").await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = request(&app, "DELETE", "/docs/new.txt", &headers, "").await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(
+            href_prefix(&Uri::from_static("/dav/a/b"), &Uri::from_static("/a/b")),
+            "/dav"
+        );
+        assert_eq!(
+            href_prefix(&Uri::from_static("/dav/"), &Uri::from_static("/")),
+            "/dav"
+        );
+        assert_eq!(href("/dav", "", true), "/dav/");
+        assert_eq!(href("", "a b/c", false), "/a%20b/c");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "if",
+            HeaderValue::from_static(
+                "</dav/a> (<urn:uuid:1> [\"etag\"]) (Not <opaquelocktoken:2>)",
+            ),
+        );
+        headers.insert("timeout", HeaderValue::from_static("Second-x, Second-10"));
+        assert_eq!(
+            submitted_tokens(&headers),
+            vec!["urn:uuid:1".to_string(), "opaquelocktoken:2".to_string()]
+        );
+        assert_eq!(parse_timeout(&headers), Some(Duration::from_secs(10)));
+
+        let req = Request::builder()
+            .header("host", "a")
+            .header("destination", "http://a/dav/b")
+            .finish();
+        assert_eq!(destination(&req, "/dav").unwrap(), "/b");
+        assert_eq!(destination(&req, "/other"), Err(StatusCode::BAD_GATEWAY));
+    }
+
+    #[test]
+    fn test_is_same_server() {
+        let mut headers = HeaderMap::new();
+        let relative = Uri::from_static("/a");
+        let absolute = Uri::from_static("http://example.com/a");
+        assert!(is_same_server(&relative, &relative, &headers));
+        assert!(!is_same_server(&absolute, &relative, &headers));
+
+        headers.insert(header::HOST, HeaderValue::from_static("Example.com"));
+        for (uri, same) in [
+            ("http://example.com/a", true),
+            ("http://example.com:80/a", true),
+            ("https://example.com/a", true),
+            ("http://example.com:8080/a", false),
+            ("http://other.com/a", false),
+            ("ftp://example.com/a", false),
+        ] {
+            let uri = uri.parse::<Uri>().unwrap();
+            assert_eq!(is_same_server(&uri, &relative, &headers), same, "{}", uri);
+        }
+
+        let req_uri = Uri::from_static("https://example.com:8443/a");
+        for (uri, same) in [
+            ("https://example.com:8443/b", true),
+            ("http://example.com:8443/b", false),
+            ("https://example.com/b", false),
+        ] {
+            let uri = uri.parse::<Uri>().unwrap();
+            assert_eq!(is_same_server(&uri, &req_uri, &headers), same, "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::{borrow::Cow, fmt::Write};
+
+use roxmltree::{Document, Node};
+
+pub(crate) const DAV_NS: &str = "DAV:";
+
+/// The qualified name of a property.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct PropName {
+    pub(crate) ns: String,
+    pub(crate) name: String,
+}
+
+impl PropName {
+    pub(crate) fn dav(name: &str) -> Self {
+        Self {
+            ns: DAV_NS.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn from_node(node: Node) -> Self {
+        Self {
+            ns: node.tag_name().namespace().unwrap_or_default().to_string(),
+            name: node.tag_name().name().to_string(),
+        }
+    }
+
+    /// Writes an element with the name of this property and the specified
+    /// content.
+    pub(crate) fn write_element(&self, output: &mut String, content: &str) {
+        let (open, close) = if self.ns == DAV_NS {
+            (format!("D:{}", self.name), format!("D:{}", self.name))
+        } else if self.ns.is_empty() {
+            (format!("{} xmlns=\"\"", self.name), self.name.to_string())
+        } else {
+            (
+                format!("{} xmlns=\"{}\"", self.name, escape(&self.ns)),
+                self.name.to_string(),
+            )
+        };
+        if content.is_empty() {
+            let _ = write!(output, "<{}/>", open);
+        } else {
+            let _ = write!(output, "<{}>{}</{}>", open, content, close);
+        }
+    }
+}
+
+/// The body of a `PROPFIND` request.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum PropFind {
+    AllProp,
+    PropName,
+    Prop(Vec<PropName>),
+}
+
+/// The scope of a lock requested by a `LOCK` request.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct LockInfo {
+    pub(crate) exclusive: bool,
+    /// The content of the `owner` element, serialized as XML.
+    pub(crate) owner: Option<String>,
+}
+
+fn is_dav_element(node: &Node, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(DAV_NS)
+        && node.tag_name().name() == name
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is_dav_element(child, name))
+}
+
+fn root<'a, 'input>(doc: &'a Document<'input>, name: &str) -> Option<Node<'a, 'input>> {
+    Some(doc.root_element()).filter(|node| is_dav_element(node, name))
+}
+
+/// Parses the body of a `PROPFIND` request, an empty body is an `allprop`
+/// request.
+pub(crate) fn parse_propfind(body: &str) -> Option<PropFind> {
+    if body.trim().is_empty() {
+        return Some(PropFind::AllProp);
+    }
+
+    let doc = Document::parse(body).ok()?;
+    let propfind = root(&doc, "propfind")?;
+    if child(propfind, "allprop").is_some() {
+        Some(PropFind::AllProp)
+    } else if child(propfind, "propname").is_some() {
+        Some(PropFind::PropName)
+    } else {
+        let prop = child(propfind, "prop")?;
+        Some(PropFind::Prop(
+            prop.children()
+                .filter(Node::is_element)
+                .map(PropName::from_node)
+                .collect(),
+        ))
+    }
+}
+
+/// Parses the body of a `PROPPATCH` request, and returns the names of the
+/// properties to set or remove.
+pub(crate) fn parse_proppatch(body: &str) -> Option<Vec<PropName>> {
+    let doc = Document::parse(body).ok()?;
+    let update = root(&doc, "propertyupdate")?;
+    Some(
+        update
+            .children()
+            .filter(|node| is_dav_element(node, "set") || is_dav_element(node, "remove"))
+            .filter_map(|node| child(node, "prop"))
+            .flat_map(|prop| prop.children().filter(Node::is_element))
+            .map(PropName::from_node)
+            .collect(),
+    )
+}
+
+/// Parses the body of a `LOCK` request that creates a lock.
+pub(crate) fn parse_lockinfo(body: &str) -> Option<LockInfo> {
+    let doc = Document::parse(body).ok()?;
+    let lockinfo = root(&doc, "lockinfo")?;
+    let scope = child(lockinfo, "lockscope")?;
+    let exclusive = if child(scope, "exclusive").is_some() {
+        true
+    } else if child(scope, "shared").is_some() {
+        false
+    } else {
+        return None;
+    };
+    child(child(lockinfo, "locktype")?, "write")?;
+
+    let owner = child(lockinfo, "owner").map(|owner| {
+        let mut output = String::new();
+        for node in owner.children() {
+            serialize_node(node, &mut output);
+        }
+        output
+    });
+    Some(LockInfo { exclusive, owner })
+}
+
+/// Serializes an XML node, declaring the namespace on every element so that
+/// it can be embedded in any document.
+fn serialize_node(node: Node, output: &mut String) {
+    if node.is_text() {
+        output.push_str(&escape(node.text().unwrap_or_default()));
+    } else if node.is_element() {
+        let name = node.tag_name().name();
+        let ns = node.tag_name().namespace().unwrap_or_default();
+        let _ = write!(output, "<{} xmlns=\"{}\"", name, escape(ns));
+        for attr in node.attributes() {
+            if attr.namespace().is_none() {
+                let _ = write!(output, " {}=\"{}\"", attr.name(), escape(attr.value()));
+            }
+        }
+        output.push('>');
+        for child in node.children() {
+            serialize_node(child, output);
+        }
+        let _ = write!(output, "</{}>", name);
+    }
+}
+
+/// Escapes the special characters of XML.
+pub(crate) fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(&['<', '>', '&', '"', '\''][..]) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut output = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '&' => output.push_str("&amp;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            c => output.push(c),
+        }
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_propfind() {
+        assert_eq!(parse_propfind(""), Some(PropFind::AllProp));
+        assert_eq!(
+            parse_propfind(r#"<?xml version="1.0"?><propfind xmlns="DAV:"><allprop/></propfind>"#),
+            Some(PropFind::AllProp)
+        );
+        assert_eq!(
+            parse_propfind(r#"<D:propfind xmlns:D="DAV:"><D:propname/></D:propfind>"#),
+            Some(PropFind::PropName)
+        );
+        assert_eq!(
+            parse_propfind(
+                r#"<D:propfind xmlns:D="DAV:"><D:prop xmlns:R="http://example.com/">
+                    <D:getetag/><R:author/>
+                </D:prop></D:propfind>"#
+            ),
+            Some(PropFind::Prop(vec![
+                PropName::dav("getetag"),
+                PropName {
+                    ns: "http://example.com/".to_string(),
+                    name: "author".to_string()
+                }
+            ]))
+        );
+        assert_eq!(parse_propfind("<propfind/>"), None);
+        assert_eq!(parse_propfind("<D:propfind"), None);
+    }
+
+    #[test]
+    fn test_parse_lockinfo() {
+        assert_eq!(
+            parse_lockinfo(
+                r#"<D:lockinfo xmlns:D='DAV:'>
+                    <D:lockscope><D:exclusive/></D:lockscope>
+                    <D:locktype><D:write/></D:locktype>
+                    <D:owner><D:href>http://example.org/~ejw/</D:href></D:owner>
+                </D:lockinfo>"#
+            ),
+            Some(LockInfo {
+                exclusive: true,
+                owner: Some(r#"<href xmlns="DAV:">http://example.org/~ejw/</href>"#.to_string()),
+            })
+        );
+        assert_eq!(
+            parse_lockinfo(
+                r#"<lockinfo xmlns="DAV:"><lockscope><shared/></lockscope><locktype><write/></locktype></lockinfo>"#
+            ),
+            Some(LockInfo {
+                exclusive: false,
+                owner: None,
+            })
+        );
+        assert_eq!(
+            parse_lockinfo(r#"<lockinfo xmlns="DAV:"><lockscope><shared/></lockscope></lockinfo>"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("abc"), "abc");
+        assert_eq!(escape("<a & 'b'>"), "&lt;a &amp; &apos;b&apos;&gt;");
+    }
+}
@@ -1,7 +1,8 @@
 //! Some common error types.
 
 use std::{
-    fmt::{Debug, Display},
+    fmt::{self, Debug, Display, Formatter},
+    panic::Location,
     string::FromUtf8Error,
 };
 
@@ -574,6 +575,52 @@ impl IntoResponse for UpgradeError {
     }
 }
 
+/// A possible error value when adding a route.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RouteError {
+    /// The path is not a valid pattern.
+    InvalidPath {
+        /// The path pattern.
+        path: String,
+        /// The location where the route was added.
+        location: &'static Location<'static>,
+    },
+
+    /// The path conflicts with a route that was added earlier.
+    Conflict {
+        /// The path pattern.
+        path: String,
+        /// The location where the route was added.
+        location: &'static Location<'static>,
+        /// The path pattern of the existing route.
+        existing_path: String,
+        /// The location where the existing route was added.
+        existing_location: &'static Location<'static>,
+    },
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::InvalidPath { path, location } => {
+                write!(f, "invalid path `{}` at {}", path, location)
+            }
+            RouteError::Conflict {
+                path,
+                location,
+                existing_path,
+                existing_location,
+            } => write!(
+                f,
+                "path `{}` at {} conflicts with `{}` at {}",
+                path, location, existing_path, existing_location
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,18 +37,24 @@
 //! |------------------|--------------------------------|
 //! |compression  | Support decompress request body and compress response body |
 //! |cookie            | Support for Cookie             |
+//! |csrf              | Support for CSRF protection    |
+//! |embed             | Support for serve files compiled into the binary |
 //! |multipart         | Support for Multipart          |
 //! |native-tls        | Support for HTTP server over TLS with [`native-tls`](https://crates.io/crates/native-tls)  |
 //! |opentelemetry     | Support for opentelemetry    |
 //! |prometheus        | Support for Prometheus       |
+//! |redis-ratelimit   | Support for RedisRateLimitStore |
 //! |redis-session     | Support for RedisSession     |
+//! |request-id        | Support for request ids    |
 //! |rustls            | Support for HTTP server over TLS with [`rustls`](https://crates.io/crates/rustls)  |
+//! |security-headers  | Support for security headers with CSP nonces |
 //! |session           | Support for session    |
 //! |sse               | Support Server-Sent Events (SSE)       |
 //! |staticfiles       | Support for serve static files       |
 //! |tempfile          | Support for [`tempfile`](https://crates.io/crates/tempfile) |
 //! |template          | Support for [`askama`](https://crates.io/crates/askama)       |
 //! |tower-compat      | Adapters for `tower::Layer` and `tower::Service`. |
+//! |webdav            | Support for serve a directory over WebDAV |
 //! |websocket         | Support for WebSocket          |
 
 #![doc(html_favicon_url = "https://poem.rs/assets/favicon.ico")]
@@ -76,6 +82,8 @@ mod request;
 mod response;
 mod route;
 mod server;
+#[cfg(any(feature = "webdav", feature = "request-id"))]
+mod uuid;
 
 pub use addr::Addr;
 pub use async_trait::async_trait;
@@ -87,7 +95,13 @@ pub use poem_derive::handler;
 pub use request::{OnUpgrade, Request, RequestBuilder, RequestParts, Upgraded};
 pub use response::{Response, ResponseBuilder, ResponseParts};
 pub use route::{
-    connect, delete, get, head, options, patch, post, put, trace, Route, RouteDomain, RouteMethod,
+    connect, delete, get, head, options, patch, post, put, trace, Route, RouteAccept,
+    RouteContentType, RouteDomain, RouteHeader, RouteMethod,
 };
 pub use server::Server;
 pub use web::{FromRequest, IntoResponse, RequestBody};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::web::typed_path::{parse_path_param, write_path_param};
+}
@@ -0,0 +1,652 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use futures_util::future::{FutureExt, Shared};
+use headers::{Age, CacheControl, HeaderMapExt};
+use hyper::body::HttpBody;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+use crate::{
+    endpoint::Endpoint,
+    http::{
+        header::{self, HeaderName, HeaderValue},
+        HeaderMap, Method, StatusCode,
+    },
+    middleware::Middleware,
+    Body, IntoResponse, Request, Response, Result,
+};
+
+/// A response stored by the [`Cache`] middleware.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The status code of the response.
+    pub status: StatusCode,
+    /// The headers of the response.
+    pub headers: HeaderMap,
+    /// The body of the response.
+    pub body: Bytes,
+    /// The request headers listed in the `Vary` header of the response, with
+    /// the values they had in the request.
+    pub vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    /// The time when the response was stored.
+    pub created_at: SystemTime,
+    /// The time when the response is no longer fresh.
+    pub expires_at: SystemTime,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self, now: SystemTime) -> bool {
+        self.expires_at > now
+    }
+
+    fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.created_at).unwrap_or_default()
+    }
+
+    /// Returns `true` if this response was stored for a request with the same
+    /// values of the headers listed in the `Vary` header.
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+
+    fn to_response(&self, now: SystemTime) -> Response {
+        let mut resp = Response::builder()
+            .status(self.status)
+            .body(self.body.clone());
+        *resp.headers_mut() = self.headers.clone();
+        resp.headers_mut()
+            .typed_insert(Age::from_secs(self.age(now).as_secs()));
+        resp
+    }
+}
+
+/// Represents a back-end storage of the [`Cache`] middleware.
+#[async_trait::async_trait]
+pub trait CacheStore: Send + Sync + 'static {
+    /// Load the responses stored for a key, one for each variant of the
+    /// resource selected by the `Vary` header.
+    async fn get(&self, key: &str) -> Result<Vec<CachedResponse>>;
+
+    /// Replace the responses stored for a key.
+    async fn set(&self, key: &str, responses: Vec<CachedResponse>) -> Result<()>;
+}
+
+struct InnerStore {
+    capacity: usize,
+    entries: HashMap<String, (Vec<CachedResponse>, u64)>,
+    /// The keys of the entries, ordered from the least recently used.
+    lru: BTreeMap<u64, String>,
+    counter: u64,
+}
+
+impl InnerStore {
+    fn touch(&mut self, key: &str) {
+        self.counter += 1;
+        if let Some((_, used)) = self.entries.get_mut(key) {
+            self.lru.remove(used);
+            *used = self.counter;
+            self.lru.insert(self.counter, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((_, used)) = self.entries.remove(key) {
+            self.lru.remove(&used);
+        }
+    }
+}
+
+/// A cache store using memory, which evicts the least recently used entries
+/// when it is full.
+pub struct MemoryCacheStore {
+    inner: Mutex<InnerStore>,
+}
+
+impl Default for MemoryCacheStore {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl MemoryCacheStore {
+    /// Create a `MemoryCacheStore` that holds at most `capacity` resources.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(InnerStore {
+                capacity,
+                entries: HashMap::new(),
+                lru: BTreeMap::new(),
+                counter: 0,
+            }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheStore for MemoryCacheStore {
+    async fn get(&self, key: &str) -> Result<Vec<CachedResponse>> {
+        let mut inner = self.inner.lock();
+        let now = SystemTime::now();
+        let responses = match inner.entries.get_mut(key) {
+            Some((responses, _)) => {
+                responses.retain(|resp| resp.is_fresh(now));
+                responses.clone()
+            }
+            None => return Ok(Vec::new()),
+        };
+
+        if responses.is_empty() {
+            inner.remove(key);
+        } else {
+            inner.touch(key);
+        }
+        Ok(responses)
+    }
+
+    async fn set(&self, key: &str, responses: Vec<CachedResponse>) -> Result<()> {
+        let mut inner = self.inner.lock();
+        inner.remove(key);
+        if responses.is_empty() || inner.capacity == 0 {
+            return Ok(());
+        }
+
+        inner.entries.insert(key.to_string(), (responses, 0));
+        inner.touch(key);
+        while inner.entries.len() > inner.capacity {
+            match inner.lru.keys().next().copied() {
+                Some(used) => {
+                    if let Some(key) = inner.lru.remove(&used) {
+                        inner.entries.remove(&key);
+                    }
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+type InflightRequests = Mutex<HashMap<String, Shared<oneshot::Receiver<CachedResponse>>>>;
+
+/// Middleware for caching the responses of `GET` and `HEAD` requests.
+///
+/// Responses are stored with their status, headers and body, and are keyed by
+/// the method, the URI and the request headers listed in their `Vary` header.
+///
+/// A response is stored if its status is cacheable and its `Cache-Control`
+/// header has a `s-maxage` or `max-age` directive, or if a default TTL is set
+/// with [`Cache::default_ttl`]. Responses with the `no-store`, `no-cache` or
+/// `private` directives, with a `Set-Cookie` header or a `Vary: *` header are
+/// never stored, and neither are responses to requests with an
+/// `Authorization` header unless they are marked `public`.
+///
+/// The `no-store`, `no-cache`, `max-age` and `only-if-cached` directives of
+/// the request are respected.
+///
+/// When several requests miss the cache at the same time, only the first of
+/// them is handled by the endpoint and the others wait for its response.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     handler,
+///     middleware::{Cache, MemoryCacheStore},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn report() -> String {
+///     "expensive report".to_string()
+/// }
+///
+/// let app = Route::new().at(
+///     "/report",
+///     report.with(
+///         Cache::new()
+///             .store(MemoryCacheStore::new(100))
+///             .default_ttl(Duration::from_secs(60)),
+///     ),
+/// );
+/// ```
+pub struct Cache {
+    store: Arc<dyn CacheStore>,
+    default_ttl: Option<Duration>,
+    max_body_size: usize,
+    inflight: Arc<InflightRequests>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(MemoryCacheStore::default()),
+            default_ttl: None,
+            max_body_size: 1024 * 1024,
+            inflight: Default::default(),
+        }
+    }
+}
+
+impl Cache {
+    /// Creates a new `Cache` middleware with a [`MemoryCacheStore`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the store of the responses.
+    pub fn store(self, store: impl CacheStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            ..self
+        }
+    }
+
+    /// Sets the time to live of the responses without a `s-maxage` or
+    /// `max-age` directive, they are not stored by default.
+    pub fn default_ttl(self, ttl: Duration) -> Self {
+        Self {
+            default_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of the body of a stored response, defaults to
+    /// 1MB.
+    ///
+    /// Responses whose body may be larger, such as streams, are never stored.
+    pub fn max_body_size(self, size: usize) -> Self {
+        Self {
+            max_body_size: size,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Cache {
+    type Output = CacheEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CacheEndpoint {
+            inner: ep,
+            store: self.store.clone(),
+            default_ttl: self.default_ttl,
+            max_body_size: self.max_body_size,
+            inflight: self.inflight.clone(),
+        }
+    }
+}
+
+/// Endpoint for Cache middleware.
+pub struct CacheEndpoint<E> {
+    inner: E,
+    store: Arc<dyn CacheStore>,
+    default_ttl: Option<Duration>,
+    max_body_size: usize,
+    inflight: Arc<InflightRequests>,
+}
+
+/// Removes a key from the in-flight requests when the request that handles it
+/// completes or is cancelled.
+struct InflightGuard<'a> {
+    inflight: &'a InflightRequests,
+    key: &'a str,
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        self.inflight.lock().remove(self.key);
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for CacheEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return self.inner.call(req).await.into_response();
+        }
+        let cache_control = req
+            .headers()
+            .typed_get::<CacheControl>()
+            .unwrap_or_else(CacheControl::new);
+        if cache_control.no_store() {
+            return self.inner.call(req).await.into_response();
+        }
+
+        let key = format!("{} {}", req.method(), req.uri());
+        if cache_control.no_cache() {
+            return self.fetch(req, &key, None).await;
+        }
+        if let Some(resp) = self
+            .lookup(&key, req.headers(), cache_control.max_age())
+            .await
+        {
+            return resp;
+        }
+        if cache_control.only_if_cached() {
+            return StatusCode::GATEWAY_TIMEOUT.into();
+        }
+
+        let waiting = {
+            let mut inflight = self.inflight.lock();
+            match inflight.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = oneshot::channel();
+                    inflight.insert(key.clone(), receiver.shared());
+                    Ok(sender)
+                }
+            }
+        };
+
+        match waiting {
+            Ok(sender) => {
+                let _guard = InflightGuard {
+                    inflight: &self.inflight,
+                    key: &key,
+                };
+                self.fetch(req, &key, Some(sender)).await
+            }
+            Err(receiver) => match receiver.await {
+                Ok(cached) if cached.matches(req.headers()) => {
+                    cached.to_response(SystemTime::now())
+                }
+                // the response cannot be shared, so this request is handled on its own
+                _ => self.fetch(req, &key, None).await,
+            },
+        }
+    }
+}
+
+impl<E: Endpoint> CacheEndpoint<E> {
+    async fn lookup(
+        &self,
+        key: &str,
+        headers: &HeaderMap,
+        max_age: Option<Duration>,
+    ) -> Option<Response> {
+        // errors of the store are handled as cache misses
+        let responses = self.store.get(key).await.ok()?;
+        let now = SystemTime::now();
+        responses
+            .iter()
+            .find(|cached| {
+                cached.is_fresh(now)
+                    && cached.matches(headers)
+                    && max_age
+                        .map(|max_age| cached.age(now) <= max_age)
+                        .unwrap_or(true)
+            })
+            .map(|cached| cached.to_response(now))
+    }
+
+    /// Calls the inner endpoint and stores the response if it is cacheable.
+    async fn fetch(
+        &self,
+        req: Request,
+        key: &str,
+        sender: Option<oneshot::Sender<CachedResponse>>,
+    ) -> Response {
+        let req_headers = req.headers().clone();
+        let mut resp = self.inner.call(req).await.into_response();
+
+        let ttl = match self.ttl(&resp, req_headers.contains_key(header::AUTHORIZATION)) {
+            Some(ttl) => ttl,
+            None => return resp,
+        };
+        let vary = match vary(&resp, &req_headers) {
+            Some(vary) => vary,
+            None => return resp,
+        };
+        let body: hyper::Body = resp.take_body().into();
+        match body.size_hint().upper() {
+            Some(size) if size <= self.max_body_size as u64 => {}
+            _ => {
+                resp.set_body(Body::from(body));
+                return resp;
+            }
+        }
+        let data = match hyper::body::to_bytes(body).await {
+            Ok(data) => data,
+            Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into(),
+        };
+        resp.set_body(data.clone());
+
+        let now = SystemTime::now();
+        let cached = CachedResponse {
+            status: resp.status(),
+            headers: resp.headers().clone(),
+            body: data,
+            vary,
+            created_at: now,
+            expires_at: now + ttl,
+        };
+        let mut responses = self.store.get(key).await.unwrap_or_default();
+        responses.retain(|resp| resp.is_fresh(now) && resp.vary != cached.vary);
+        responses.push(cached.clone());
+        let _ = self.store.set(key, responses).await;
+
+        if let Some(sender) = sender {
+            let _ = sender.send(cached);
+        }
+        resp
+    }
+
+    /// Returns the time to live of a response, or `None` if it must not be
+    /// stored.
+    fn ttl(&self, resp: &Response, authorized: bool) -> Option<Duration> {
+        if !is_cacheable_status(resp.status()) || resp.headers().contains_key(header::SET_COOKIE) {
+            return None;
+        }
+
+        let cache_control = resp.headers().typed_get::<CacheControl>();
+        if let Some(cache_control) = &cache_control {
+            if cache_control.no_store() || cache_control.no_cache() || cache_control.private() {
+                return None;
+            }
+        }
+        let public = cache_control
+            .as_ref()
+            .map(|cache_control| cache_control.public() || cache_control.s_max_age().is_some())
+            .unwrap_or_default();
+        if authorized && !public {
+            return None;
+        }
+
+        cache_control
+            .and_then(|cache_control| {
+                cache_control
+                    .s_max_age()
+                    .or_else(|| cache_control.max_age())
+            })
+            .or(self.default_ttl)
+            .filter(|ttl| *ttl > Duration::ZERO)
+    }
+}
+
+fn is_cacheable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::OK
+            | StatusCode::NON_AUTHORITATIVE_INFORMATION
+            | StatusCode::NO_CONTENT
+            | StatusCode::MULTIPLE_CHOICES
+            | StatusCode::MOVED_PERMANENTLY
+            | StatusCode::PERMANENT_REDIRECT
+            | StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::GONE
+    )
+}
+
+/// Returns the values of the request headers listed in the `Vary` header of
+/// the response, or `None` if the response varies on everything.
+fn vary(
+    resp: &Response,
+    req_headers: &HeaderMap,
+) -> Option<Vec<(HeaderName, Option<HeaderValue>)>> {
+    let mut vary = Vec::new();
+    for value in resp.headers().get_all(header::VARY) {
+        for name in value.to_str().ok()?.split(',').map(str::trim) {
+            if name == "*" {
+                return None;
+            }
+            if let Ok(name) = name.parse::<HeaderName>() {
+                let value = req_headers.get(&name).cloned();
+                vary.push((name, value));
+            }
+        }
+    }
+    Some(vary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{endpoint::make, http::Uri, EndpointExt};
+
+    fn counting_endpoint(
+        counter: Arc<AtomicUsize>,
+        cache_control: &'static str,
+    ) -> impl Endpoint<Output = Response> {
+        make(move |req| {
+            let counter = counter.clone();
+            async move {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let lang = req
+                    .headers()
+                    .get(header::ACCEPT_LANGUAGE)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or("en")
+                    .to_string();
+                Response::builder()
+                    .header(header::CACHE_CONTROL, cache_control)
+                    .header(header::VARY, "accept-language")
+                    .body(format!("{} {}", lang, n))
+            }
+        })
+    }
+
+    async fn get(ep: &impl Endpoint<Output = Response>, headers: &[(&str, &str)]) -> String {
+        let mut req = Request::builder().uri(Uri::from_static("/report"));
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        ep.call(req.finish())
+            .await
+            .into_body()
+            .into_string()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn cache_hit() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let ep = counting_endpoint(counter.clone(), "max-age=60").with(Cache::new());
+
+        assert_eq!(get(&ep, &[]).await, "en 0");
+        let resp = ep
+            .call(Request::builder().uri(Uri::from_static("/report")).finish())
+            .await;
+        assert_eq!(resp.headers().get(header::AGE).unwrap(), "0");
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "en 0");
+
+        // vary
+        assert_eq!(get(&ep, &[("accept-language", "fr")]).await, "fr 1");
+        assert_eq!(get(&ep, &[("accept-language", "fr")]).await, "fr 1");
+        assert_eq!(get(&ep, &[]).await, "en 0");
+
+        // request directives
+        assert_eq!(get(&ep, &[("cache-control", "no-cache")]).await, "en 2");
+        assert_eq!(get(&ep, &[]).await, "en 2");
+        assert_eq!(get(&ep, &[("cache-control", "no-store")]).await, "en 3");
+        assert_eq!(get(&ep, &[]).await, "en 2");
+
+        let resp = ep
+            .call(
+                Request::builder()
+                    .uri(Uri::from_static("/other"))
+                    .header(header::CACHE_CONTROL, "only-if-cached")
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn not_cacheable() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let ep = counting_endpoint(counter.clone(), "no-store").with(Cache::new());
+        assert_eq!(get(&ep, &[]).await, "en 0");
+        assert_eq!(get(&ep, &[]).await, "en 1");
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let ep = counting_endpoint(counter.clone(), "max-age=60").with(Cache::new());
+        assert_eq!(get(&ep, &[("authorization", "Bearer abc")]).await, "en 0");
+        assert_eq!(get(&ep, &[("authorization", "Bearer abc")]).await, "en 1");
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let ep = counting_endpoint(counter.clone(), "public")
+            .with(Cache::new().default_ttl(Duration::from_secs(60)));
+        assert_eq!(get(&ep, &[]).await, "en 0");
+        assert_eq!(get(&ep, &[]).await, "en 0");
+        let ep = counting_endpoint(counter.clone(), "public").with(Cache::new());
+        assert_eq!(get(&ep, &[]).await, "en 1");
+        assert_eq!(get(&ep, &[]).await, "en 2");
+    }
+
+    #[tokio::test]
+    async fn coalescing() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let ep = counting_endpoint(counter.clone(), "max-age=60").with(Cache::new());
+        let (a, b, c) = tokio::join!(get(&ep, &[]), get(&ep, &[]), get(&ep, &[]));
+        assert_eq!(
+            (a.as_str(), b.as_str(), c.as_str()),
+            ("en 0", "en 0", "en 0")
+        );
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(ep.inflight.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_store_lru() {
+        let store = MemoryCacheStore::new(2);
+        let now = SystemTime::now();
+        let response = |expires_at| CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+            vary: Vec::new(),
+            created_at: now,
+            expires_at,
+        };
+        let fresh = now + Duration::from_secs(60);
+
+        store.set("a", vec![response(fresh)]).await.unwrap();
+        store.set("b", vec![response(fresh)]).await.unwrap();
+        assert_eq!(store.get("a").await.unwrap().len(), 1);
+        store.set("c", vec![response(fresh)]).await.unwrap();
+        assert_eq!(store.get("a").await.unwrap().len(), 1);
+        assert!(store.get("b").await.unwrap().is_empty());
+        assert_eq!(store.get("c").await.unwrap().len(), 1);
+
+        store.set("a", vec![response(now)]).await.unwrap();
+        assert!(store.get("a").await.unwrap().is_empty());
+        assert_eq!(store.inner.lock().entries.len(), 1);
+    }
+}
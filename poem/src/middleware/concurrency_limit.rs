@@ -0,0 +1,371 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    endpoint::Endpoint,
+    http::{header, StatusCode},
+    middleware::Middleware,
+    IntoResponse, Request, Response,
+};
+
+/// Middleware for limiting the number of requests handled concurrently by an
+/// endpoint.
+///
+/// Requests above the limit wait in a bounded queue, which is empty by
+/// default. When the queue is full, or a request has waited longer than the
+/// queue timeout, the request is rejected with `503 Service Unavailable` and a
+/// `Retry-After` header.
+///
+/// With [`ConcurrencyLimit::adaptive`], the limit is adjusted to the observed
+/// latency: it is increased by one every time `limit` requests complete within
+/// the target latency, and is decreased by 10% every time a request is slower.
+///
+/// Every endpoint the middleware is applied to has its own limit.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{handler, middleware::ConcurrencyLimit, EndpointExt, Route};
+///
+/// #[handler]
+/// fn search() {}
+///
+/// let app = Route::new().at(
+///     "/search",
+///     search.with(
+///         ConcurrencyLimit::new(64)
+///             .queue(128)
+///             .queue_timeout(Duration::from_secs(1))
+///             .adaptive(8, 256, Duration::from_millis(200)),
+///     ),
+/// );
+/// ```
+pub struct ConcurrencyLimit {
+    limit: usize,
+    max_queued: usize,
+    queue_timeout: Option<Duration>,
+    retry_after: Duration,
+    adaptive: Option<(usize, usize, Duration)>,
+}
+
+impl ConcurrencyLimit {
+    /// Creates a new `ConcurrencyLimit` middleware that handles at most
+    /// `limit` requests at the same time.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            max_queued: 0,
+            queue_timeout: None,
+            retry_after: Duration::from_secs(1),
+            adaptive: None,
+        }
+    }
+
+    /// Sets the maximum number of requests waiting for the previous ones to
+    /// complete, defaults to `0`.
+    pub fn queue(self, max_queued: usize) -> Self {
+        Self { max_queued, ..self }
+    }
+
+    /// Sets the maximum time a request waits in the queue, requests wait
+    /// until they can be handled by default.
+    pub fn queue_timeout(self, timeout: Duration) -> Self {
+        Self {
+            queue_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the value of the `Retry-After` header of the rejected requests,
+    /// defaults to 1 second.
+    pub fn retry_after(self, retry_after: Duration) -> Self {
+        Self {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// Adjusts the limit between `min` and `max` to keep the latency of the
+    /// requests below `target_latency`, starting from the limit passed to
+    /// [`ConcurrencyLimit::new`].
+    pub fn adaptive(self, min: usize, max: usize, target_latency: Duration) -> Self {
+        Self {
+            adaptive: Some((min.max(1), max.max(min), target_latency)),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ConcurrencyLimit {
+    type Output = ConcurrencyLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let limit = match self.adaptive {
+            Some((min, max, _)) => self.limit.clamp(min, max),
+            None => self.limit,
+        };
+
+        ConcurrencyLimitEndpoint {
+            inner: ep,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            queued: AtomicUsize::new(0),
+            max_queued: self.max_queued,
+            queue_timeout: self.queue_timeout,
+            retry_after: self.retry_after,
+            adaptive: self.adaptive.map(|(min, max, target_latency)| {
+                Mutex::new(AdaptiveLimit {
+                    limit: limit as f64,
+                    min,
+                    max,
+                    target_latency,
+                    excess_permits: 0,
+                })
+            }),
+        }
+    }
+}
+
+/// The state of an adaptive limit, which uses additive increase and
+/// multiplicative decrease (AIMD).
+struct AdaptiveLimit {
+    limit: f64,
+    min: usize,
+    max: usize,
+    target_latency: Duration,
+    /// The number of permits to remove from the semaphore when they are
+    /// released, after the limit has been decreased.
+    excess_permits: usize,
+}
+
+impl AdaptiveLimit {
+    /// Updates the limit with the latency of a request, and returns the
+    /// number of permits to add to the semaphore.
+    fn update(&mut self, latency: Duration) -> usize {
+        let old = self.limit as usize;
+        if latency > self.target_latency {
+            self.limit = (self.limit * 0.9).max(self.min as f64);
+        } else {
+            self.limit = (self.limit + 1.0 / self.limit).min(self.max as f64);
+        }
+        let new = self.limit as usize;
+
+        if new < old {
+            self.excess_permits += old - new;
+            0
+        } else {
+            let added = new - old;
+            let paid = added.min(self.excess_permits);
+            self.excess_permits -= paid;
+            added - paid
+        }
+    }
+}
+
+/// Endpoint for ConcurrencyLimit middleware.
+pub struct ConcurrencyLimitEndpoint<E> {
+    inner: E,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    max_queued: usize,
+    queue_timeout: Option<Duration>,
+    retry_after: Duration,
+    adaptive: Option<Mutex<AdaptiveLimit>>,
+}
+
+/// Releases a permit when a request completes or is cancelled, unless the
+/// adaptive limit has been decreased.
+struct PermitGuard<'a> {
+    permit: Option<OwnedSemaphorePermit>,
+    adaptive: Option<&'a Mutex<AdaptiveLimit>>,
+}
+
+impl Drop for PermitGuard<'_> {
+    fn drop(&mut self) {
+        if let (Some(permit), Some(adaptive)) = (self.permit.take(), self.adaptive) {
+            let mut adaptive = adaptive.lock();
+            if adaptive.excess_permits > 0 {
+                adaptive.excess_permits -= 1;
+                permit.forget();
+            }
+        }
+    }
+}
+
+/// Decrements the number of queued requests when a request leaves the queue.
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<E> ConcurrencyLimitEndpoint<E> {
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        let _guard = QueueGuard(&self.queued);
+        if queued >= self.max_queued {
+            return None;
+        }
+        let acquire = self.semaphore.clone().acquire_owned();
+        match self.queue_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire).await.ok()?.ok(),
+            None => acquire.await.ok(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for ConcurrencyLimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let permit = match self.acquire().await {
+            Some(permit) => permit,
+            None => {
+                let secs =
+                    self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+                return Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(header::RETRY_AFTER, secs)
+                    .finish();
+            }
+        };
+        let _guard = PermitGuard {
+            permit: Some(permit),
+            adaptive: self.adaptive.as_ref(),
+        };
+
+        let start = Instant::now();
+        let resp = self.inner.call(req).await.into_response();
+        if let Some(adaptive) = &self.adaptive {
+            let added = adaptive.lock().update(start.elapsed());
+            if added > 0 {
+                self.semaphore.add_permits(added);
+            }
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{middleware::test_harness::sleep_endpoint, EndpointExt};
+
+    async fn call_many(ep: &impl Endpoint<Output = Response>, n: usize) -> Vec<StatusCode> {
+        futures_util::future::join_all((0..n).map(|_| ep.call(Request::default())))
+            .await
+            .into_iter()
+            .map(|resp| resp.status())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn shed_load() {
+        let ep = sleep_endpoint(Duration::from_millis(50)).with(ConcurrencyLimit::new(2));
+        let statuses = call_many(&ep, 3).await;
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let ep = sleep_endpoint(Duration::from_millis(50))
+            .with(ConcurrencyLimit::new(1).retry_after(Duration::from_millis(1500)));
+        let (_, resp) = tokio::join!(ep.call(Request::default()), ep.call(Request::default()));
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "2");
+    }
+
+    #[tokio::test]
+    async fn queue() {
+        let ep = sleep_endpoint(Duration::from_millis(20)).with(ConcurrencyLimit::new(1).queue(2));
+        let statuses = call_many(&ep, 4).await;
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+        assert_eq!(ep.queued.load(Ordering::SeqCst), 0);
+
+        let ep = sleep_endpoint(Duration::from_millis(100)).with(
+            ConcurrencyLimit::new(1)
+                .queue(1)
+                .queue_timeout(Duration::from_millis(10)),
+        );
+        let statuses = call_many(&ep, 2).await;
+        assert_eq!(
+            statuses,
+            vec![StatusCode::OK, StatusCode::SERVICE_UNAVAILABLE]
+        );
+    }
+
+    #[tokio::test]
+    async fn adaptive() {
+        let ep = sleep_endpoint(Duration::from_millis(20))
+            .with(ConcurrencyLimit::new(10).adaptive(2, 20, Duration::from_millis(5)));
+        for _ in 0..20 {
+            ep.call(Request::default()).await;
+        }
+        assert_eq!(ep.adaptive.as_ref().unwrap().lock().limit, 2.0);
+        assert_eq!(ep.semaphore.available_permits(), 2);
+
+        let ep = sleep_endpoint(Duration::from_millis(1)).with(ConcurrencyLimit::new(2).adaptive(
+            2,
+            3,
+            Duration::from_secs(1),
+        ));
+        for _ in 0..10 {
+            ep.call(Request::default()).await;
+        }
+        assert_eq!(ep.adaptive.as_ref().unwrap().lock().limit, 3.0);
+        assert_eq!(ep.semaphore.available_permits(), 3);
+    }
+
+    #[test]
+    fn test_adaptive_limit() {
+        let mut limit = AdaptiveLimit {
+            limit: 10.0,
+            min: 1,
+            max: 100,
+            target_latency: Duration::from_millis(10),
+            excess_permits: 0,
+        };
+        assert_eq!(limit.update(Duration::from_millis(20)), 0);
+        assert_eq!(limit.limit as usize, 9);
+        assert_eq!(limit.excess_permits, 1);
+
+        let mut added = 0;
+        for _ in 0..25 {
+            added += limit.update(Duration::from_millis(1));
+        }
+        assert_eq!(limit.limit as usize, 11);
+        assert_eq!(limit.excess_permits, 0);
+        assert_eq!(added, 1);
+    }
+}
@@ -0,0 +1,534 @@
+use std::sync::Arc;
+
+use rand::{rngs::OsRng, thread_rng, Rng};
+
+use crate::{
+    endpoint::Endpoint,
+    http::{Method, StatusCode},
+    middleware::Middleware,
+    web::cookie::{Cookie, SameSite},
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+const TOKEN_LEN: usize = 32;
+
+/// A CSRF token for the current request, which must be sent back with the
+/// requests that use unsafe methods.
+///
+/// The token is masked with a random value, so that it changes for every
+/// request and cannot be recovered with compression attacks such as BREACH.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::CsrfToken, web::Html};
+///
+/// #[handler]
+/// fn form(token: &CsrfToken) -> Html<String> {
+///     Html(format!(
+///         r#"<form method="post"><input type="hidden" name="csrf_token" value="{}"></form>"#,
+///         token.0
+///     ))
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CsrfToken(pub String);
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for &'a CsrfToken {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self, Self::Error> {
+        Ok(req
+            .extensions()
+            .get::<CsrfToken>()
+            .expect("To use the `CsrfToken` extractor, the `Csrf` middleware is required."))
+    }
+}
+
+/// Where the secret token of a client is stored.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum TokenStorage {
+    /// A signed cookie, which is compared with the token sent in the request
+    /// (double-submit cookie).
+    Cookie,
+    /// The session of the client.
+    #[cfg(feature = "session")]
+    Session,
+}
+
+struct CsrfConfig {
+    storage: TokenStorage,
+    cookie_name: String,
+    secure: bool,
+    header_name: String,
+    form_field: String,
+    exempt_paths: Vec<String>,
+}
+
+/// Middleware for Cross-Site Request Forgery (CSRF) protection.
+///
+/// A secret token is generated for each client, and is stored in a signed
+/// cookie by default, or in the session of the client with
+/// [`Csrf::session_bound`]. The [`CsrfToken`] extractor returns a masked
+/// version of this token, which must be sent back in the `X-CSRF-Token` header
+/// or in the `csrf_token` field of an `application/x-www-form-urlencoded` form
+/// for the requests that use an unsafe method, such as `POST`. Otherwise, they
+/// are rejected with `403 Forbidden`.
+///
+/// The signed cookie requires the [`CookieJarManager`] middleware created with
+/// [`CookieJarManager::with_key`], and the session-bound token requires a
+/// session middleware such as [`ServerSession`].
+///
+/// [`CookieJarManager`]: crate::middleware::CookieJarManager
+/// [`CookieJarManager::with_key`]: crate::middleware::CookieJarManager::with_key
+/// [`ServerSession`]: crate::session::ServerSession
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{CookieJarManager, Csrf, CsrfToken},
+///     web::cookie::CookieKey,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn form(token: &CsrfToken) -> String {
+///     token.0.clone()
+/// }
+///
+/// #[handler]
+/// fn webhook() {}
+///
+/// let app = Route::new()
+///     .at("/form", form)
+///     .at("/webhook", webhook)
+///     .with(Csrf::new().exempt("/webhook"))
+///     .with(CookieJarManager::with_key(CookieKey::generate()));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
+pub struct Csrf {
+    config: CsrfConfig,
+}
+
+impl Default for Csrf {
+    fn default() -> Self {
+        Self {
+            config: CsrfConfig {
+                storage: TokenStorage::Cookie,
+                cookie_name: "poem-csrf-token".to_string(),
+                secure: true,
+                header_name: "x-csrf-token".to_string(),
+                form_field: "csrf_token".to_string(),
+                exempt_paths: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Csrf {
+    /// Creates a new `Csrf` middleware that stores the secret tokens in
+    /// signed cookies.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Stores the secret tokens in the sessions of the clients instead of
+    /// cookies.
+    #[cfg(feature = "session")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "session")))]
+    pub fn session_bound(mut self) -> Self {
+        self.config.storage = TokenStorage::Session;
+        self
+    }
+
+    /// Sets the name of the cookie that stores the secret token, defaults to
+    /// `poem-csrf-token`.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.config.cookie_name = name.into();
+        self
+    }
+
+    /// Sets the `Secure` attribute of the cookie, defaults to `true`.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.config.secure = secure;
+        self
+    }
+
+    /// Sets the name of the request header that contains the token, defaults
+    /// to `X-CSRF-Token`.
+    pub fn header_name(mut self, name: impl Into<String>) -> Self {
+        self.config.header_name = name.into();
+        self
+    }
+
+    /// Sets the name of the form field that contains the token, defaults to
+    /// `csrf_token`.
+    pub fn form_field(mut self, name: impl Into<String>) -> Self {
+        self.config.form_field = name.into();
+        self
+    }
+
+    /// Exempts a path from the verification, a path that ends with `/` exempts
+    /// all the paths it prefixes.
+    pub fn exempt(mut self, path: impl Into<String>) -> Self {
+        self.config.exempt_paths.push(path.into());
+        self
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Csrf {
+    type Output = CsrfEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let config = &self.config;
+        CsrfEndpoint {
+            inner: ep,
+            config: Arc::new(CsrfConfig {
+                storage: config.storage,
+                cookie_name: config.cookie_name.clone(),
+                secure: config.secure,
+                header_name: config.header_name.clone(),
+                form_field: config.form_field.clone(),
+                exempt_paths: config.exempt_paths.clone(),
+            }),
+        }
+    }
+}
+
+/// Endpoint for Csrf middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "csrf")))]
+pub struct CsrfEndpoint<E> {
+    inner: E,
+    config: Arc<CsrfConfig>,
+}
+
+impl<E> CsrfEndpoint<E> {
+    /// Returns the secret token of the client, and creates it if it does not
+    /// exist.
+    fn secret(&self, req: &Request) -> Vec<u8> {
+        let config = &self.config;
+        let secret = match config.storage {
+            TokenStorage::Cookie => {
+                req.cookie()
+                    .signed()
+                    .get(&config.cookie_name)
+                    .and_then(|cookie| {
+                        base64::decode_config(cookie.value_str(), base64::URL_SAFE_NO_PAD).ok()
+                    })
+            }
+            #[cfg(feature = "session")]
+            TokenStorage::Session => session(req)
+                .get::<String>(&config.cookie_name)
+                .and_then(|value| base64::decode_config(value, base64::URL_SAFE_NO_PAD).ok()),
+        };
+        if let Some(secret) = secret.filter(|secret| secret.len() == TOKEN_LEN) {
+            return secret;
+        }
+
+        let mut secret = vec![0; TOKEN_LEN];
+        OsRng.fill(&mut secret[..]);
+        let value = base64::encode_config(&secret, base64::URL_SAFE_NO_PAD);
+        match config.storage {
+            TokenStorage::Cookie => {
+                let mut cookie = Cookie::new_with_str(&config.cookie_name, value);
+                cookie.set_path("/");
+                cookie.set_http_only(true);
+                cookie.set_secure(config.secure);
+                cookie.set_same_site(SameSite::Strict);
+                req.cookie().signed().add(cookie);
+            }
+            #[cfg(feature = "session")]
+            TokenStorage::Session => session(req).set(&config.cookie_name, value),
+        }
+        secret
+    }
+
+    fn is_exempt(&self, path: &str) -> bool {
+        self.config.exempt_paths.iter().any(|exempt| {
+            if exempt.ends_with('/') {
+                path.starts_with(exempt.as_str())
+            } else {
+                path == exempt
+            }
+        })
+    }
+
+    /// Returns the token sent in the request header or form.
+    async fn submitted_token(&self, req: &mut Request) -> Result<Option<String>, Response> {
+        if let Some(value) = req
+            .headers()
+            .get(self.config.header_name.as_str())
+            .and_then(|value| value.to_str().ok())
+        {
+            return Ok(Some(value.to_string()));
+        }
+
+        let is_form = req
+            .content_type()
+            .map(|content_type| content_type.starts_with("application/x-www-form-urlencoded"))
+            .unwrap_or_default();
+        if !is_form {
+            return Ok(None);
+        }
+
+        // the body is read to find the token, and restored for the endpoint
+        let data = req
+            .take_body()
+            .into_bytes()
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let token = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&data)
+            .ok()
+            .and_then(|fields| {
+                fields
+                    .into_iter()
+                    .find(|(name, _)| name == &self.config.form_field)
+                    .map(|(_, value)| value)
+            });
+        req.set_body(data);
+        Ok(token)
+    }
+}
+
+#[cfg(feature = "session")]
+fn session(req: &Request) -> &crate::session::Session {
+    req.extensions().get().expect(
+        "To use the `Csrf::session_bound` method, a session middleware such as `ServerSession` \
+         is required.",
+    )
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for CsrfEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let secret = self.secret(&req);
+        req.extensions_mut().insert(CsrfToken(mask_token(&secret)));
+
+        let is_safe = matches!(
+            *req.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+        );
+        if !is_safe && !self.is_exempt(req.uri().path()) {
+            let token = match self.submitted_token(&mut req).await {
+                Ok(token) => token,
+                Err(resp) => return resp,
+            };
+            let valid = token
+                .and_then(|token| unmask_token(&token))
+                .map(|token| constant_time_eq(&token, &secret))
+                .unwrap_or_default();
+            if !valid {
+                return (StatusCode::FORBIDDEN, "invalid CSRF token").into_response();
+            }
+        }
+
+        self.inner.call(req).await.into_response()
+    }
+}
+
+/// Masks a secret token with a random pad, the result is the pad followed by
+/// the token XORed with the pad.
+fn mask_token(secret: &[u8]) -> String {
+    let mut pad = vec![0; secret.len()];
+    thread_rng().fill(&mut pad[..]);
+    let masked = pad
+        .iter()
+        .zip(secret)
+        .map(|(pad, secret)| pad ^ secret)
+        .collect::<Vec<_>>();
+    pad.extend(masked);
+    base64::encode_config(&pad, base64::URL_SAFE_NO_PAD)
+}
+
+fn unmask_token(token: &str) -> Option<Vec<u8>> {
+    let data = base64::decode_config(token.trim(), base64::URL_SAFE_NO_PAD).ok()?;
+    if data.len() != TOKEN_LEN * 2 {
+        return None;
+    }
+    let (pad, masked) = data.split_at(TOKEN_LEN);
+    Some(
+        pad.iter()
+            .zip(masked)
+            .map(|(pad, masked)| pad ^ masked)
+            .collect(),
+    )
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        endpoint::make_sync,
+        handler,
+        http::header,
+        middleware::CookieJarManager,
+        web::{cookie::CookieKey, Form},
+        EndpointExt, Route,
+    };
+
+    #[handler(internal)]
+    fn csrf_token(token: &CsrfToken) -> String {
+        token.0.clone()
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Comment {
+        text: String,
+    }
+
+    #[handler(internal)]
+    fn comment(Form(comment): Form<Comment>) -> String {
+        comment.text
+    }
+
+    fn app() -> impl Endpoint<Output = Response> {
+        Route::new()
+            .at("/token", csrf_token)
+            .at("/comment", comment)
+            .at("/hooks/github", make_sync(|_| "hook"))
+            .with(Csrf::new().exempt("/hooks/"))
+            .with(CookieJarManager::with_key(CookieKey::generate()))
+    }
+
+    async fn get_token(app: &impl Endpoint<Output = Response>) -> (String, String) {
+        let resp = app
+            .call(Request::builder().uri("/token".parse().unwrap()).finish())
+            .await;
+        let cookie = resp
+            .headers()
+            .get(header::SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let token = resp.into_body().into_string().await.unwrap();
+        (cookie, token)
+    }
+
+    fn post(cookie: &str) -> crate::RequestBuilder {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/comment".parse().unwrap())
+            .header(header::COOKIE, cookie)
+            .content_type("application/x-www-form-urlencoded")
+    }
+
+    #[tokio::test]
+    async fn double_submit_cookie() {
+        let app = app();
+        let (cookie, token) = get_token(&app).await;
+        assert!(cookie.starts_with("poem-csrf-token="));
+
+        let resp = app
+            .call(
+                post(&cookie)
+                    .header("x-csrf-token", &token)
+                    .body("text=hello"),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app
+            .call(post(&cookie).body(format!("text=hello&csrf_token={}", token)))
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "hello");
+
+        // the masked token changes every time
+        let resp = app
+            .call(
+                Request::builder()
+                    .uri("/token".parse().unwrap())
+                    .header(header::COOKIE, &cookie)
+                    .finish(),
+            )
+            .await;
+        assert!(resp.headers().get(header::SET_COOKIE).is_none());
+        let other_token = resp.into_body().into_string().await.unwrap();
+        assert_ne!(token, other_token);
+        let resp = app
+            .call(
+                post(&cookie)
+                    .header("x-csrf-token", &other_token)
+                    .body("text=hello"),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejected() {
+        let app = app();
+        let (cookie, token) = get_token(&app).await;
+        let (other_cookie, _) = get_token(&app).await;
+
+        let resp = app.call(post(&cookie).finish()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = app
+            .call(post(&other_cookie).header("x-csrf-token", &token).finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = app
+            .call(post(&cookie).header("x-csrf-token", "invalid").finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = app
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/hooks/github".parse().unwrap())
+                    .finish(),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[cfg(feature = "session")]
+    #[tokio::test]
+    async fn session_bound() {
+        use crate::session::{CookieConfig, CookieSession};
+
+        let app = Route::new()
+            .at("/token", csrf_token)
+            .at("/comment", comment)
+            .with(Csrf::new().session_bound())
+            .with(CookieSession::new(CookieConfig::signed(
+                CookieKey::generate(),
+            )));
+        let (cookie, token) = get_token(&app).await;
+        assert!(cookie.starts_with("poem-session="));
+
+        let resp = app
+            .call(post(&cookie).body(format!("text=hello&csrf_token={}", token)))
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app.call(post(&cookie).finish()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_mask_token() {
+        let secret = vec![7; TOKEN_LEN];
+        let token = mask_token(&secret);
+        assert_ne!(token, mask_token(&secret));
+        assert_eq!(unmask_token(&token), Some(secret));
+        assert_eq!(unmask_token("abc"), None);
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+}
@@ -0,0 +1,441 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::stream;
+use hyper::body::HttpBody;
+
+use crate::{
+    endpoint::Endpoint,
+    http::{
+        header::{self, HeaderValue},
+        HeaderMap, Method, StatusCode,
+    },
+    middleware::{Cors, Middleware},
+    Body, IntoResponse, Request, Response,
+};
+
+/// The flag of the frame that contains the trailers in a gRPC-Web response.
+const TRAILERS_FLAG: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Encoding {
+    Binary,
+    Text,
+}
+
+/// Returns the encoding of a gRPC-Web content type, and the remainder of the
+/// content type, such as `+proto`.
+fn grpc_web_encoding(content_type: &str) -> Option<(Encoding, &str)> {
+    if let Some(rest) = content_type.strip_prefix("application/grpc-web-text") {
+        Some((Encoding::Text, rest))
+    } else {
+        content_type
+            .strip_prefix("application/grpc-web")
+            .map(|rest| (Encoding::Binary, rest))
+    }
+}
+
+/// Middleware that translates gRPC-Web requests to gRPC, so that a gRPC
+/// service can be called directly from browsers.
+///
+/// Requests with the `application/grpc-web` content type are forwarded as
+/// `application/grpc` requests, and the trailers of the response are moved
+/// into the response body. Requests with the `application/grpc-web-text`
+/// content type are also supported, their bodies and the response bodies are
+/// base64 encoded. Other requests are forwarded unchanged.
+///
+/// Browsers send cross-origin gRPC-Web requests with a CORS preflight request,
+/// [`GrpcWeb::cors`] creates a [`Cors`] middleware that allows them.
+///
+/// # Example
+///
+/// ```
+/// use poem::{endpoint::make_sync, middleware::GrpcWeb, EndpointExt, Route};
+///
+/// // an endpoint that serves a gRPC service, for example with `tower-compat`
+/// let grpc_service = make_sync(|_| "");
+///
+/// let app = Route::new().nest(
+///     "/",
+///     grpc_service
+///         .with(GrpcWeb)
+///         .with(GrpcWeb::cors().allow_origin("https://example.com")),
+/// );
+/// ```
+pub struct GrpcWeb;
+
+impl GrpcWeb {
+    /// Creates a [`Cors`] middleware that allows the `POST` method and the
+    /// headers used by gRPC-Web clients, and exposes the `grpc-status` and
+    /// `grpc-message` headers.
+    ///
+    /// The allowed origins can be configured on the returned middleware.
+    pub fn cors() -> Cors {
+        Cors::new()
+            .allow_method(Method::POST)
+            .allow_headers([
+                "content-type",
+                "x-grpc-web",
+                "x-user-agent",
+                "grpc-timeout",
+                "authorization",
+            ])
+            .expose_headers(["grpc-status", "grpc-message", "grpc-status-details-bin"])
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for GrpcWeb {
+    type Output = GrpcWebEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        GrpcWebEndpoint { inner: ep }
+    }
+}
+
+/// Endpoint for GrpcWeb middleware.
+pub struct GrpcWebEndpoint<E> {
+    inner: E,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for GrpcWebEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let (encoding, subtype) = match grpc_web_encoding(content_type) {
+            Some((encoding, subtype)) if req.method() == Method::POST => {
+                (encoding, subtype.to_string())
+            }
+            _ => return self.inner.call(req).await.into_response(),
+        };
+
+        if encoding == Encoding::Text {
+            let data = match req.take_body().into_bytes().await {
+                Ok(data) => data,
+                Err(err) => return err.into_response(),
+            };
+            match decode_base64(&data) {
+                Some(data) => req.set_body(data),
+                None => {
+                    return (StatusCode::BAD_REQUEST, "invalid base64 request body").into_response()
+                }
+            }
+        }
+
+        let headers = req.headers_mut();
+        headers.remove(header::CONTENT_LENGTH);
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+        if let Ok(value) = HeaderValue::from_str(&format!("application/grpc{}", subtype)) {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+
+        let mut resp = self.inner.call(req).await.into_response();
+        let is_grpc = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.starts_with("application/grpc"))
+            .unwrap_or_default();
+        if !is_grpc {
+            return resp;
+        }
+
+        let content_type = match encoding {
+            Encoding::Binary => format!("application/grpc-web{}", subtype),
+            Encoding::Text => format!("application/grpc-web-text{}", subtype),
+        };
+        let headers = resp.headers_mut();
+        headers.remove(header::CONTENT_LENGTH);
+        if let Ok(value) = HeaderValue::from_str(&content_type) {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+
+        let body = resp.take_body();
+        resp.set_body(translate_response_body(body, encoding));
+        resp
+    }
+}
+
+/// Decodes a base64 request body, which can be made of several padded
+/// chunks.
+fn decode_base64(data: &[u8]) -> Option<Vec<u8>> {
+    let data = data
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    if data.len() % 4 != 0 {
+        return None;
+    }
+
+    // every group of 4 characters is decoded independently of the others
+    let mut output = Vec::with_capacity(data.len() / 4 * 3);
+    for group in data.chunks(4) {
+        base64::decode_config_buf(group, base64::STANDARD, &mut output).ok()?;
+    }
+    Some(output)
+}
+
+/// Encodes the trailers as a gRPC-Web frame.
+fn encode_trailers(trailers: &HeaderMap) -> Bytes {
+    let mut payload = Vec::new();
+    for (name, value) in trailers {
+        payload.extend_from_slice(name.as_str().as_bytes());
+        payload.push(b':');
+        payload.extend_from_slice(value.as_bytes());
+        payload.extend_from_slice(b"\r\n");
+    }
+
+    let mut frame = BytesMut::with_capacity(5 + payload.len());
+    frame.put_u8(TRAILERS_FLAG);
+    frame.put_u32(payload.len() as u32);
+    frame.put_slice(&payload);
+    frame.freeze()
+}
+
+struct ResponseBodyState {
+    body: hyper::Body,
+    encoding: Encoding,
+    /// The bytes that have not been base64 encoded yet, because the encoded
+    /// chunks must be a multiple of 3 bytes to be concatenated.
+    pending: Vec<u8>,
+    finished: bool,
+}
+
+impl ResponseBodyState {
+    fn encode(&mut self, data: Bytes, last: bool) -> Bytes {
+        match self.encoding {
+            Encoding::Binary => data,
+            Encoding::Text => {
+                self.pending.extend_from_slice(&data);
+                let len = if last {
+                    self.pending.len()
+                } else {
+                    self.pending.len() / 3 * 3
+                };
+                let encoded = base64::encode(&self.pending[..len]);
+                self.pending.drain(..len);
+                Bytes::from(encoded)
+            }
+        }
+    }
+}
+
+fn translate_response_body(body: Body, encoding: Encoding) -> Body {
+    let state = ResponseBodyState {
+        body: body.into(),
+        encoding,
+        pending: Vec::new(),
+        finished: false,
+    };
+
+    Body(hyper::Body::wrap_stream(stream::unfold(
+        state,
+        |mut state| async move {
+            if state.finished {
+                return None;
+            }
+
+            match state.body.data().await {
+                Some(Ok(data)) => {
+                    let data = state.encode(data, false);
+                    Some((Ok(data), state))
+                }
+                Some(Err(err)) => {
+                    state.finished = true;
+                    Some((Err(err), state))
+                }
+                None => {
+                    state.finished = true;
+                    let trailers = match state.body.trailers().await {
+                        Ok(trailers) => trailers,
+                        Err(err) => return Some((Err(err), state)),
+                    };
+                    let frame = trailers
+                        .filter(|trailers| !trailers.is_empty())
+                        .map(|trailers| encode_trailers(&trailers))
+                        .unwrap_or_default();
+                    let data = state.encode(frame, true);
+                    Some((Ok(data), state))
+                }
+            }
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make, EndpointExt};
+
+    fn grpc_service() -> impl Endpoint<Output = Response> {
+        make(|req| async move {
+            assert_eq!(req.content_type(), Some("application/grpc+proto"));
+            assert_eq!(req.headers().get(header::TE).unwrap(), "trailers");
+            let data = req.into_body().into_vec().await.unwrap();
+
+            let (mut sender, body) = hyper::Body::channel();
+            tokio::spawn(async move {
+                sender.send_data(data.into()).await.unwrap();
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                sender.send_trailers(trailers).await.unwrap();
+            });
+            Response::builder()
+                .content_type("application/grpc+proto")
+                .body(Body(body))
+        })
+        .with(GrpcWeb)
+    }
+
+    fn message(data: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    fn expected_response(data: &[u8]) -> Vec<u8> {
+        let mut expected = message(data);
+        expected.extend_from_slice(b"\x80\x00\x00\x00\x0fgrpc-status:0\r\n");
+        expected
+    }
+
+    #[tokio::test]
+    async fn binary() {
+        let ep = grpc_service();
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .content_type("application/grpc-web+proto")
+                    .body(message(b"hello")),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.content_type(), Some("application/grpc-web+proto"));
+        assert_eq!(
+            resp.into_body().into_vec().await.unwrap(),
+            expected_response(b"hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn text() {
+        let ep = grpc_service();
+
+        // two padded chunks
+        let mut body = base64::encode(&message(b"a")[..4]);
+        body.push_str(&base64::encode(&message(b"a")[4..]));
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .content_type("application/grpc-web-text+proto")
+                    .body(body),
+            )
+            .await;
+        assert_eq!(resp.content_type(), Some("application/grpc-web-text+proto"));
+        let body = resp.into_body().into_vec().await.unwrap();
+        assert_eq!(decode_base64(&body).unwrap(), expected_response(b"a"));
+
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .content_type("application/grpc-web-text+proto")
+                    .body("abc"),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "tower-compat")]
+    #[tokio::test]
+    async fn tower_compat() {
+        use std::{
+            convert::Infallible,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        use crate::endpoint::TowerCompatExt;
+
+        /// A gRPC response body, which is not a `hyper::Body`.
+        struct GrpcBody(Option<Bytes>);
+
+        impl HttpBody for GrpcBody {
+            type Data = Bytes;
+            type Error = Infallible;
+
+            fn poll_data(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+                Poll::Ready(self.0.take().map(Ok))
+            }
+
+            fn poll_trailers(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("0"));
+                Poll::Ready(Ok(Some(trailers)))
+            }
+        }
+
+        let ep = tower::service_fn(|req: http::Request<hyper::Body>| async move {
+            let data = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            Ok::<_, std::io::Error>(
+                http::Response::builder()
+                    .header(header::CONTENT_TYPE, "application/grpc+proto")
+                    .body(GrpcBody(Some(data)))
+                    .unwrap(),
+            )
+        })
+        .compat()
+        .with(GrpcWeb);
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .content_type("application/grpc-web+proto")
+                    .body(message(b"hello")),
+            )
+            .await;
+        assert_eq!(resp.content_type(), Some("application/grpc-web+proto"));
+        assert_eq!(
+            resp.into_body().into_vec().await.unwrap(),
+            expected_response(b"hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn passthrough() {
+        let ep = make(|req| async move { req.content_type().unwrap_or_default().to_string() })
+            .with(GrpcWeb);
+        let resp = ep
+            .call(
+                Request::builder()
+                    .method(Method::POST)
+                    .content_type("application/json")
+                    .body("{}"),
+            )
+            .await;
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b"YWJj").unwrap(), b"abc");
+        assert_eq!(decode_base64(b"YQ==YWJj\r\n").unwrap(), b"aabc");
+        assert_eq!(decode_base64(b"YQ="), None);
+    }
+}
@@ -1,37 +1,68 @@
 //! Commonly used middleware.
 
 mod add_data;
+mod cache;
 #[cfg(feature = "compression")]
 mod compression;
+mod concurrency_limit;
 #[cfg(feature = "cookie")]
 mod cookie_jar_manager;
 mod cors;
+#[cfg(feature = "csrf")]
+mod csrf;
+mod grpc_web;
 mod normalize_path;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_metrics;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_tracing;
 mod propagate_header;
+mod rate_limit;
+#[cfg(feature = "request-id")]
+mod request_id;
+#[cfg(feature = "security-headers")]
+mod security_headers;
 mod set_header;
 mod size_limit;
+#[cfg(test)]
+pub(crate) mod test_harness;
+mod timeout;
 #[cfg(feature = "tower-compat")]
 mod tower_compat;
 mod tracing_mw;
 
 pub use add_data::{AddData, AddDataEndpoint};
+pub use cache::{Cache, CacheEndpoint, CacheStore, CachedResponse, MemoryCacheStore};
 #[cfg(feature = "compression")]
 pub use compression::{Compression, CompressionEndpoint};
+pub use concurrency_limit::{ConcurrencyLimit, ConcurrencyLimitEndpoint};
 #[cfg(feature = "cookie")]
 pub use cookie_jar_manager::{CookieJarManager, CookieJarManagerEndpoint};
 pub use cors::{Cors, CorsEndpoint};
+#[cfg(feature = "csrf")]
+pub use csrf::{Csrf, CsrfEndpoint, CsrfToken};
+pub use grpc_web::{GrpcWeb, GrpcWebEndpoint};
 pub use normalize_path::{NormalizePath, NormalizePathEndpoint, TrailingSlash};
 #[cfg(feature = "opentelemetry")]
 pub use opentelemetry_metrics::{OpenTelemetryMetrics, OpenTelemetryMetricsEndpoint};
 #[cfg(feature = "opentelemetry")]
 pub use opentelemetry_tracing::{OpenTelemetryTracing, OpenTelemetryTracingEndpoint};
 pub use propagate_header::{PropagateHeader, PropagateHeaderEndpoint};
+#[cfg(feature = "redis-ratelimit")]
+pub use rate_limit::RedisRateLimitStore;
+pub use rate_limit::{
+    MemoryRateLimitStore, Quota, RateLimit, RateLimitDecision, RateLimitEndpoint, RateLimitStore,
+};
+#[cfg(feature = "request-id")]
+pub use request_id::{RequestId, RequestIdFormat, SetRequestId, SetRequestIdEndpoint};
+#[cfg(feature = "security-headers")]
+pub use security_headers::{
+    ContentSecurityPolicy, CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy, CspNonce, CspSource,
+    FrameOptions, Hsts, ReferrerPolicy, SecurityHeaders, SecurityHeadersEndpoint,
+};
 pub use set_header::{SetHeader, SetHeaderEndpoint};
 pub use size_limit::{SizeLimit, SizeLimitEndpoint};
+pub use timeout::{Timeout, TimeoutEndpoint};
 #[cfg(feature = "tower-compat")]
 pub use tower_compat::TowerLayerCompatExt;
 pub use tracing_mw::{Tracing, TracingEndpoint};
@@ -0,0 +1,79 @@
+use std::{collections::HashMap, time::Instant};
+
+use parking_lot::Mutex;
+
+use super::{Quota, RateLimitDecision, RateLimitStore};
+use crate::Result;
+
+struct InnerStore {
+    /// The theoretical arrival time of each key, in microseconds since the
+    /// creation of the store.
+    tats: HashMap<String, u64>,
+    /// The number of keys above which the keys with an expired theoretical
+    /// arrival time are removed.
+    cleanup_threshold: usize,
+}
+
+/// A rate limiting store using memory.
+pub struct MemoryRateLimitStore {
+    start: Instant,
+    inner: Mutex<InnerStore>,
+}
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            inner: Mutex::new(InnerStore {
+                tats: HashMap::new(),
+                cleanup_threshold: 1024,
+            }),
+        }
+    }
+}
+
+impl MemoryRateLimitStore {
+    /// Create a `MemoryRateLimitStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn check(&self, key: &str, quota: &Quota) -> Result<RateLimitDecision> {
+        let now = self.start.elapsed().as_micros() as u64;
+        let mut inner = self.inner.lock();
+
+        let (decision, tat) = quota.gcra(now, inner.tats.get(key).copied());
+        if let Some(tat) = tat {
+            inner.tats.insert(key.to_string(), tat);
+        }
+
+        if inner.tats.len() > inner.cleanup_threshold {
+            // the quota of these keys is fully available
+            inner.tats.retain(|_, tat| *tat > now);
+            inner.cleanup_threshold = (inner.tats.len() * 2).max(1024);
+        }
+        Ok(decision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_store() {
+        let store = MemoryRateLimitStore::new();
+        let quota = Quota::new(1, Duration::from_millis(50));
+        assert!(store.check("a", &quota).await.unwrap().allowed);
+        assert!(!store.check("a", &quota).await.unwrap().allowed);
+        assert!(store.check("b", &quota).await.unwrap().allowed);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(store.check("a", &quota).await.unwrap().allowed);
+    }
+}
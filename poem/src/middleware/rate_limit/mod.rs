@@ -0,0 +1,343 @@
+mod memory_store;
+#[cfg(feature = "redis-ratelimit")]
+mod redis_store;
+
+use std::{sync::Arc, time::Duration};
+
+pub use memory_store::MemoryRateLimitStore;
+#[cfg(feature = "redis-ratelimit")]
+pub use redis_store::RedisRateLimitStore;
+
+use crate::{
+    endpoint::Endpoint,
+    http::{HeaderValue, StatusCode},
+    middleware::Middleware,
+    IntoResponse, Request, Response, Result,
+};
+
+/// The number of requests allowed in a period of time.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Quota {
+    limit: u32,
+    period: Duration,
+}
+
+impl Quota {
+    /// Creates a quota that allows `limit` requests per `period`, which are
+    /// all allowed in a burst.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` or `period` is zero.
+    pub fn new(limit: u32, period: Duration) -> Self {
+        assert!(limit > 0, "the limit of a quota must not be zero");
+        assert!(
+            period > Duration::ZERO,
+            "the period of a quota must not be zero"
+        );
+        Self { limit, period }
+    }
+
+    /// Creates a quota that allows `limit` requests per second.
+    pub fn per_second(limit: u32) -> Self {
+        Self::new(limit, Duration::from_secs(1))
+    }
+
+    /// Creates a quota that allows `limit` requests per minute.
+    pub fn per_minute(limit: u32) -> Self {
+        Self::new(limit, Duration::from_secs(60))
+    }
+
+    /// Creates a quota that allows `limit` requests per hour.
+    pub fn per_hour(limit: u32) -> Self {
+        Self::new(limit, Duration::from_secs(3600))
+    }
+
+    /// Returns the number of requests allowed in a period.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Returns the period.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the period and the time between two requests, in
+    /// microseconds.
+    fn micros(&self) -> (u64, u64) {
+        let period = self.period.as_micros() as u64;
+        (period, (period / self.limit as u64).max(1))
+    }
+
+    /// Applies the generic cell rate algorithm (GCRA) to a request made at
+    /// `now`, with the theoretical arrival time `tat` of the key, both in
+    /// microseconds.
+    ///
+    /// Returns the decision, and the new theoretical arrival time if the
+    /// request is allowed.
+    fn gcra(&self, now: u64, tat: Option<u64>) -> (RateLimitDecision, Option<u64>) {
+        let (period, interval) = self.micros();
+        let tat = tat.unwrap_or(now).max(now);
+        let new_tat = tat + interval;
+        if new_tat - now > period {
+            let decision = self.decision(false, tat - now, new_tat - now - period);
+            (decision, None)
+        } else {
+            (self.decision(true, new_tat - now, 0), Some(new_tat))
+        }
+    }
+
+    /// Creates the decision of a request from the time until the quota is
+    /// fully available, and the time until the next request is allowed, in
+    /// microseconds.
+    fn decision(&self, allowed: bool, reset: u64, retry_after: u64) -> RateLimitDecision {
+        let (period, interval) = self.micros();
+        RateLimitDecision {
+            allowed,
+            limit: self.limit,
+            remaining: (period.saturating_sub(reset) / interval).min(self.limit as u64) as u32,
+            reset: Duration::from_micros(reset),
+            retry_after: Duration::from_micros(retry_after),
+        }
+    }
+}
+
+/// The result of checking a request against a [`Quota`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimitDecision {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+    /// The number of requests allowed in a period.
+    pub limit: u32,
+    /// The number of requests that are still allowed now.
+    pub remaining: u32,
+    /// The time until the quota is fully available again.
+    pub reset: Duration,
+    /// The time until the next request is allowed, zero if the request is
+    /// allowed.
+    pub retry_after: Duration,
+}
+
+/// Represents a back-end storage of the [`RateLimit`] middleware.
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync + 'static {
+    /// Checks a request for a key against a quota, and records it if it is
+    /// allowed.
+    ///
+    /// This operation must be atomic, because several requests for the same
+    /// key can be checked at the same time.
+    async fn check(&self, key: &str, quota: &Quota) -> Result<RateLimitDecision>;
+}
+
+type KeyFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// Middleware for limiting the rate of requests with the generic cell rate
+/// algorithm (GCRA).
+///
+/// Requests are grouped by a key, which is the IP address of the remote peer
+/// by default, and can be computed from the request with [`RateLimit::key`].
+/// Requests without a key are not limited.
+///
+/// The `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers
+/// are added to the responses. Requests that exceed the quota are rejected with
+/// `429 Too Many Requests` and a `Retry-After` header.
+///
+/// The state is kept in a [`MemoryRateLimitStore`] by default, a store shared
+/// by several servers can be set with [`RateLimit::store`]. If the store
+/// fails, the request is allowed.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{Quota, RateLimit},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index() {}
+///
+/// let app = Route::new()
+///     .at("/", index)
+///     .with(RateLimit::new(Quota::per_minute(60)).key(|req| {
+///         req.headers()
+///             .get("x-api-key")
+///             .and_then(|value| value.to_str().ok())
+///             .map(ToString::to_string)
+///     }));
+/// ```
+pub struct RateLimit {
+    quota: Quota,
+    key: KeyFn,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimit {
+    /// Creates a new `RateLimit` middleware.
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            key: Arc::new(|req| {
+                req.remote_addr()
+                    .as_socket_addr()
+                    .map(|addr| addr.ip().to_string())
+            }),
+            store: Arc::new(MemoryRateLimitStore::new()),
+        }
+    }
+
+    /// Sets the function that computes the key of a request, requests are not
+    /// limited when it returns `None`.
+    pub fn key<F>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            key: Arc::new(f),
+            ..self
+        }
+    }
+
+    /// Sets the store of the rate limiting state.
+    pub fn store(self, store: impl RateLimitStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RateLimit {
+    type Output = RateLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint {
+            inner: ep,
+            quota: self.quota,
+            key: self.key.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Endpoint for RateLimit middleware.
+pub struct RateLimitEndpoint<E> {
+    inner: E,
+    quota: Quota,
+    key: KeyFn,
+    store: Arc<dyn RateLimitStore>,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for RateLimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let key = match (self.key)(&req) {
+            Some(key) => key,
+            None => return self.inner.call(req).await.into_response(),
+        };
+        let decision = match self.store.check(&key, &self.quota).await {
+            Ok(decision) => decision,
+            Err(err) => {
+                tracing::warn!(error = ?err, "failed to check the rate limit.");
+                return self.inner.call(req).await.into_response();
+            }
+        };
+
+        let mut resp = if decision.allowed {
+            self.inner.call(req).await.into_response()
+        } else {
+            let mut resp = Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .finish();
+            resp.headers_mut()
+                .insert("retry-after", ceil_secs(decision.retry_after));
+            resp
+        };
+
+        let headers = resp.headers_mut();
+        headers.insert("ratelimit-limit", decision.limit.into());
+        headers.insert("ratelimit-remaining", decision.remaining.into());
+        headers.insert("ratelimit-reset", ceil_secs(decision.reset));
+        resp
+    }
+}
+
+fn ceil_secs(duration: Duration) -> HeaderValue {
+    let secs = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    secs.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make_sync, EndpointExt};
+
+    #[test]
+    fn test_gcra() {
+        let quota = Quota::new(2, Duration::from_secs(1));
+
+        let (decision, tat) = quota.gcra(1_000_000, None);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 1);
+        assert_eq!(decision.reset, Duration::from_millis(500));
+        assert_eq!(tat, Some(1_500_000));
+
+        let (decision, tat) = quota.gcra(1_000_000, tat);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(tat, Some(2_000_000));
+
+        let (decision, _) = quota.gcra(1_200_000, tat);
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset, Duration::from_millis(800));
+        assert_eq!(decision.retry_after, Duration::from_millis(300));
+
+        let (decision, tat) = quota.gcra(1_500_000, tat);
+        assert!(decision.allowed);
+        assert_eq!(tat, Some(2_500_000));
+
+        let (decision, _) = quota.gcra(5_000_000, tat);
+        assert_eq!(decision.remaining, 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let ep = make_sync(|_| "hello").with(RateLimit::new(Quota::per_minute(2)).key(|req| {
+            req.headers()
+                .get("x-api-key")
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        }));
+        let request = |key: &'static str| Request::builder().header("x-api-key", key).finish();
+
+        let resp = ep.call(request("a")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("ratelimit-limit").unwrap(), "2");
+        assert_eq!(resp.headers().get("ratelimit-remaining").unwrap(), "1");
+        assert_eq!(resp.headers().get("ratelimit-reset").unwrap(), "30");
+
+        let resp = ep.call(request("a")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("ratelimit-remaining").unwrap(), "0");
+
+        let resp = ep.call(request("a")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get("ratelimit-remaining").unwrap(), "0");
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "30");
+
+        let resp = ep.call(request("b")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // requests without a key are not limited
+        for _ in 0..3 {
+            let resp = ep.call(Request::default()).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(resp.headers().get("ratelimit-limit").is_none());
+        }
+    }
+}
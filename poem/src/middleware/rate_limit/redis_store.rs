@@ -0,0 +1,105 @@
+use redis::{aio::ConnectionLike, Script};
+
+use super::{Quota, RateLimitDecision, RateLimitStore};
+use crate::{error::InternalServerError, Result};
+
+/// Applies the generic cell rate algorithm atomically, with the clock of the
+/// redis server, in microseconds.
+const GCRA_SCRIPT: &str = r#"
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local period = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local tat = tonumber(redis.call("GET", KEYS[1]) or now)
+if tat < now then
+    tat = now
+end
+local new_tat = tat + interval
+if new_tat - now > period then
+    return {0, tat - now, new_tat - now - period}
+end
+-- numbers are formatted explicitly, because their default conversion to
+-- strings loses precision
+local ttl = math.ceil((new_tat - now) / 1000)
+redis.call("SET", KEYS[1], string.format("%d", new_tat), "PX", string.format("%d", ttl))
+return {1, new_tat - now, 0}
+"#;
+
+/// A rate limiting store using redis, which can be shared by several
+/// servers.
+#[cfg_attr(docsrs, doc(cfg(feature = "redis-ratelimit")))]
+pub struct RedisRateLimitStore<T> {
+    connection: T,
+    prefix: String,
+    script: Script,
+}
+
+impl<T> RedisRateLimitStore<T> {
+    /// Create a `RedisRateLimitStore`.
+    pub fn new(connection: T) -> Self {
+        Self {
+            connection,
+            prefix: "ratelimit:".to_string(),
+            script: Script::new(GCRA_SCRIPT),
+        }
+    }
+
+    /// Sets the prefix of the redis keys, defaults to `ratelimit:`.
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ConnectionLike + Clone + Sync + Send + 'static> RateLimitStore for RedisRateLimitStore<T> {
+    async fn check(&self, key: &str, quota: &Quota) -> Result<RateLimitDecision> {
+        let (period, interval) = quota.micros();
+        let (allowed, reset, retry_after): (u8, u64, u64) = self
+            .script
+            .key(format!("{}{}", self.prefix, key))
+            .arg(period)
+            .arg(interval)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(InternalServerError)?;
+        Ok(quota.decision(allowed == 1, reset, retry_after))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use redis::{aio::ConnectionManager, Client, ConnectionLike};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn redis_store() {
+        let mut client = match Client::open("redis://127.0.0.1/") {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+        if !client.check_connection() {
+            return;
+        }
+
+        let store = RedisRateLimitStore::new(ConnectionManager::new(client).await.unwrap())
+            .prefix(format!("poem-test-{}:", std::process::id()));
+        let quota = Quota::new(2, Duration::from_millis(200));
+        let decision = store.check("a", &quota).await.unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 1);
+        assert!(store.check("a", &quota).await.unwrap().allowed);
+        let decision = store.check("a", &quota).await.unwrap();
+        assert!(!decision.allowed);
+        assert!(decision.retry_after > Duration::ZERO);
+        assert!(store.check("b", &quota).await.unwrap().allowed);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(store.check("a", &quota).await.unwrap().allowed);
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    endpoint::Endpoint,
+    http::{header::HeaderName, HeaderValue},
+    middleware::Middleware,
+    uuid::uuid_v4,
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+/// The maximum length of a request id sent by the client.
+const MAX_INCOMING_LEN: usize = 128;
+
+/// The id of the current request, set by the [`SetRequestId`] middleware.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::RequestId};
+///
+/// #[handler]
+/// fn index(request_id: &RequestId) -> String {
+///     format!("request id: {}", request_id)
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RequestId(pub String);
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for &'a RequestId {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self, Self::Error> {
+        Ok(req
+            .extensions()
+            .get::<RequestId>()
+            .expect("To use the `RequestId` extractor, the `SetRequestId` middleware is required."))
+    }
+}
+
+/// The format of the generated request ids.
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RequestIdFormat {
+    /// A random UUID (version 4), such as
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    Uuid,
+    /// A [ULID](https://github.com/ulid/spec), which is sortable by creation time, such as
+    /// `01ARZ3NDEKTSV4RRFFQ69G5FAV`.
+    Ulid,
+}
+
+impl RequestIdFormat {
+    fn generate(self) -> String {
+        match self {
+            RequestIdFormat::Uuid => uuid_v4(&mut thread_rng()),
+            RequestIdFormat::Ulid => {
+                let millis = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis())
+                    .unwrap_or_default();
+                let random = thread_rng().gen::<u128>() & ((1 << 80) - 1);
+                encode_ulid(((millis & ((1 << 48) - 1)) << 80) | random)
+            }
+        }
+    }
+}
+
+/// Encodes a ULID with the Crockford's base32 alphabet.
+fn encode_ulid(value: u128) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    (0..26)
+        .map(|i| ALPHABET[((value >> (125 - 5 * i)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// Middleware for identifying each request with an id.
+///
+/// The id is read from the `X-Request-Id` header of the request, and is
+/// generated when the header is missing or invalid. It is added to the
+/// request extensions, so it can be extracted with [`RequestId`], and is
+/// sent back in the `X-Request-Id` header of the response.
+///
+/// The id is also recorded in the `request_id` field of the span created by
+/// the [`Tracing`](crate::middleware::Tracing) middleware, so that the logs of
+/// a request can be found from the id reported by a client.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{RequestId, RequestIdFormat, SetRequestId, Tracing},
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index(request_id: &RequestId) -> String {
+///     request_id.to_string()
+/// }
+///
+/// let app = Route::new()
+///     .at("/", index)
+///     .with(Tracing)
+///     .with(SetRequestId::new().format(RequestIdFormat::Ulid));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+pub struct SetRequestId {
+    header_name: HeaderName,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+}
+
+impl Default for SetRequestId {
+    fn default() -> Self {
+        Self {
+            header_name: HeaderName::from_static("x-request-id"),
+            format: RequestIdFormat::Uuid,
+            trust_incoming: true,
+        }
+    }
+}
+
+impl SetRequestId {
+    /// Creates a new `SetRequestId` middleware.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the name of the header containing the request id, defaults to
+    /// `x-request-id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not a valid header name.
+    pub fn header_name(self, name: &str) -> Self {
+        Self {
+            header_name: HeaderName::try_from(name).expect("valid header name"),
+            ..self
+        }
+    }
+
+    /// Sets the format of the generated request ids, defaults to
+    /// [`RequestIdFormat::Uuid`].
+    pub fn format(self, format: RequestIdFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Sets whether to use the request id sent by the client, defaults to
+    /// `true`.
+    ///
+    /// It should be disabled when the server is directly exposed to the
+    /// clients, rather than behind a proxy that sets the request id.
+    pub fn trust_incoming(self, trust_incoming: bool) -> Self {
+        Self {
+            trust_incoming,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SetRequestId {
+    type Output = SetRequestIdEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        SetRequestIdEndpoint {
+            inner: ep,
+            header_name: self.header_name.clone(),
+            format: self.format,
+            trust_incoming: self.trust_incoming,
+        }
+    }
+}
+
+/// Endpoint for SetRequestId middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "request-id")))]
+pub struct SetRequestIdEndpoint<E> {
+    inner: E,
+    header_name: HeaderName,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+}
+
+fn is_valid_request_id(value: &HeaderValue) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_INCOMING_LEN
+        && value.as_bytes().iter().all(u8::is_ascii_graphic)
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for SetRequestIdEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let value = req
+            .headers()
+            .get(&self.header_name)
+            .filter(|value| self.trust_incoming && is_valid_request_id(value))
+            .cloned()
+            .unwrap_or_else(|| {
+                HeaderValue::from_str(&self.format.generate()).expect("valid request id")
+            });
+        let request_id = RequestId(value.to_str().unwrap_or_default().to_string());
+
+        tracing::Span::current().record("request_id", request_id.0.as_str());
+        req.headers_mut().insert(&self.header_name, value.clone());
+        req.extensions_mut().insert(request_id);
+
+        let mut resp = self.inner.call(req).await.into_response();
+        resp.headers_mut().insert(&self.header_name, value);
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Dispatch, Event, Metadata, Subscriber,
+    };
+    use tracing_core::span::Current;
+
+    use super::*;
+    use crate::{handler, http::StatusCode, middleware::Tracing, EndpointExt};
+
+    #[handler(internal)]
+    fn index(request_id: &RequestId) -> String {
+        request_id.to_string()
+    }
+
+    async fn call(ep: &impl Endpoint<Output = Response>, req: Request) -> (String, String) {
+        let resp = ep.call(req).await;
+        let header = resp
+            .headers()
+            .get("x-request-id")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        (header, resp.into_body().into_string().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn generate() {
+        let ep = index.with(SetRequestId::new());
+        let (header, body) = call(&ep, Request::default()).await;
+        assert_eq!(header, body);
+        assert_eq!(header.len(), 36);
+        assert_eq!(&header[14..15], "4");
+
+        let (other, _) = call(&ep, Request::default()).await;
+        assert_ne!(header, other);
+
+        let ep = index.with(SetRequestId::new().format(RequestIdFormat::Ulid));
+        let (header, body) = call(&ep, Request::default()).await;
+        assert_eq!(header, body);
+        assert_eq!(header.len(), 26);
+    }
+
+    #[tokio::test]
+    async fn incoming() {
+        let ep = index.with(SetRequestId::new());
+        let req = Request::builder()
+            .header("x-request-id", "abc-123")
+            .finish();
+        assert_eq!(
+            call(&ep, req).await,
+            ("abc-123".to_string(), "abc-123".to_string())
+        );
+
+        let req = Request::builder().header("x-request-id", "a b").finish();
+        let (header, _) = call(&ep, req).await;
+        assert_eq!(header.len(), 36);
+
+        let ep = index.with(
+            SetRequestId::new()
+                .header_name("x-trace-id")
+                .trust_incoming(false),
+        );
+        let req = Request::builder().header("x-trace-id", "abc-123").finish();
+        let resp = ep.call(req).await;
+        assert!(resp.headers().get("x-request-id").is_none());
+        let header = resp.headers().get("x-trace-id").unwrap().clone();
+        assert_ne!(header, "abc-123");
+        assert_eq!(resp.into_body().into_string().await.unwrap(), header);
+    }
+
+    /// A subscriber that records the `request_id` field of the spans.
+    #[derive(Default)]
+    struct SpanRecorder {
+        spans: Mutex<Vec<(&'static Metadata<'static>, Option<String>)>>,
+        stack: Mutex<Vec<Id>>,
+    }
+
+    struct RequestIdVisitor<'a>(&'a mut Option<String>);
+
+    impl Visit for RequestIdVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "request_id" {
+                *self.0 = Some(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+    }
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut request_id = None;
+            attrs.record(&mut RequestIdVisitor(&mut request_id));
+            let mut spans = self.spans.lock();
+            spans.push((attrs.metadata(), request_id));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock();
+            let (_, request_id) = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut RequestIdVisitor(request_id));
+        }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            self.stack.lock().push(span.clone());
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.stack.lock().pop();
+        }
+
+        fn current_span(&self) -> Current {
+            match self.stack.lock().last() {
+                Some(id) => {
+                    Current::new(id.clone(), self.spans.lock()[id.into_u64() as usize - 1].0)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    async fn traced_request_id(ep: impl Endpoint<Output = Response>) -> Option<String> {
+        let dispatch = Dispatch::new(SpanRecorder::default());
+        let _guard = tracing::dispatcher::set_default(&dispatch);
+        let req = Request::builder()
+            .header("x-request-id", "abc-123")
+            .finish();
+        assert_eq!(ep.call(req).await.status(), StatusCode::OK);
+
+        let recorder = dispatch.downcast_ref::<SpanRecorder>().unwrap();
+        let spans = recorder.spans.lock();
+        let mut requests = spans
+            .iter()
+            .filter(|(metadata, _)| metadata.name() == "request");
+        let (_, request_id) = requests.next().unwrap();
+        assert!(requests.next().is_none());
+        request_id.clone()
+    }
+
+    #[tokio::test]
+    async fn tracing_span() {
+        assert_eq!(
+            traced_request_id(index.with(Tracing).with(SetRequestId::new())).await,
+            Some("abc-123".to_string())
+        );
+        assert_eq!(
+            traced_request_id(index.with(SetRequestId::new()).with(Tracing)).await,
+            Some("abc-123".to_string())
+        );
+    }
+
+    #[test]
+    fn test_encode_ulid() {
+        assert_eq!(encode_ulid(0), "00000000000000000000000000");
+        assert_eq!(encode_ulid(u128::MAX), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+        assert_eq!(
+            encode_ulid(0x0156_3e3a_b5d3_d676_4c61_efb9_9302_bd5b),
+            "01ARZ3NDEKTSV4RRFFQ69G5FAV"
+        );
+    }
+}
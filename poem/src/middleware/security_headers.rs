@@ -0,0 +1,786 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use rand::{thread_rng, Rng};
+
+use crate::{
+    endpoint::Endpoint,
+    http::{header, header::HeaderName, HeaderValue},
+    middleware::Middleware,
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+};
+
+/// A random nonce generated by the [`SecurityHeaders`] middleware for each
+/// request, which allows the inline scripts and styles that carry it in the
+/// `nonce` attribute.
+///
+/// It implements `Display`, so it can be used directly in a template, such as
+/// a [`HtmlTemplate`](crate::web::HtmlTemplate).
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, middleware::CspNonce, web::Html};
+///
+/// #[handler]
+/// fn index(nonce: &CspNonce) -> Html<String> {
+///     Html(format!(
+///         r#"<script nonce="{}">console.log("hello")</script>"#,
+///         nonce
+///     ))
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CspNonce(pub String);
+
+impl CspNonce {
+    fn generate() -> Self {
+        Self(base64::encode_config(
+            thread_rng().gen::<[u8; 16]>(),
+            base64::URL_SAFE_NO_PAD,
+        ))
+    }
+}
+
+impl Display for CspNonce {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a> FromRequest<'a> for &'a CspNonce {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self, Self::Error> {
+        Ok(req.extensions().get::<CspNonce>().expect(
+            "To use the `CspNonce` extractor, the `SecurityHeaders` middleware is required.",
+        ))
+    }
+}
+
+/// A source of a [`ContentSecurityPolicy`] directive.
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CspSource {
+    /// `'none'`
+    None,
+    /// `'self'`
+    SelfOrigin,
+    /// `'unsafe-inline'`
+    UnsafeInline,
+    /// `'unsafe-eval'`
+    UnsafeEval,
+    /// `'strict-dynamic'`
+    StrictDynamic,
+    /// `'nonce-<value>'`, with the [`CspNonce`] of the request.
+    Nonce,
+    /// A host or a scheme, such as `https://cdn.example.com` or `data:`.
+    Host(String),
+}
+
+impl From<&str> for CspSource {
+    fn from(host: &str) -> Self {
+        CspSource::Host(host.to_string())
+    }
+}
+
+impl From<String> for CspSource {
+    fn from(host: String) -> Self {
+        CspSource::Host(host)
+    }
+}
+
+/// A `Content-Security-Policy` built from its directives.
+///
+/// # Example
+///
+/// ```
+/// use poem::middleware::{ContentSecurityPolicy, CspSource};
+///
+/// let policy = ContentSecurityPolicy::new()
+///     .default_src([CspSource::SelfOrigin])
+///     .script_src([CspSource::SelfOrigin, CspSource::Nonce])
+///     .img_src([CspSource::SelfOrigin, "https://images.example.com".into()])
+///     .upgrade_insecure_requests();
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<CspSource>)>,
+}
+
+macro_rules! define_directives {
+    ($($(#[$docs:meta])* ($method:ident, $name:literal)),*) => {
+        $(
+        $(#[$docs])*
+        pub fn $method<I, S>(self, sources: I) -> Self
+        where
+            I: IntoIterator<Item = S>,
+            S: Into<CspSource>,
+        {
+            self.directive($name, sources)
+        }
+        )*
+    };
+}
+
+impl ContentSecurityPolicy {
+    /// Creates an empty policy.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the sources of a directive, replacing the previous ones.
+    pub fn directive<I, S>(mut self, name: impl Into<String>, sources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<CspSource>,
+    {
+        let name = name.into();
+        let sources = sources.into_iter().map(Into::into).collect();
+        match self.directives.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, s)) => *s = sources,
+            None => self.directives.push((name, sources)),
+        }
+        self
+    }
+
+    define_directives!(
+        /// Sets the `default-src` directive.
+        (default_src, "default-src"),
+        /// Sets the `script-src` directive.
+        (script_src, "script-src"),
+        /// Sets the `style-src` directive.
+        (style_src, "style-src"),
+        /// Sets the `img-src` directive.
+        (img_src, "img-src"),
+        /// Sets the `connect-src` directive.
+        (connect_src, "connect-src"),
+        /// Sets the `font-src` directive.
+        (font_src, "font-src"),
+        /// Sets the `object-src` directive.
+        (object_src, "object-src"),
+        /// Sets the `media-src` directive.
+        (media_src, "media-src"),
+        /// Sets the `frame-src` directive.
+        (frame_src, "frame-src"),
+        /// Sets the `worker-src` directive.
+        (worker_src, "worker-src"),
+        /// Sets the `manifest-src` directive.
+        (manifest_src, "manifest-src"),
+        /// Sets the `base-uri` directive.
+        (base_uri, "base-uri"),
+        /// Sets the `form-action` directive.
+        (form_action, "form-action"),
+        /// Sets the `frame-ancestors` directive.
+        (frame_ancestors, "frame-ancestors")
+    );
+
+    /// Adds the `upgrade-insecure-requests` directive.
+    pub fn upgrade_insecure_requests(self) -> Self {
+        self.directive("upgrade-insecure-requests", Vec::<CspSource>::new())
+    }
+
+    /// Sets the `report-uri` directive.
+    pub fn report_uri(self, uri: impl Into<String>) -> Self {
+        self.directive("report-uri", [uri.into()])
+    }
+
+    fn uses_nonce(&self) -> bool {
+        self.directives
+            .iter()
+            .any(|(_, sources)| sources.contains(&CspSource::Nonce))
+    }
+
+    fn to_header_value(&self, nonce: Option<&CspNonce>) -> String {
+        let mut value = String::new();
+        for (name, sources) in &self.directives {
+            if !value.is_empty() {
+                value.push_str("; ");
+            }
+            value.push_str(name);
+            for source in sources {
+                value.push(' ');
+                match source {
+                    CspSource::None => value.push_str("'none'"),
+                    CspSource::SelfOrigin => value.push_str("'self'"),
+                    CspSource::UnsafeInline => value.push_str("'unsafe-inline'"),
+                    CspSource::UnsafeEval => value.push_str("'unsafe-eval'"),
+                    CspSource::StrictDynamic => value.push_str("'strict-dynamic'"),
+                    CspSource::Nonce => {
+                        if let Some(nonce) = nonce {
+                            value.push_str(&format!("'nonce-{}'", nonce));
+                        }
+                    }
+                    CspSource::Host(host) => value.push_str(host),
+                }
+            }
+        }
+        value
+    }
+}
+
+/// The `Strict-Transport-Security` header.
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Default for Hsts {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(365 * 24 * 60 * 60)).include_subdomains()
+    }
+}
+
+impl Hsts {
+    /// Creates a `Strict-Transport-Security` header with the specified
+    /// `max-age`.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+
+    /// Adds the `includeSubDomains` directive.
+    pub fn include_subdomains(self) -> Self {
+        Self {
+            include_subdomains: true,
+            ..self
+        }
+    }
+
+    /// Adds the `preload` directive.
+    pub fn preload(self) -> Self {
+        Self {
+            preload: true,
+            ..self
+        }
+    }
+
+    fn to_header_value(self) -> String {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        value
+    }
+}
+
+macro_rules! define_header_enum {
+    ($(#[$docs:meta])* $name:ident { $($(#[$variant_docs:meta])* $variant:ident => $value:literal),* $(,)? }) => {
+        $(#[$docs])*
+        #[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum $name {
+            $(
+            $(#[$variant_docs])*
+            $variant,
+            )*
+        }
+
+        impl $name {
+            fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $value,)*
+                }
+            }
+        }
+    };
+}
+
+define_header_enum!(
+    /// The `X-Frame-Options` header.
+    FrameOptions {
+        /// `DENY`
+        Deny => "DENY",
+        /// `SAMEORIGIN`
+        SameOrigin => "SAMEORIGIN",
+    }
+);
+
+define_header_enum!(
+    /// The `Referrer-Policy` header.
+    ReferrerPolicy {
+        /// `no-referrer`
+        NoReferrer => "no-referrer",
+        /// `no-referrer-when-downgrade`
+        NoReferrerWhenDowngrade => "no-referrer-when-downgrade",
+        /// `origin`
+        Origin => "origin",
+        /// `origin-when-cross-origin`
+        OriginWhenCrossOrigin => "origin-when-cross-origin",
+        /// `same-origin`
+        SameOrigin => "same-origin",
+        /// `strict-origin`
+        StrictOrigin => "strict-origin",
+        /// `strict-origin-when-cross-origin`
+        StrictOriginWhenCrossOrigin => "strict-origin-when-cross-origin",
+        /// `unsafe-url`
+        UnsafeUrl => "unsafe-url",
+    }
+);
+
+define_header_enum!(
+    /// The `Cross-Origin-Opener-Policy` header.
+    CrossOriginOpenerPolicy {
+        /// `same-origin`
+        SameOrigin => "same-origin",
+        /// `same-origin-allow-popups`
+        SameOriginAllowPopups => "same-origin-allow-popups",
+        /// `unsafe-none`
+        UnsafeNone => "unsafe-none",
+    }
+);
+
+define_header_enum!(
+    /// The `Cross-Origin-Embedder-Policy` header.
+    CrossOriginEmbedderPolicy {
+        /// `require-corp`
+        RequireCorp => "require-corp",
+        /// `credentialless`
+        Credentialless => "credentialless",
+        /// `unsafe-none`
+        UnsafeNone => "unsafe-none",
+    }
+);
+
+/// Middleware for setting the security related headers of the responses.
+///
+/// The defaults are:
+///
+/// | Header | Value |
+/// |--------|-------|
+/// | `Strict-Transport-Security` | `max-age=31536000; includeSubDomains` |
+/// | `Content-Security-Policy` | `default-src 'self'; script-src 'self' 'nonce-<nonce>'; object-src 'none'; base-uri 'self'; frame-ancestors 'self'` |
+/// | `X-Content-Type-Options` | `nosniff` |
+/// | `X-Frame-Options` | `SAMEORIGIN` |
+/// | `Referrer-Policy` | `strict-origin-when-cross-origin` |
+/// | `Permissions-Policy` | `camera=(), microphone=(), geolocation=()` |
+/// | `Cross-Origin-Opener-Policy` | `same-origin` |
+///
+/// `Cross-Origin-Embedder-Policy` is not set by default, because it blocks
+/// the cross-origin resources that do not opt in. Every header can be disabled
+/// by passing `None` to its method, and the headers already set by the
+/// endpoint are left unchanged.
+///
+/// A new [`CspNonce`] is generated for every request, and replaces the
+/// [`CspSource::Nonce`] sources of the policy.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     handler,
+///     middleware::{ContentSecurityPolicy, CspNonce, CspSource, Hsts, SecurityHeaders},
+///     web::Html,
+///     EndpointExt, Route,
+/// };
+///
+/// #[handler]
+/// fn index(nonce: &CspNonce) -> Html<String> {
+///     Html(format!(r#"<script nonce="{}"></script>"#, nonce))
+/// }
+///
+/// let app = Route::new().at("/", index).with(
+///     SecurityHeaders::new()
+///         .hsts(Hsts::default().preload())
+///         .content_security_policy(
+///             ContentSecurityPolicy::new()
+///                 .default_src([CspSource::SelfOrigin])
+///                 .script_src([CspSource::Nonce, CspSource::StrictDynamic]),
+///         )
+///         .cross_origin_embedder_policy(None),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+pub struct SecurityHeaders {
+    hsts: Option<Hsts>,
+    csp: Option<ContentSecurityPolicy>,
+    csp_report_only: bool,
+    content_type_options: bool,
+    frame_options: Option<FrameOptions>,
+    referrer_policy: Option<ReferrerPolicy>,
+    permissions_policy: Option<String>,
+    coop: Option<CrossOriginOpenerPolicy>,
+    coep: Option<CrossOriginEmbedderPolicy>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            hsts: Some(Hsts::default()),
+            csp: Some(
+                ContentSecurityPolicy::new()
+                    .default_src([CspSource::SelfOrigin])
+                    .script_src([CspSource::SelfOrigin, CspSource::Nonce])
+                    .object_src([CspSource::None])
+                    .base_uri([CspSource::SelfOrigin])
+                    .frame_ancestors([CspSource::SelfOrigin]),
+            ),
+            csp_report_only: false,
+            content_type_options: true,
+            frame_options: Some(FrameOptions::SameOrigin),
+            referrer_policy: Some(ReferrerPolicy::StrictOriginWhenCrossOrigin),
+            permissions_policy: Some("camera=(), microphone=(), geolocation=()".to_string()),
+            coop: Some(CrossOriginOpenerPolicy::SameOrigin),
+            coep: None,
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Creates a new `SecurityHeaders` middleware with the default headers.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the `Strict-Transport-Security` header.
+    pub fn hsts(self, hsts: impl Into<Option<Hsts>>) -> Self {
+        Self {
+            hsts: hsts.into(),
+            ..self
+        }
+    }
+
+    /// Sets the `Content-Security-Policy` header.
+    pub fn content_security_policy(self, csp: impl Into<Option<ContentSecurityPolicy>>) -> Self {
+        Self {
+            csp: csp.into(),
+            ..self
+        }
+    }
+
+    /// Sends the policy in the `Content-Security-Policy-Report-Only` header,
+    /// so that the violations are reported but not blocked.
+    pub fn csp_report_only(self) -> Self {
+        Self {
+            csp_report_only: true,
+            ..self
+        }
+    }
+
+    /// Sets whether to send the `X-Content-Type-Options: nosniff` header,
+    /// defaults to `true`.
+    pub fn content_type_options(self, enabled: bool) -> Self {
+        Self {
+            content_type_options: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the `X-Frame-Options` header.
+    pub fn frame_options(self, frame_options: impl Into<Option<FrameOptions>>) -> Self {
+        Self {
+            frame_options: frame_options.into(),
+            ..self
+        }
+    }
+
+    /// Sets the `Referrer-Policy` header.
+    pub fn referrer_policy(self, referrer_policy: impl Into<Option<ReferrerPolicy>>) -> Self {
+        Self {
+            referrer_policy: referrer_policy.into(),
+            ..self
+        }
+    }
+
+    /// Sets the `Permissions-Policy` header, such as `fullscreen=(self)`.
+    pub fn permissions_policy<'a>(self, policy: impl Into<Option<&'a str>>) -> Self {
+        Self {
+            permissions_policy: policy.into().map(ToString::to_string),
+            ..self
+        }
+    }
+
+    /// Sets the `Cross-Origin-Opener-Policy` header.
+    pub fn cross_origin_opener_policy(
+        self,
+        policy: impl Into<Option<CrossOriginOpenerPolicy>>,
+    ) -> Self {
+        Self {
+            coop: policy.into(),
+            ..self
+        }
+    }
+
+    /// Sets the `Cross-Origin-Embedder-Policy` header.
+    pub fn cross_origin_embedder_policy(
+        self,
+        policy: impl Into<Option<CrossOriginEmbedderPolicy>>,
+    ) -> Self {
+        Self {
+            coep: policy.into(),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for SecurityHeaders {
+    type Output = SecurityHeadersEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        let mut headers = Vec::new();
+        let mut push = |name: HeaderName, value: &str| {
+            headers.push((
+                name,
+                HeaderValue::from_str(value).expect("valid security header value"),
+            ))
+        };
+
+        if let Some(hsts) = self.hsts {
+            push(header::STRICT_TRANSPORT_SECURITY, &hsts.to_header_value());
+        }
+        if self.content_type_options {
+            push(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+        }
+        if let Some(frame_options) = self.frame_options {
+            push(header::X_FRAME_OPTIONS, frame_options.as_str());
+        }
+        if let Some(referrer_policy) = self.referrer_policy {
+            push(header::REFERRER_POLICY, referrer_policy.as_str());
+        }
+        if let Some(permissions_policy) = &self.permissions_policy {
+            push(
+                HeaderName::from_static("permissions-policy"),
+                permissions_policy,
+            );
+        }
+        if let Some(coop) = self.coop {
+            push(
+                HeaderName::from_static("cross-origin-opener-policy"),
+                coop.as_str(),
+            );
+        }
+        if let Some(coep) = self.coep {
+            push(
+                HeaderName::from_static("cross-origin-embedder-policy"),
+                coep.as_str(),
+            );
+        }
+
+        let csp = self.csp.clone().map(|csp| {
+            let name = if self.csp_report_only {
+                header::CONTENT_SECURITY_POLICY_REPORT_ONLY
+            } else {
+                header::CONTENT_SECURITY_POLICY
+            };
+            // the header value only depends on the request when it has a nonce
+            let value = match csp.uses_nonce() {
+                true => None,
+                false => Some(
+                    HeaderValue::from_str(&csp.to_header_value(None))
+                        .expect("valid content security policy"),
+                ),
+            };
+            (name, csp, value)
+        });
+
+        SecurityHeadersEndpoint {
+            inner: ep,
+            headers,
+            csp,
+        }
+    }
+}
+
+/// Endpoint for SecurityHeaders middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "security-headers")))]
+pub struct SecurityHeadersEndpoint<E> {
+    inner: E,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    csp: Option<(HeaderName, ContentSecurityPolicy, Option<HeaderValue>)>,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for SecurityHeadersEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let nonce = CspNonce::generate();
+        req.extensions_mut().insert(nonce.clone());
+
+        let mut resp = self.inner.call(req).await.into_response();
+        let headers = resp.headers_mut();
+        for (name, value) in &self.headers {
+            headers.entry(name).or_insert_with(|| value.clone());
+        }
+        if let Some((name, csp, value)) = &self.csp {
+            headers.entry(name).or_insert_with(|| match value {
+                Some(value) => value.clone(),
+                None => HeaderValue::from_str(&csp.to_header_value(Some(&nonce)))
+                    .expect("valid content security policy"),
+            });
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, EndpointExt};
+
+    #[handler(internal)]
+    fn index(nonce: &CspNonce) -> String {
+        nonce.to_string()
+    }
+
+    #[tokio::test]
+    async fn default_headers() {
+        let ep = index.with(SecurityHeaders::new());
+        let resp = ep.call(Request::default()).await;
+        let headers = resp.headers().clone();
+        let nonce = resp.into_body().into_string().await.unwrap();
+        assert_eq!(
+            base64::decode_config(&nonce, base64::URL_SAFE_NO_PAD)
+                .unwrap()
+                .len(),
+            16
+        );
+
+        assert_eq!(
+            headers.get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=31536000; includeSubDomains"
+        );
+        assert_eq!(
+            headers.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            &format!(
+                "default-src 'self'; script-src 'self' 'nonce-{}'; object-src 'none'; base-uri 'self'; frame-ancestors 'self'",
+                nonce
+            )
+        );
+        assert_eq!(
+            headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(headers.get(header::X_FRAME_OPTIONS).unwrap(), "SAMEORIGIN");
+        assert_eq!(
+            headers.get(header::REFERRER_POLICY).unwrap(),
+            "strict-origin-when-cross-origin"
+        );
+        assert_eq!(
+            headers.get("permissions-policy").unwrap(),
+            "camera=(), microphone=(), geolocation=()"
+        );
+        assert_eq!(
+            headers.get("cross-origin-opener-policy").unwrap(),
+            "same-origin"
+        );
+        assert!(headers.get("cross-origin-embedder-policy").is_none());
+
+        // the nonce changes for every request
+        let resp = ep.call(Request::default()).await;
+        assert_ne!(resp.into_body().into_string().await.unwrap(), nonce);
+    }
+
+    #[tokio::test]
+    async fn custom_headers() {
+        let ep = index.with(
+            SecurityHeaders::new()
+                .hsts(Hsts::new(Duration::from_secs(60)).preload())
+                .content_security_policy(
+                    ContentSecurityPolicy::new()
+                        .default_src([CspSource::SelfOrigin, "https://cdn.example.com".into()])
+                        .upgrade_insecure_requests(),
+                )
+                .csp_report_only()
+                .content_type_options(false)
+                .frame_options(FrameOptions::Deny)
+                .referrer_policy(None)
+                .permissions_policy(None)
+                .cross_origin_opener_policy(None)
+                .cross_origin_embedder_policy(CrossOriginEmbedderPolicy::RequireCorp),
+        );
+        let resp = ep.call(Request::default()).await;
+        let headers = resp.headers();
+
+        assert_eq!(
+            headers.get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=60; preload"
+        );
+        assert!(headers.get(header::CONTENT_SECURITY_POLICY).is_none());
+        assert_eq!(
+            headers
+                .get(header::CONTENT_SECURITY_POLICY_REPORT_ONLY)
+                .unwrap(),
+            "default-src 'self' https://cdn.example.com; upgrade-insecure-requests"
+        );
+        assert!(headers.get(header::X_CONTENT_TYPE_OPTIONS).is_none());
+        assert_eq!(headers.get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert!(headers.get(header::REFERRER_POLICY).is_none());
+        assert!(headers.get("permissions-policy").is_none());
+        assert!(headers.get("cross-origin-opener-policy").is_none());
+        assert_eq!(
+            headers.get("cross-origin-embedder-policy").unwrap(),
+            "require-corp"
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_endpoint_headers() {
+        let ep = crate::endpoint::make_sync(|_| {
+            Response::builder()
+                .header(header::X_FRAME_OPTIONS, "DENY")
+                .header(header::CONTENT_SECURITY_POLICY, "default-src 'none'")
+                .finish()
+        })
+        .with(SecurityHeaders::new());
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert_eq!(
+            resp.headers().get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'none'"
+        );
+    }
+
+    #[cfg(feature = "template")]
+    #[tokio::test]
+    async fn html_template() {
+        use askama::Template;
+
+        use crate::web::HtmlTemplate;
+
+        #[derive(Template)]
+        #[template(source = r#"<script nonce="{{ nonce }}"></script>"#, ext = "html")]
+        struct Page {
+            nonce: CspNonce,
+        }
+
+        #[handler(internal)]
+        fn page(nonce: &CspNonce) -> HtmlTemplate<Page> {
+            HtmlTemplate(Page {
+                nonce: nonce.clone(),
+            })
+        }
+
+        let resp = page
+            .with(SecurityHeaders::new())
+            .call(Request::default())
+            .await;
+        let csp = resp
+            .headers()
+            .get(header::CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = resp.into_body().into_string().await.unwrap();
+        let nonce = body
+            .strip_prefix(r#"<script nonce=""#)
+            .and_then(|s| s.strip_suffix(r#""></script>"#))
+            .unwrap();
+        assert!(csp.contains(&format!("'nonce-{}'", nonce)));
+    }
+}
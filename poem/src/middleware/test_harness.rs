@@ -0,0 +1,12 @@
+use std::time::Duration;
+
+use crate::{endpoint::make, Endpoint};
+
+/// An endpoint that responds with `done` after sleeping for the specified
+/// duration.
+pub(crate) fn sleep_endpoint(duration: Duration) -> impl Endpoint<Output = &'static str> {
+    make(move |_| async move {
+        tokio::time::sleep(duration).await;
+        "done"
+    })
+}
@@ -0,0 +1,260 @@
+use std::{
+    error::Error as StdError,
+    io::{Error as IoError, ErrorKind},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::stream;
+use hyper::body::HttpBody;
+
+use crate::{
+    endpoint::Endpoint, http::StatusCode, middleware::Middleware, Body, IntoResponse, Request,
+    Response,
+};
+
+/// The state of a [`Timeout`] middleware for a request, which is stored in the
+/// request extensions so that a nested `Timeout` can override it.
+#[derive(Default)]
+struct TimeoutState {
+    overridden: AtomicBool,
+    body_timed_out: AtomicBool,
+}
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+type TimeoutResponseFn = Arc<dyn Fn(StatusCode) -> Response + Send + Sync>;
+
+/// Middleware for limiting the time spent handling a request.
+///
+/// If the inner endpoint does not complete in time, its future is dropped and
+/// `503 Service Unavailable` is returned. A separate limit can be set for
+/// reading the request body with [`Timeout::read_body_timeout`], if the body
+/// is not read in time, reading it fails and `408 Request Timeout` is
+/// returned. These responses can be customized with [`Timeout::on_timeout`].
+///
+/// A `Timeout` applied to an endpoint overrides the limits of the `Timeout`
+/// middlewares applied to the routes that contain it.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{handler, middleware::Timeout, EndpointExt, Route};
+///
+/// #[handler]
+/// fn index() {}
+///
+/// #[handler]
+/// fn upload() {}
+///
+/// let app = Route::new()
+///     .at("/", index)
+///     .at(
+///         "/upload",
+///         upload.with(Timeout::new(Duration::from_secs(300))),
+///     )
+///     .with(Timeout::new(Duration::from_secs(10)).read_body_timeout(Duration::from_secs(5)));
+/// ```
+pub struct Timeout {
+    timeout: Duration,
+    read_body_timeout: Option<Duration>,
+    on_timeout: TimeoutResponseFn,
+}
+
+impl Timeout {
+    /// Creates a new `Timeout` middleware that limits the time spent handling
+    /// a request.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            read_body_timeout: None,
+            on_timeout: Arc::new(|status| status.into()),
+        }
+    }
+
+    /// Sets the time limit for reading the request body, which is measured
+    /// from the beginning of the request.
+    pub fn read_body_timeout(self, timeout: Duration) -> Self {
+        Self {
+            read_body_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets a function that creates the response returned when a time limit
+    /// is exceeded.
+    ///
+    /// The function receives `503 Service Unavailable` when the endpoint did
+    /// not complete in time, and `408 Request Timeout` when the request body
+    /// was not read in time.
+    pub fn on_timeout<F, R>(self, f: F) -> Self
+    where
+        F: Fn(StatusCode) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        Self {
+            on_timeout: Arc::new(move |status| f(status).into_response()),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Timeout {
+    type Output = TimeoutEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TimeoutEndpoint {
+            inner: ep,
+            timeout: self.timeout,
+            read_body_timeout: self.read_body_timeout,
+            on_timeout: self.on_timeout.clone(),
+        }
+    }
+}
+
+/// Endpoint for Timeout middleware.
+pub struct TimeoutEndpoint<E> {
+    inner: E,
+    timeout: Duration,
+    read_body_timeout: Option<Duration>,
+    on_timeout: TimeoutResponseFn,
+}
+
+#[async_trait::async_trait]
+impl<E: Endpoint> Endpoint for TimeoutEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Self::Output {
+        if let Some(parent) = req.extensions().get::<Arc<TimeoutState>>() {
+            parent.overridden.store(true, Ordering::SeqCst);
+        }
+        let state = Arc::new(TimeoutState::default());
+        req.extensions_mut().insert(state.clone());
+        if let Some(timeout) = self.read_body_timeout {
+            let body = req.take_body();
+            req.set_body(limit_body(body, timeout, state.clone()));
+        }
+
+        let fut = self.inner.call(req);
+        tokio::pin!(fut);
+        let resp = match tokio::time::timeout(self.timeout, &mut fut).await {
+            Ok(resp) => resp.into_response(),
+            // a nested `Timeout` limits the time spent by the endpoint
+            Err(_) if state.overridden.load(Ordering::SeqCst) => fut.await.into_response(),
+            Err(_) => return (self.on_timeout)(StatusCode::SERVICE_UNAVAILABLE),
+        };
+
+        if state.body_timed_out.load(Ordering::SeqCst) {
+            return (self.on_timeout)(StatusCode::REQUEST_TIMEOUT);
+        }
+        resp
+    }
+}
+
+/// Wraps a request body so that reading it fails after the specified
+/// duration, unless a nested `Timeout` has overridden it.
+fn limit_body(body: Body, timeout: Duration, state: Arc<TimeoutState>) -> Body {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let body: hyper::Body = body.into();
+
+    Body(hyper::Body::wrap_stream(stream::unfold(
+        (body, state, false),
+        move |(mut body, state, finished)| async move {
+            if finished {
+                return None;
+            }
+
+            let data = if state.overridden.load(Ordering::SeqCst) {
+                body.data().await
+            } else {
+                match tokio::time::timeout_at(deadline, body.data()).await {
+                    Ok(data) => data,
+                    Err(_) if state.overridden.load(Ordering::SeqCst) => body.data().await,
+                    Err(_) => {
+                        state.body_timed_out.store(true, Ordering::SeqCst);
+                        let err = IoError::new(ErrorKind::TimedOut, "request body read timed out");
+                        return Some((Err(err.into()), (body, state, true)));
+                    }
+                }
+            };
+
+            match data? {
+                Ok(data) => Some((Ok::<_, BoxError>(data), (body, state, false))),
+                Err(err) => Some((Err(err.into()), (body, state, true))),
+            }
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{endpoint::make, middleware::test_harness::sleep_endpoint, EndpointExt};
+
+    #[tokio::test]
+    async fn handler_timeout() {
+        let ep = sleep_endpoint(Duration::from_millis(200))
+            .with(Timeout::new(Duration::from_millis(20)));
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let ep = sleep_endpoint(Duration::from_millis(200)).with(
+            Timeout::new(Duration::from_millis(20))
+                .on_timeout(|status| (status, format!("timed out: {}", status.as_u16()))),
+        );
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "timed out: 503"
+        );
+
+        let ep =
+            sleep_endpoint(Duration::from_millis(1)).with(Timeout::new(Duration::from_secs(1)));
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "done");
+    }
+
+    #[tokio::test]
+    async fn override_timeout() {
+        let ep = sleep_endpoint(Duration::from_millis(100))
+            .with(Timeout::new(Duration::from_secs(1)))
+            .with(Timeout::new(Duration::from_millis(20)));
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "done");
+
+        let ep = sleep_endpoint(Duration::from_millis(200))
+            .with(Timeout::new(Duration::from_millis(20)))
+            .with(Timeout::new(Duration::from_secs(1)));
+        let resp = ep.call(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn read_body_timeout() {
+        let ep = make(|req| async move {
+            match req.into_body().into_string().await {
+                Ok(body) => body.into_response(),
+                Err(err) => err.into_response(),
+            }
+        })
+        .with(Timeout::new(Duration::from_secs(1)).read_body_timeout(Duration::from_millis(20)));
+
+        let (mut sender, body) = hyper::Body::channel();
+        tokio::spawn(async move {
+            sender.send_data("a".into()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let _ = sender.send_data("b".into()).await;
+        });
+        let resp = ep.call(Request::builder().body(Body(body))).await;
+        assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let resp = ep.call(Request::builder().body("abc")).await;
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "abc");
+    }
+}
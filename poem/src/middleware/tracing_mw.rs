@@ -5,6 +5,10 @@ use tracing::{Instrument, Level};
 use crate::{Endpoint, IntoResponse, Middleware, Request, Response};
 
 /// Middleware for [`tracing`](https://crates.io/crates/tracing).
+///
+/// With the `request-id` feature, the `request_id` field of the span is set
+/// to the id of the request when the
+/// [`SetRequestId`](crate::middleware::SetRequestId) middleware is used.
 #[derive(Default)]
 pub struct Tracing;
 
@@ -34,7 +38,12 @@ impl<E: Endpoint> Endpoint for TracingEndpoint<E> {
             version = ?req.version(),
             method = %req.method(),
             path = %req.uri(),
+            request_id = tracing::field::Empty,
         );
+        #[cfg(feature = "request-id")]
+        if let Some(request_id) = req.extensions().get::<crate::middleware::RequestId>() {
+            span.record("request_id", request_id.0.as_str());
+        }
 
         async move {
             let now = SystemTime::now();
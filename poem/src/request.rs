@@ -4,6 +4,7 @@ use std::{
     future::Future,
     io::Error,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -24,7 +25,7 @@ use crate::{
         headers::{Header, HeaderMapExt},
         LocalAddr, RemoteAddr,
     },
-    RequestBody,
+    Endpoint, RequestBody, Response,
 };
 
 #[derive(Default)]
@@ -33,6 +34,11 @@ pub(crate) struct RequestState {
     pub(crate) remote_addr: RemoteAddr,
     pub(crate) original_uri: Uri,
     pub(crate) match_params: PathParams,
+    /// The length of the path prefix that has been stripped by nested routes,
+    /// but not yet removed from the URI.
+    pub(crate) stripped_prefix_len: usize,
+    /// The fallback endpoint inherited from the parent of a nested route.
+    pub(crate) fallback: Option<Arc<dyn Endpoint<Output = Response>>>,
     #[cfg(feature = "cookie")]
     pub(crate) cookie_jar: Option<CookieJar>,
     pub(crate) on_upgrade: Mutex<Option<OnUpgrade>>,
@@ -114,6 +120,8 @@ impl From<(http::Request<hyper::Body>, LocalAddr, RemoteAddr)> for Request {
                 remote_addr,
                 original_uri: parts.uri,
                 match_params: Default::default(),
+                stripped_prefix_len: 0,
+                fallback: None,
                 #[cfg(feature = "cookie")]
                 cookie_jar: None,
                 on_upgrade,
@@ -216,12 +224,14 @@ impl Request {
     }
 
     /// Returns the path parameter with the specified `name`.
+    ///
+    /// The value is not percent-decoded.
     pub fn path_param(&self, name: &str) -> Option<&str> {
         self.state
             .match_params
-            .iter()
-            .find(|(key, _)| key == name)
-            .map(|(_, value)| value.as_str())
+            .iter(self.uri.path())
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
     }
 
     /// Returns the content type of this request.
@@ -305,6 +315,13 @@ impl Request {
         &mut self.state
     }
 
+    /// Returns the URI with a mutable reference to the state, so that the path
+    /// parameters can be matched against the path without copying it.
+    #[inline]
+    pub(crate) fn uri_and_state_mut(&mut self) -> (&Uri, &mut RequestState) {
+        (&self.uri, &mut self.state)
+    }
+
     /// Returns the parameters used by the extractor.
     pub fn split(mut self) -> (Request, RequestBody) {
         let body = self.take_body();
@@ -0,0 +1,123 @@
+use mime::Mime;
+
+/// Returns how specifically `range` matches `mime`, or `None` if it does not
+/// match at all.
+///
+/// `*/*` has the lowest specificity, `type/*` is more specific and
+/// `type/subtype` is the most specific.
+pub(crate) fn match_specificity(range: &Mime, mime: &Mime) -> Option<u8> {
+    if range.type_() == mime::STAR && range.subtype() == mime::STAR {
+        Some(0)
+    } else if range.type_() != mime.type_() {
+        None
+    } else if range.subtype() == mime::STAR {
+        Some(1)
+    } else if range.subtype() == mime.subtype() {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Parses the value of an `Accept` header into media ranges and their
+/// quality values, which are in the range `0..=1000`.
+///
+/// Invalid items are ignored.
+pub(crate) fn parse_accept(value: &str) -> Vec<(Mime, u16)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let mime = item.trim().parse::<Mime>().ok()?;
+            let quality = match mime.get_param("q") {
+                Some(q) => parse_quality(q.as_str())?,
+                None => 1000,
+            };
+            Some((mime, quality))
+        })
+        .collect()
+}
+
+/// Parses the value of a header with quality values other than `Accept`,
+/// such as `Accept-Encoding`, into its items and their quality values.
+///
+/// Items with an invalid quality value are ignored.
+#[cfg(any(feature = "staticfiles", feature = "embed"))]
+pub(crate) fn parse_quality_values(value: &str) -> Vec<(&str, u16)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let value = parts.next()?.trim();
+            let quality = match parts.find_map(|param| param.trim().strip_prefix("q=")) {
+                Some(q) => parse_quality(q.trim())?,
+                None => 1000,
+            };
+            Some((value, quality))
+        })
+        .filter(|(value, _)| !value.is_empty())
+        .collect()
+}
+
+fn parse_quality(value: &str) -> Option<u16> {
+    let q = value.parse::<f32>().ok()?;
+    if (0.0..=1.0).contains(&q) {
+        Some((q * 1000.0).round() as u16)
+    } else {
+        None
+    }
+}
+
+/// Returns the quality value of `mime` according to the media ranges of an
+/// `Accept` header.
+///
+/// The quality of the most specific matching range is used, and `0` is
+/// returned if no range matches.
+pub(crate) fn accept_quality(accept: &[(Mime, u16)], mime: &Mime) -> u16 {
+    accept
+        .iter()
+        .filter_map(|(range, quality)| {
+            match_specificity(range, mime).map(|specificity| (specificity, *quality))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, quality)| quality)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_accept() {
+        assert_eq!(
+            parse_accept("text/html, application/json;q=0.5, */*;q=0.1, invalid, a/b;q=2"),
+            vec![
+                (mime::TEXT_HTML, 1000),
+                ("application/json;q=0.5".parse().unwrap(), 500),
+                ("*/*;q=0.1".parse().unwrap(), 100),
+            ]
+        );
+    }
+
+    #[cfg(any(feature = "staticfiles", feature = "embed"))]
+    #[test]
+    fn test_parse_quality_values() {
+        assert_eq!(
+            parse_quality_values("gzip;q=0.5, br , deflate;q=2, , identity;q=0"),
+            vec![("gzip", 500), ("br", 1000), ("identity", 0)]
+        );
+    }
+
+    #[test]
+    fn test_accept_quality() {
+        let accept = parse_accept("text/*;q=0.3, text/html;q=0.7, */*;q=0.5");
+        assert_eq!(accept_quality(&accept, &mime::TEXT_HTML), 700);
+        assert_eq!(accept_quality(&accept, &mime::TEXT_PLAIN), 300);
+        assert_eq!(accept_quality(&accept, &mime::APPLICATION_JSON), 500);
+
+        let accept = parse_accept("application/json, text/html;q=0");
+        assert_eq!(accept_quality(&accept, &mime::APPLICATION_JSON), 1000);
+        assert_eq!(accept_quality(&accept, &mime::TEXT_HTML), 0);
+        assert_eq!(accept_quality(&accept, &mime::IMAGE_PNG), 0);
+    }
+}
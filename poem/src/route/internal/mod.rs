@@ -1,2 +1,4 @@
+pub(crate) mod media_type;
+pub(crate) mod param_types;
 pub(crate) mod radix_tree;
 pub(crate) mod trie;
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+/// A function that returns the length of the matched prefix of a path
+/// segment, or `None` if it does not match.
+pub(crate) type ParamMatchFn = Arc<dyn Fn(&str) -> Option<usize> + Send + Sync>;
+
+/// The named parameter types that can be used in path patterns, such as
+/// `:id<int>`.
+#[derive(Clone, Default)]
+pub(crate) struct ParamTypes {
+    custom: HashMap<String, ParamMatchFn>,
+}
+
+impl ParamTypes {
+    pub(crate) fn register(&mut self, name: String, f: ParamMatchFn) {
+        self.custom.insert(name, f);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<ParamMatchFn> {
+        if let Some(f) = self.custom.get(name) {
+            return Some(f.clone());
+        }
+
+        let f: fn(&str) -> Option<usize> = match name {
+            "int" | "i64" => match_number::<i64>,
+            "i32" => match_number::<i32>,
+            "u64" => match_number::<u64>,
+            "u32" => match_number::<u32>,
+            "uuid" => match_uuid,
+            "slug" => match_slug,
+            _ => return None,
+        };
+        Some(Arc::new(f))
+    }
+}
+
+fn non_empty(len: usize) -> Option<usize> {
+    if len > 0 {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+fn match_number<T: FromStr>(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let sign = matches!(bytes.first(), Some(b'-')) as usize;
+    let digits = bytes[sign..]
+        .iter()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    let len = non_empty(digits)? + sign;
+    s[..len].parse::<T>().ok().map(|_| len)
+}
+
+fn match_uuid(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes().get(..36)?;
+    for (i, c) in bytes.iter().enumerate() {
+        let valid = match i {
+            8 | 13 | 18 | 23 => *c == b'-',
+            _ => c.is_ascii_hexdigit(),
+        };
+        if !valid {
+            return None;
+        }
+    }
+    Some(36)
+}
+
+fn match_slug(s: &str) -> Option<usize> {
+    non_empty(
+        s.bytes()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == b'-' || *c == b'_')
+            .count(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_number() {
+        assert_eq!(match_number::<i64>("123"), Some(3));
+        assert_eq!(match_number::<i64>("-123.json"), Some(4));
+        assert_eq!(match_number::<i64>("-"), None);
+        assert_eq!(match_number::<i64>("abc"), None);
+        assert_eq!(match_number::<u64>("-1"), None);
+        assert_eq!(match_number::<u32>("4294967295"), Some(10));
+        assert_eq!(match_number::<u32>("4294967296"), None);
+        assert_eq!(match_number::<i32>("-2147483648"), Some(11));
+    }
+
+    #[test]
+    fn test_match_uuid() {
+        assert_eq!(match_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8"), Some(36));
+        assert_eq!(
+            match_uuid("67E55044-10B1-426F-9247-BB680E5FE0C8.json"),
+            Some(36)
+        );
+        assert_eq!(match_uuid("67e55044-10b1-426f-9247-bb680e5fe0c"), None);
+        assert_eq!(match_uuid("67e55044x10b1-426f-9247-bb680e5fe0c8"), None);
+        assert_eq!(match_uuid("67e55044-10b1-426f-9247-bb680e5fe0cg"), None);
+    }
+
+    #[test]
+    fn test_match_slug() {
+        assert_eq!(match_slug("hello-world_2"), Some(13));
+        assert_eq!(match_slug("hello.html"), Some(5));
+        assert_eq!(match_slug(".html"), None);
+    }
+
+    #[test]
+    fn test_custom_param_type() {
+        let mut types = ParamTypes::default();
+        assert!(types.get("hex").is_none());
+        types.register(
+            "hex".to_string(),
+            Arc::new(|s: &str| non_empty(s.bytes().take_while(u8::is_ascii_hexdigit).count())),
+        );
+        assert_eq!((types.get("hex").unwrap())("ff0x"), Some(3));
+
+        types.register("int".to_string(), Arc::new(|_: &str| None));
+        assert_eq!((types.get("int").unwrap())("1"), None);
+    }
+}
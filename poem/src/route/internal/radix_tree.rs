@@ -1,4 +1,8 @@
-use std::fmt::{self, Debug, Formatter};
+use std::{
+    fmt::{self, Debug, Formatter},
+    ops::Range,
+    sync::Arc,
+};
 
 use nom::{
     branch::alt,
@@ -11,6 +15,8 @@ use nom::{
 use regex::bytes::Regex;
 use smallvec::SmallVec;
 
+use super::param_types::{ParamMatchFn, ParamTypes};
+
 fn longest_common_prefix(a: &[u8], b: &[u8]) -> usize {
     a.iter().zip(b).take_while(|(a, b)| **a == **b).count()
 }
@@ -27,7 +33,7 @@ enum Segment<'a> {
     Static(&'a [u8]),
     Param(&'a [u8]),
     CatchAll(&'a [u8]),
-    Regex(Option<&'a [u8]>, PathRegex),
+    Regex(Option<&'a [u8]>, PathMatcher),
 }
 
 fn find_slash(path: &[u8]) -> Option<usize> {
@@ -74,34 +80,64 @@ enum NodeType {
     Regex,
 }
 
-struct PathRegex {
-    re_str: String,
-    re: Regex,
+enum MatcherKind {
+    Regex(Regex),
+    Func(ParamMatchFn),
+}
+
+/// Matches the text in angle brackets, which is either the name of a
+/// parameter type such as `int`, or a regular expression.
+struct PathMatcher {
+    source: String,
+    kind: MatcherKind,
 }
 
-impl PathRegex {
-    fn new(re_bytes: &[u8]) -> Option<Self> {
-        let re_str = std::str::from_utf8(re_bytes).ok()?;
-        Some(PathRegex {
-            re_str: re_str.to_string(),
-            re: Regex::new(re_str).ok()?,
+impl PathMatcher {
+    fn new(source: &[u8], types: &ParamTypes) -> Option<Self> {
+        let source = std::str::from_utf8(source).ok()?;
+        let kind = match types.get(source) {
+            Some(f) => MatcherKind::Func(f),
+            None => MatcherKind::Regex(Regex::new(&format!("^(?:{})", source)).ok()?),
+        };
+        Some(PathMatcher {
+            source: source.to_string(),
+            kind,
         })
     }
+
+    /// Returns the length of the matched prefix of `path`.
+    fn matches(&self, path: &[u8]) -> Option<usize> {
+        match &self.kind {
+            MatcherKind::Regex(re) => re.find(path).map(|m| m.end()),
+            MatcherKind::Func(f) => {
+                let segment = match find_slash(path) {
+                    Some(pos) => &path[..pos],
+                    None => path,
+                };
+                let len = f(std::str::from_utf8(segment).ok()?)?;
+                if len > 0 && len <= segment.len() {
+                    Some(len)
+                } else {
+                    None
+                }
+            }
+        }
+    }
 }
 
-impl Debug for PathRegex {
+impl Debug for PathMatcher {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("PathRegex").field(&self.re_str).finish()
+        f.debug_tuple("PathMatcher").field(&self.source).finish()
     }
 }
 
-impl PartialEq for PathRegex {
+impl PartialEq for PathMatcher {
     fn eq(&self, other: &Self) -> bool {
-        self.re_str.eq(&other.re_str)
+        self.source.eq(&other.source)
     }
 }
 
-impl Eq for PathRegex {}
+impl Eq for PathMatcher {}
 
 #[derive(Debug, Eq, PartialEq)]
 struct Node<T> {
@@ -109,7 +145,7 @@ struct Node<T> {
     name: Vec<u8>,
     children: Vec<Node<T>>,
     indices: Vec<u8>,
-    re: Option<PathRegex>,
+    re: Option<PathMatcher>,
     param_child: Option<Box<Node<T>>>,
     catch_all_child: Option<Box<Node<T>>>,
     regex_child: Option<Box<Node<T>>>,
@@ -154,7 +190,7 @@ impl<T> Node<T> {
                         re: None,
                         param_child: child.param_child.take(),
                         catch_all_child: child.catch_all_child.take(),
-                        regex_child: None,
+                        regex_child: child.regex_child.take(),
                         data: child.data.take(),
                     };
 
@@ -256,7 +292,7 @@ impl<T> Node<T> {
         &mut self,
         segments: Vec<Segment<'_>>,
         name: Option<&[u8]>,
-        re: PathRegex,
+        re: PathMatcher,
         data: T,
     ) -> bool {
         let child = match &mut self.regex_child {
@@ -283,10 +319,10 @@ impl<T> Node<T> {
         child.insert_child(segments, data)
     }
 
-    fn matches<'a: 'b, 'b>(
+    fn matches<'a, 'b>(
         &'a self,
         path: &'b [u8],
-        params: &mut SmallVec<[(&'b [u8], &'b [u8]); 8]>,
+        params: &mut SmallVec<[(&'a [u8], &'b [u8]); 8]>,
     ) -> Option<&'a T> {
         if path.is_empty() {
             return if let Some(catch_all_child) = &self.catch_all_child {
@@ -310,8 +346,8 @@ impl<T> Node<T> {
 
         params.truncate(num_params);
         if let Some(regex_child) = &self.regex_child {
-            if let Some(captures) = regex_child.re.as_ref().unwrap().re.captures(path) {
-                let value = &path[..captures[0].len()];
+            if let Some(len) = regex_child.re.as_ref().unwrap().matches(path) {
+                let value = &path[..len];
                 if !regex_child.name.is_empty() {
                     params.push((&regex_child.name, value));
                 }
@@ -343,11 +379,166 @@ impl<T> Node<T> {
     }
 }
 
-pub(crate) type PathParams = Vec<(String, String)>;
+#[derive(Debug, Eq, PartialEq)]
+enum PatternToken {
+    Byte(u8),
+    Param(Vec<u8>),
+    CatchAll(Vec<u8>),
+    Regex(Option<Vec<u8>>, Vec<u8>),
+}
+
+/// A parsed path pattern, used to check whether two routes conflict before
+/// they are added to the tree.
+#[derive(Debug)]
+pub(crate) struct PathPattern {
+    tokens: Vec<PatternToken>,
+}
+
+impl PathPattern {
+    pub(crate) fn parse(path: &str, types: &ParamTypes) -> Option<Self> {
+        let mut tokens = Vec::new();
+        for segment in parse_path_segments(path.as_bytes())? {
+            match segment {
+                RawSegment::Static(value) => {
+                    tokens.extend(value.iter().copied().map(PatternToken::Byte))
+                }
+                RawSegment::Param(name) => tokens.push(PatternToken::Param(name.to_vec())),
+                RawSegment::CatchAll(name) => tokens.push(PatternToken::CatchAll(name.to_vec())),
+                RawSegment::Regex(name, re_bytes) => {
+                    PathMatcher::new(re_bytes, types)?;
+                    tokens.push(PatternToken::Regex(
+                        name.map(<[u8]>::to_vec),
+                        re_bytes.to_vec(),
+                    ));
+                }
+            }
+        }
+        Some(Self { tokens })
+    }
+
+    /// Returns the names of the parameters captured by the pattern, in the
+    /// order of [`Matches::params`].
+    pub(crate) fn param_names(&self) -> Vec<Arc<str>> {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                PatternToken::Param(name)
+                | PatternToken::CatchAll(name)
+                | PatternToken::Regex(Some(name), _) => {
+                    Some(Arc::from(String::from_utf8_lossy(name)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns `true` if adding both patterns to the same tree would cause one
+    /// of them to replace, rename or shadow the other.
+    ///
+    /// This is the case for identical patterns, for patterns that capture the
+    /// same segment with different names or different regular expressions,
+    /// and for patterns that are otherwise the same but capture a segment with
+    /// a parameter in one and with a regular expression in the other.
+    pub(crate) fn conflicts_with(&self, other: &PathPattern) -> bool {
+        let mut a = self.tokens.iter();
+        let mut b = other.tokens.iter();
+
+        loop {
+            match (a.next(), b.next()) {
+                (None, None) => return true,
+                (Some(PatternToken::Byte(x)), Some(PatternToken::Byte(y))) if x == y => {}
+                (Some(PatternToken::Param(x)), Some(PatternToken::Param(y))) => {
+                    if x != y {
+                        return true;
+                    }
+                }
+                (Some(PatternToken::Regex(x, re_x)), Some(PatternToken::Regex(y, re_y))) => {
+                    if x != y || re_x != re_y {
+                        return true;
+                    }
+                }
+                // a parameter and a regular expression are different nodes of the
+                // tree, but they can match the same segment
+                (Some(PatternToken::Param(_)), Some(PatternToken::Regex(_, _)))
+                | (Some(PatternToken::Regex(_, _)), Some(PatternToken::Param(_))) => {}
+                (Some(PatternToken::CatchAll(_)), Some(PatternToken::CatchAll(_))) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// The value of a path parameter.
+#[derive(Debug)]
+pub(crate) enum ParamValue {
+    /// The range of the value in the path of the request URI.
+    Path(Range<usize>),
+    /// A value that is not in the path of the request URI, such as a label of
+    /// the host, or a parameter in a prefix that has been removed from the
+    /// URI.
+    Owned(String),
+}
+
+impl ParamValue {
+    /// Returns the value, `path` is the path of the request URI.
+    pub(crate) fn as_str<'a>(&'a self, path: &'a str) -> &'a str {
+        match self {
+            ParamValue::Path(range) => path.get(range.clone()).unwrap_or_default(),
+            ParamValue::Owned(value) => value,
+        }
+    }
+}
+
+/// The parameters matched by the routers of a request.
+///
+/// The values matched in the path are stored as ranges, so that matching does
+/// not allocate. The ranges are updated when a prefix is removed from the URI
+/// with [`PathParams::strip_prefix`].
+#[derive(Debug, Default)]
+pub(crate) struct PathParams(SmallVec<[(Arc<str>, ParamValue); 8]>);
+
+impl PathParams {
+    pub(crate) fn push(&mut self, name: Arc<str>, value: ParamValue) {
+        self.0.push((name, value));
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<(Arc<str>, ParamValue)> {
+        self.0.pop()
+    }
+
+    /// Returns an iterator over the names and values of the parameters, `path`
+    /// is the path of the request URI.
+    pub(crate) fn iter<'a>(&'a self, path: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.0
+            .iter()
+            .map(move |(name, value)| (&**name, value.as_str(path)))
+    }
+
+    /// Updates the ranges after the first `prefix_len` bytes of `path` have
+    /// been removed from the URI, and `offset` bytes have been inserted at the
+    /// start of the new path.
+    pub(crate) fn strip_prefix(&mut self, path: &str, prefix_len: usize, offset: usize) {
+        for (_, value) in &mut self.0 {
+            if let ParamValue::Path(range) = value {
+                *value = if range.start >= prefix_len {
+                    ParamValue::Path(
+                        range.start - prefix_len + offset..range.end - prefix_len + offset,
+                    )
+                } else {
+                    ParamValue::Owned(path.get(range.clone()).unwrap_or_default().to_string())
+                };
+            }
+        }
+    }
+}
+
+/// The parameters captured by a match, which borrow their names from the tree
+/// and their values from the path.
+pub(crate) type MatchParams<'a, 'b> = SmallVec<[(&'a str, &'b str); 8]>;
 
 #[derive(Debug, Eq, PartialEq)]
-pub(crate) struct Matches<'a, T> {
-    pub(crate) params: PathParams,
+pub(crate) struct Matches<'a, 'b, T> {
+    pub(crate) params: MatchParams<'a, 'b>,
     pub(crate) data: &'a T,
 }
 
@@ -375,7 +566,12 @@ impl<T> Default for RadixTree<T> {
 }
 
 impl<T> RadixTree<T> {
+    #[cfg(test)]
     pub(crate) fn add(&mut self, path: &str, data: T) -> bool {
+        self.add_with_types(path, data, &ParamTypes::default())
+    }
+
+    pub(crate) fn add_with_types(&mut self, path: &str, data: T, types: &ParamTypes) -> bool {
         let raw_segments = match parse_path_segments(path.as_bytes()) {
             Some(raw_segments) => raw_segments,
             None => return false,
@@ -388,7 +584,7 @@ impl<T> RadixTree<T> {
                 RawSegment::Param(name) => Segment::Param(name),
                 RawSegment::CatchAll(name) => Segment::CatchAll(name),
                 RawSegment::Regex(name, re_bytes) => {
-                    if let Some(re) = PathRegex::new(re_bytes) {
+                    if let Some(re) = PathMatcher::new(re_bytes, types) {
                         Segment::Regex(name, re)
                     } else {
                         return false;
@@ -402,26 +598,17 @@ impl<T> RadixTree<T> {
         self.root.insert_child(segments, data)
     }
 
-    pub(crate) fn matches(&self, path: &str) -> Option<Matches<T>> {
-        let mut params = SmallVec::default();
-
-        match self.root.matches(path.as_bytes(), &mut params) {
-            Some(data) => {
-                let mut params2 = Vec::with_capacity(params.len());
-                for (name, value) in params {
-                    if let (Ok(name), Ok(value)) =
-                        (std::str::from_utf8(name), std::str::from_utf8(value))
-                    {
-                        params2.push((name.to_string(), value.to_string()));
-                    }
-                }
-                Some(Matches {
-                    params: params2,
-                    data,
-                })
+    pub(crate) fn matches<'a, 'b>(&'a self, path: &'b str) -> Option<Matches<'a, 'b, T>> {
+        let mut raw_params = SmallVec::new();
+        let data = self.root.matches(path.as_bytes(), &mut raw_params)?;
+
+        let mut params = SmallVec::new();
+        for (name, value) in raw_params {
+            if let (Ok(name), Ok(value)) = (std::str::from_utf8(name), std::str::from_utf8(value)) {
+                params.push((name, value));
             }
-            None => None,
         }
+        Some(Matches { params, data })
     }
 }
 
@@ -847,7 +1034,9 @@ mod tests {
                                 name: b"name".to_vec(),
                                 children: vec![],
                                 indices: vec![],
-                                re: Some(PathRegex::new(b"\\d+").unwrap()),
+                                re: Some(
+                                    PathMatcher::new(b"\\d+", &ParamTypes::default()).unwrap()
+                                ),
                                 param_child: None,
                                 catch_all_child: None,
                                 regex_child: None,
@@ -874,7 +1063,7 @@ mod tests {
                                 data: Some(1),
                             }],
                             indices: vec![b'/'],
-                            re: Some(PathRegex::new(b"\\d+").unwrap()),
+                            re: Some(PathMatcher::new(b"\\d+", &ParamTypes::default()).unwrap()),
                             param_child: None,
                             catch_all_child: None,
                             regex_child: None,
@@ -909,16 +1098,51 @@ mod tests {
         assert!(!tree.add("/k/h/:name<\\d>+", 2));
     }
 
-    fn create_url_params<I, K, V>(values: I) -> PathParams
-    where
-        I: IntoIterator<Item = (K, V)>,
-        K: Into<String>,
-        V: Into<String>,
-    {
-        values
-            .into_iter()
-            .map(|(k, v)| (k.into(), v.into()))
-            .collect()
+    #[test]
+    fn test_split_keeps_regex_child() {
+        let mut tree = RadixTree::default();
+        assert!(tree.add("/abc/<\\d+>", 1));
+        assert!(tree.add("/ab", 2));
+        assert_eq!(tree.matches("/abc/123").map(|m| *m.data), Some(1));
+        assert_eq!(tree.matches("/ab").map(|m| *m.data), Some(2));
+    }
+
+    #[test]
+    fn test_path_pattern_conflicts() {
+        fn conflicts(a: &str, b: &str) -> bool {
+            let a = PathPattern::parse(a, &ParamTypes::default()).unwrap();
+            let b = PathPattern::parse(b, &ParamTypes::default()).unwrap();
+            assert_eq!(a.conflicts_with(&b), b.conflicts_with(&a));
+            a.conflicts_with(&b)
+        }
+
+        assert!(PathPattern::parse("/a/:", &ParamTypes::default()).is_none());
+        assert!(PathPattern::parse("/a/<(>", &ParamTypes::default()).is_none());
+
+        assert!(conflicts("/a/b", "/a/b"));
+        assert!(conflicts("/a/:id", "/a/:name"));
+        assert!(conflicts("/a/:id/b", "/a/:name/c"));
+        assert!(conflicts("/a/:id", "/a/:name<\\d+>"));
+        assert!(conflicts("/a/:id", "/a/:id<\\d+>"));
+        assert!(conflicts("/a/:id", "/a/<\\d+>"));
+        assert!(conflicts("/a/:id/foo", "/a/:name<\\d+>/foo"));
+        assert!(conflicts("/a/:id<\\d+>", "/a/:id<[a-z]+>"));
+        assert!(conflicts("/a/<\\d+>", "/a/:id<\\d+>"));
+        assert!(conflicts("/a/*p", "/a/*q"));
+        assert!(conflicts("/a/:id/*p", "/a/:id/*p"));
+
+        assert!(!conflicts("/a/b", "/a/bc"));
+        assert!(!conflicts("/a/b", "/a/:id"));
+        assert!(!conflicts("/a/:id", "/a/:id/b"));
+        assert!(!conflicts("/a/:id/foo", "/a/:name<\\d+>/bar"));
+        assert!(!conflicts("/a/:id/foo", "/a/<\\d+>/bar"));
+        assert!(!conflicts("/a/:id<\\d+>/b", "/a/:id<\\d+>/c"));
+        assert!(!conflicts("/a/*p", "/a/:id"));
+        assert!(!conflicts("/a/*p", "/a"));
+    }
+
+    fn create_url_params<'a>(values: Vec<(&'a str, &'a str)>) -> MatchParams<'a, 'a> {
+        values.into_iter().collect()
     }
 
     #[test]
@@ -946,14 +1170,14 @@ mod tests {
             (
                 "/ab/def",
                 Some(Matches {
-                    params: vec![],
+                    params: create_url_params(vec![]),
                     data: &1,
                 }),
             ),
             (
                 "/abc/def",
                 Some(Matches {
-                    params: vec![],
+                    params: create_url_params(vec![]),
                     data: &2,
                 }),
             ),
@@ -1009,7 +1233,7 @@ mod tests {
             (
                 "/abc/123/def",
                 Some(Matches {
-                    params: vec![],
+                    params: create_url_params(vec![]),
                     data: &10,
                 }),
             ),
@@ -1026,4 +1250,61 @@ mod tests {
             assert_eq!(tree.matches(path), res);
         }
     }
+
+    #[test]
+    fn test_matches_param_types() {
+        let mut types = ParamTypes::default();
+        types.register(
+            "hex".to_string(),
+            std::sync::Arc::new(|s: &str| {
+                let len = s.bytes().take_while(u8::is_ascii_hexdigit).count();
+                if len > 0 {
+                    Some(len)
+                } else {
+                    None
+                }
+            }),
+        );
+
+        let mut tree = RadixTree::default();
+        tree.add_with_types("/users/:id<int>", 1, &types);
+        tree.add_with_types("/users/:name", 2, &types);
+        tree.add_with_types("/files/:id<uuid>.json", 3, &types);
+        tree.add_with_types("/colors/:color<hex>", 4, &types);
+        tree.add_with_types("/re/<\\d+>", 5, &types);
+        // breaking change: a name of a type is no longer a regular expression,
+        // which can still be written with a non-capturing group
+        tree.add_with_types("/names/:name<slug>", 6, &types);
+        tree.add_with_types("/names/v2/:name<(?:slug)>", 7, &types);
+
+        let matches = vec![
+            ("/users/-12", Some((1, vec![("id", "-12")]))),
+            ("/users/12a", Some((2, vec![("name", "12a")]))),
+            (
+                "/users/99999999999999999999",
+                Some((2, vec![("name", "99999999999999999999")])),
+            ),
+            (
+                "/files/67e55044-10b1-426f-9247-bb680e5fe0c8.json",
+                Some((3, vec![("id", "67e55044-10b1-426f-9247-bb680e5fe0c8")])),
+            ),
+            ("/files/67e55044.json", None),
+            ("/colors/ff00aa", Some((4, vec![("color", "ff00aa")]))),
+            ("/colors/red", None),
+            ("/re/123", Some((5, vec![]))),
+            ("/re/a123", None),
+            ("/names/hello-world", Some((6, vec![("name", "hello-world")]))),
+            ("/names/v2/slug", Some((7, vec![("name", "slug")]))),
+            ("/names/v2/hello", None),
+        ];
+
+        for (path, res) in matches {
+            assert_eq!(
+                tree.matches(path).map(|m| (*m.data, m.params)),
+                res.map(|(data, params)| (data, create_url_params(params))),
+                "{}",
+                path
+            );
+        }
+    }
 }
@@ -68,32 +68,44 @@ impl<T> Trie<T> {
         }
     }
 
-    pub(crate) fn matches(&self, domain: &str) -> Option<&T> {
+    /// Returns the data of the matched pattern, and the labels matched by
+    /// each `+` of the pattern, from right to left.
+    pub(crate) fn matches<'a, 'b>(&'a self, domain: &'b str) -> Option<(&'a T, Vec<&'b str>)> {
+        let mut captures = Vec::new();
         if domain.is_empty() {
-            return self.root.star_child.as_ref();
+            return self.root.star_child.as_ref().map(|data| (data, captures));
         }
         let segments = domain.split('.').rev().collect::<Vec<_>>();
-        Self::internal_matches(&segments, &self.root)
+        Self::internal_matches(&segments, &self.root, &mut captures).map(|data| (data, captures))
     }
 
-    fn internal_matches<'a>(segments: &[&str], parent_node: &'a Node<T>) -> Option<&'a T> {
+    fn internal_matches<'a, 'b>(
+        segments: &[&'b str],
+        parent_node: &'a Node<T>,
+        captures: &mut Vec<&'b str>,
+    ) -> Option<&'a T> {
         let (segment, tail) = match segments.split_first() {
             Some((segment, tail)) => (*segment, tail),
             None => return parent_node.data.as_ref(),
         };
 
+        let num_captures = captures.len();
+
         if let Some(node) = parent_node.named_children.get(segment) {
-            if let Some(data) = Self::internal_matches(tail, node) {
+            if let Some(data) = Self::internal_matches(tail, node, captures) {
                 return Some(data);
             }
         }
 
+        captures.truncate(num_captures);
         if let Some(plus_child) = &parent_node.plus_child {
-            if let Some(data) = Self::internal_matches(tail, plus_child) {
+            captures.push(segment);
+            if let Some(data) = Self::internal_matches(tail, plus_child, captures) {
                 return Some(data);
             }
         }
 
+        captures.truncate(num_captures);
         if let Some(data) = &parent_node.star_child {
             return Some(data);
         }
@@ -245,7 +257,28 @@ mod tests {
         ];
 
         for (domain, id) in matches {
-            assert_eq!(tree.matches(domain), id);
+            assert_eq!(tree.matches(domain).map(|(data, _)| data), id);
+        }
+    }
+
+    #[test]
+    fn test_captures() {
+        let mut tree = Trie::default();
+        tree.add("+.example.com", 1);
+        tree.add("+.+.com", 2);
+        tree.add("www.+.org", 3);
+        tree.add("*", 4);
+
+        let matches = vec![
+            ("a.example.com", Some((&1, vec!["a"]))),
+            ("a.b.com", Some((&2, vec!["b", "a"]))),
+            ("www.rust.org", Some((&3, vec!["rust"]))),
+            ("a.rust.org", Some((&4, vec![]))),
+            ("", Some((&4, vec![]))),
+        ];
+
+        for (domain, res) in matches {
+            assert_eq!(tree.matches(domain), res);
         }
     }
 }
@@ -1,16 +1,25 @@
 //! Route object and DSL
 
-mod internal;
+pub(crate) mod internal;
 mod router;
+mod router_accept;
+mod router_content_type;
 mod router_domain;
+mod router_header;
 mod router_method;
 
-pub(crate) use internal::radix_tree::PathParams;
+pub(crate) use internal::radix_tree::{ParamValue, PathParams};
 #[allow(unreachable_pub)]
 pub use router::Route;
 #[allow(unreachable_pub)]
+pub use router_accept::RouteAccept;
+#[allow(unreachable_pub)]
+pub use router_content_type::RouteContentType;
+#[allow(unreachable_pub)]
 pub use router_domain::RouteDomain;
 #[allow(unreachable_pub)]
+pub use router_header::RouteHeader;
+#[allow(unreachable_pub)]
 pub use router_method::{
     connect, delete, get, head, options, patch, post, put, trace, RouteMethod,
 };
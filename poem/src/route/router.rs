@@ -1,19 +1,44 @@
-use std::{str::FromStr, sync::Arc};
+use std::{any::Any, panic::Location, str::FromStr, sync::Arc};
 
 use http::StatusCode;
 use regex::Regex;
 
 use crate::{
     endpoint::BoxEndpoint,
+    error::RouteError,
     http::{uri::PathAndQuery, Uri},
-    route::internal::radix_tree::RadixTree,
+    route::internal::{
+        param_types::ParamTypes,
+        radix_tree::{ParamValue, PathParams, PathPattern, RadixTree},
+    },
     Endpoint, EndpointExt, IntoEndpoint, IntoResponse, Request, Response,
 };
 
+struct RouteTarget {
+    ep: BoxEndpoint<'static, Response>,
+    nested: bool,
+    /// The names of the parameters captured by the route, in the order of the
+    /// values matched by the radix tree.
+    param_names: Vec<Arc<str>>,
+}
+
+struct RouteEntry {
+    /// The path displayed in the errors.
+    path: String,
+    /// The pattern passed to the radix tree, or the pattern of a route of a
+    /// nested endpoint, prefixed with the nest path.
+    pattern_str: String,
+    pattern: PathPattern,
+    location: &'static Location<'static>,
+}
+
 /// Routing object
 #[derive(Default)]
 pub struct Route {
-    tree: RadixTree<BoxEndpoint<'static, Response>>,
+    tree: RadixTree<RouteTarget>,
+    entries: Vec<RouteEntry>,
+    param_types: ParamTypes,
+    fallback: Option<Arc<dyn Endpoint<Output = Response>>>,
 }
 
 impl Route {
@@ -61,7 +86,9 @@ impl Route {
     ///     // match regex
     ///     .at("/d/<\\d+>", get(a))
     ///     // capture with regex
-    ///     .at("/e/:name<\\d+>", get(a));
+    ///     .at("/e/:name<\\d+>", get(a))
+    ///     // capture with a parameter type
+    ///     .at("/f/:id<int>", get(a));
     ///
     /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
     /// // /a/b
@@ -99,19 +126,273 @@ impl Route {
     /// assert_eq!(resp.status(), StatusCode::OK);
     /// # });
     /// ```
+    ///
+    /// # Parameter types
+    ///
+    /// The following names can be used in angle brackets instead of a regular
+    /// expression, and are matched without the overhead of a regex:
+    ///
+    /// | Name            | Matches                                        |
+    /// |-----------------|------------------------------------------------|
+    /// | `int` / `i64`   | A decimal integer that fits in an `i64`        |
+    /// | `i32`           | A decimal integer that fits in an `i32`        |
+    /// | `u64`           | A decimal unsigned integer that fits in a `u64`|
+    /// | `u32`           | A decimal unsigned integer that fits in a `u32`|
+    /// | `uuid`          | A hyphenated UUID                              |
+    /// | `slug`          | ASCII letters, digits, `-` and `_`             |
+    ///
+    /// More types can be registered with [`Route::param_type`].
+    ///
+    /// A name of a type is never parsed as a regular expression, so a route
+    /// that matches the text of a name, such as the text `int`, must use a
+    /// non-capturing group: `:id<(?:int)>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is invalid, or conflicts with a route that was
+    /// added earlier, including the routes of the nested endpoints. Two paths
+    /// conflict if they are identical, or if they capture the same segment
+    /// with different parameter names or different regular expressions. Use
+    /// [`Route::try_at`] to handle the error instead.
     #[must_use]
-    pub fn at<E>(mut self, path: impl AsRef<str>, ep: E) -> Self
+    #[track_caller]
+    pub fn at<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.tree.add(
-            &normalize_path(path.as_ref()),
-            Box::new(ep.into_endpoint().map_to_response()),
-        );
+        match self.try_at(path, ep) {
+            Ok(route) => route,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like [`Route::at`], but returns an error if the path is invalid or
+    /// conflicts with a route that was added earlier.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{error::RouteError, handler, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let res = Route::new()
+    ///     .try_at("/users/:id", index)
+    ///     .and_then(|route| route.try_at("/users/:name", index));
+    /// assert!(matches!(res, Err(RouteError::Conflict { .. })));
+    /// ```
+    #[track_caller]
+    pub fn try_at<E>(mut self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let path = normalize_path(path.as_ref());
+        self.add_routes(
+            &path,
+            Location::caller(),
+            false,
+            vec![(path.clone(), Box::new(ep.into_endpoint().map_to_response()))],
+            Vec::new(),
+        )
+        .map_err(|mut errors| errors.remove(0))?;
+        Ok(self)
+    }
+
+    /// Registers a parameter type that can be used in the paths added after
+    /// this call, such as `:color<hex>`.
+    ///
+    /// The matcher is called with the current path segment, and returns the
+    /// length of the prefix it accepts, or `None` if it does not match. A
+    /// registered type replaces a built-in type with the same name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     handler,
+    ///     http::{StatusCode, Uri},
+    ///     web::Path,
+    ///     Endpoint, Request, Route,
+    /// };
+    ///
+    /// #[handler]
+    /// fn color(Path(color): Path<String>) -> String {
+    ///     color
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .param_type("hex", |s: &str| {
+    ///         let len = s.bytes().take_while(u8::is_ascii_hexdigit).count();
+    ///         if len > 0 {
+    ///             Some(len)
+    ///         } else {
+    ///             None
+    ///         }
+    ///     })
+    ///     .at("/colors/:color<hex>", color);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = app
+    ///     .call(
+    ///         Request::builder()
+    ///             .uri(Uri::from_static("/colors/ff00aa"))
+    ///             .finish(),
+    ///     )
+    ///     .await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "ff00aa");
+    ///
+    /// let resp = app
+    ///     .call(
+    ///         Request::builder()
+    ///             .uri(Uri::from_static("/colors/red"))
+    ///             .finish(),
+    ///     )
+    ///     .await;
+    /// assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn param_type<F>(mut self, name: impl Into<String>, matcher: F) -> Self
+    where
+        F: Fn(&str) -> Option<usize> + Send + Sync + 'static,
+    {
+        self.param_types.register(name.into(), Arc::new(matcher));
+        self
+    }
+
+    /// Sets the endpoint used when no route matches, instead of returning
+    /// `404 Not Found`.
+    ///
+    /// The fallback is also used by nested `Route` objects that have no
+    /// fallback of their own, so a nested API or single-page application can
+    /// override it for the paths under its prefix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::make_sync,
+    ///     http::{StatusCode, Uri},
+    ///     Endpoint, Request, Response, Route,
+    /// };
+    ///
+    /// let app = Route::new()
+    ///     .at("/a", make_sync(|_| "a"))
+    ///     .nest("/api", Route::new().at("/b", make_sync(|_| "b")))
+    ///     .nest("/spa", Route::new().fallback(make_sync(|_| "index.html")))
+    ///     .fallback(make_sync(|_| {
+    ///         Response::builder()
+    ///             .status(StatusCode::NOT_FOUND)
+    ///             .body("custom 404")
+    ///     }));
+    ///
+    /// async fn do_request(app: &Route, uri: &'static str) -> String {
+    ///     app.call(Request::builder().uri(Uri::from_static(uri)).finish())
+    ///         .await
+    ///         .into_body()
+    ///         .into_string()
+    ///         .await
+    ///         .unwrap()
+    /// }
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// assert_eq!(do_request(&app, "/a").await, "a");
+    /// assert_eq!(do_request(&app, "/b").await, "custom 404");
+    /// assert_eq!(do_request(&app, "/api/c").await, "custom 404");
+    /// assert_eq!(do_request(&app, "/spa/users/1").await, "index.html");
+    /// # });
+    /// ```
+    #[must_use]
+    pub fn fallback<E>(mut self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.fallback = Some(Arc::new(ep.into_endpoint().map_to_response()));
         self
     }
 
+    /// Adds the routes of an endpoint, and records the routes of a nested
+    /// endpoint to detect the conflicts with the routes added later.
+    ///
+    /// Returns every conflict found, and adds nothing if there is one.
+    fn add_routes(
+        &mut self,
+        path: &str,
+        location: &'static Location<'static>,
+        nested: bool,
+        routes: Vec<(String, BoxEndpoint<'static, Response>)>,
+        nested_routes: Vec<String>,
+    ) -> Result<(), Vec<RouteError>> {
+        let mut new_entries = Vec::with_capacity(routes.len() + nested_routes.len());
+        for (pattern_str, _) in &routes {
+            match PathPattern::parse(pattern_str, &self.param_types) {
+                Some(pattern) => new_entries.push(RouteEntry {
+                    path: path.to_string(),
+                    pattern_str: pattern_str.clone(),
+                    pattern,
+                    location,
+                }),
+                None => {
+                    return Err(vec![RouteError::InvalidPath {
+                        path: path.to_string(),
+                        location,
+                    }])
+                }
+            }
+        }
+        // the nested endpoint may use parameter types that are only registered
+        // in it, the conflicts of these routes cannot be detected
+        new_entries.extend(nested_routes.into_iter().filter_map(|pattern_str| {
+            let pattern = PathPattern::parse(&pattern_str, &self.param_types)?;
+            Some(RouteEntry {
+                path: pattern_str
+                    .strip_suffix("/*--poem-rest")
+                    .unwrap_or(&pattern_str)
+                    .to_string(),
+                pattern_str,
+                pattern,
+                location,
+            })
+        }));
+
+        let mut errors = Vec::new();
+        for new_entry in &new_entries {
+            for entry in &self.entries {
+                if !entry.pattern.conflicts_with(&new_entry.pattern) {
+                    continue;
+                }
+                let err = RouteError::Conflict {
+                    path: new_entry.path.clone(),
+                    location,
+                    existing_path: entry.path.clone(),
+                    existing_location: entry.location,
+                };
+                if !errors.contains(&err) {
+                    errors.push(err);
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        for ((pattern_str, ep), entry) in routes.into_iter().zip(&new_entries) {
+            let target = RouteTarget {
+                ep,
+                nested,
+                param_names: entry.pattern.param_names(),
+            };
+            self.tree
+                .add_with_types(&pattern_str, target, &self.param_types);
+        }
+        self.entries.extend(new_entries);
+        Ok(())
+    }
+
     /// Nest a `Endpoint` to the specified path and strip the prefix.
     ///
     /// # Example
@@ -142,13 +423,50 @@ impl Route {
     /// assert_eq!(resp.into_body().into_string().await.unwrap(), "hello");
     /// # });
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path conflicts with a route that was added earlier, or
+    /// if a route of the nested endpoint does when it is a [`Route`], see
+    /// [`Route::at`]. Use [`Route::try_nest`] to handle the errors instead.
     #[must_use]
+    #[track_caller]
     pub fn nest<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.internal_nest(&normalize_path(path.as_ref()), ep, true)
+        self.internal_nest(&normalize_path(path.as_ref()), ep, true, Location::caller())
+            .unwrap_or_else(|errors| panic_route_errors(&errors))
+    }
+
+    /// Like [`Route::nest`], but returns every conflict between the routes
+    /// of this object and the routes of the nested endpoint.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{error::RouteError, handler, Route};
+    ///
+    /// #[handler]
+    /// fn index() {}
+    ///
+    /// let users = Route::new().at("/", index).at("/:id", index);
+    /// let errors = Route::new()
+    ///     .at("/users", index)
+    ///     .at("/users/:name", index)
+    ///     .try_nest("/users", users)
+    ///     .err()
+    ///     .unwrap();
+    /// assert_eq!(errors.len(), 2);
+    /// ```
+    #[track_caller]
+    pub fn try_nest<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, Vec<RouteError>>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.internal_nest(&normalize_path(path.as_ref()), ep, true, Location::caller())
     }
 
     /// Nest a `Endpoint` to the specified path, but do not strip the prefix.
@@ -181,21 +499,79 @@ impl Route {
     /// assert_eq!(resp.into_body().into_string().await.unwrap(), "hello");
     /// # });
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path conflicts with a route that was added earlier, or
+    /// if a route of the nested endpoint does when it is a [`Route`], see
+    /// [`Route::at`]. Use [`Route::try_nest_no_strip`] to handle the errors instead.
     #[must_use]
+    #[track_caller]
     pub fn nest_no_strip<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.internal_nest(&normalize_path(path.as_ref()), ep, false)
+        self.internal_nest(
+            &normalize_path(path.as_ref()),
+            ep,
+            false,
+            Location::caller(),
+        )
+        .unwrap_or_else(|errors| panic_route_errors(&errors))
+    }
+
+    /// Like [`Route::nest_no_strip`], but returns every conflict between the
+    /// routes of this object and the routes of the nested endpoint.
+    #[track_caller]
+    pub fn try_nest_no_strip<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, Vec<RouteError>>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.internal_nest(
+            &normalize_path(path.as_ref()),
+            ep,
+            false,
+            Location::caller(),
+        )
     }
 
-    fn internal_nest<E>(mut self, path: &str, ep: E, strip: bool) -> Self
+    fn internal_nest<E>(
+        mut self,
+        path: &str,
+        ep: E,
+        strip: bool,
+        location: &'static Location<'static>,
+    ) -> Result<Self, Vec<RouteError>>
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        let ep = Arc::new(ep.into_endpoint());
+        let ep = ep.into_endpoint();
+        let prefix = if strip {
+            path.trim_end_matches('/')
+        } else {
+            ""
+        };
+        // the conflicts can only be detected with the routes of a nested `Route`,
+        // not when it is wrapped by a middleware
+        let nested_route = (&ep as &dyn Any).downcast_ref::<Route>();
+        let nested_routes = nested_route
+            .map(|route| {
+                route
+                    .entries
+                    .iter()
+                    .map(|entry| format!("{}{}", prefix, entry.pattern_str))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // a `Route` wrapped by a middleware still works, but the middleware
+        // must see the stripped URI, so it is rewritten before calling it
+        let inner_is_route = nested_route.is_some();
+
+        let ep = Arc::new(ep);
+        let display_path = path.to_string();
         let mut path = path.to_string();
         if !path.ends_with('/') {
             path.push('/');
@@ -204,7 +580,8 @@ impl Route {
         struct Nest<T> {
             inner: T,
             root: bool,
-            prefix_len: usize,
+            strip: bool,
+            inner_is_route: bool,
         }
 
         #[async_trait::async_trait]
@@ -212,25 +589,25 @@ impl Route {
             type Output = Response;
 
             async fn call(&self, mut req: Request) -> Self::Output {
-                if !self.root {
-                    let idx = req.state().match_params.len() - 1;
-                    let (name, _) = req.state_mut().match_params.remove(idx);
-                    assert_eq!(name, "--poem-rest");
-                }
-
-                let new_uri = {
-                    let uri = std::mem::take(req.uri_mut());
-                    let mut uri_parts = uri.into_parts();
-                    let path =
-                        &uri_parts.path_and_query.as_ref().unwrap().as_str()[self.prefix_len..];
-                    uri_parts.path_and_query = Some(if !path.starts_with('/') {
-                        PathAndQuery::from_str(&format!("/{}", path)).unwrap()
-                    } else {
-                        PathAndQuery::from_str(path).unwrap()
-                    });
-                    Uri::from_parts(uri_parts).unwrap()
+                let rest_len = if !self.root {
+                    let (name, rest) = req.state_mut().match_params.pop().unwrap();
+                    assert_eq!(&*name, "--poem-rest");
+                    rest.as_str(req.uri().path()).len() + 1
+                } else {
+                    0
                 };
-                *req.uri_mut() = new_uri;
+
+                // A nested `Route` matches against the stripped path directly, so
+                // the URI only needs to be rebuilt once for the final endpoint.
+                if self.strip {
+                    // an empty path is matched as `/`
+                    let matched_len =
+                        (req.uri().path().len() - req.state().stripped_prefix_len).max(1);
+                    req.state_mut().stripped_prefix_len += matched_len - rest_len;
+                }
+                if !self.inner_is_route {
+                    strip_uri_prefix(&mut req);
+                }
                 self.inner.call(req).await.into_response()
             }
         }
@@ -240,46 +617,128 @@ impl Route {
             "wildcards are not allowed in the nest path."
         );
 
-        let prefix_len = match strip {
-            false => 0,
-            true => path.len() - 1,
-        };
-        self.tree.add(
-            &format!("{}*--poem-rest", path),
+        let mut routes: Vec<(String, BoxEndpoint<'static, Response>)> = vec![(
+            format!("{}*--poem-rest", path),
             Box::new(Nest {
                 inner: ep.clone(),
                 root: false,
-                prefix_len,
-            }),
-        );
-        self.tree.add(
-            &path[..path.len() - 1],
-            Box::new(Nest {
-                inner: ep,
-                root: true,
-                prefix_len,
+                strip,
+                inner_is_route,
             }),
-        );
+        )];
+        if path.len() > 1 {
+            routes.push((
+                path[..path.len() - 1].to_string(),
+                Box::new(Nest {
+                    inner: ep,
+                    root: true,
+                    strip,
+                    inner_is_route,
+                }),
+            ));
+        }
+        self.add_routes(&display_path, location, true, routes, nested_routes)?;
+        Ok(self)
+    }
+}
 
-        self
+impl Route {
+    /// Matches the part of `full_path` after the stripped prefix, and pushes
+    /// the ranges of the parameters in `full_path` to `params`.
+    fn match_path(
+        &self,
+        full_path: &str,
+        stripped_prefix_len: usize,
+        params: &mut PathParams,
+    ) -> Option<&RouteTarget> {
+        let path = full_path
+            .get(stripped_prefix_len..)
+            .filter(|path| !path.is_empty())
+            .unwrap_or("/");
+        let matches = self.tree.matches(path)?;
+
+        // the values borrow from `path`, so their ranges in the full path are
+        // computed from the offsets of the pointers
+        for (name, (_, value)) in matches.data.param_names.iter().zip(&matches.params) {
+            let start = (value.as_ptr() as usize - path.as_ptr() as usize + stripped_prefix_len)
+                .min(full_path.len());
+            let end = (start + value.len()).min(full_path.len());
+            params.push(name.clone(), ParamValue::Path(start..end));
+        }
+        Some(matches.data)
     }
 }
 
+#[track_caller]
+fn panic_route_errors(errors: &[RouteError]) -> ! {
+    let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    panic!("{}", errors.join("\n"))
+}
+
 #[async_trait::async_trait]
 impl Endpoint for Route {
     type Output = Response;
 
     async fn call(&self, mut req: Request) -> Self::Output {
-        match self.tree.matches(req.uri().path()) {
-            Some(matches) => {
-                req.state_mut().match_params.extend(matches.params);
-                matches.data.call(req).await
+        let (uri, state) = req.uri_and_state_mut();
+        let matched = self.match_path(
+            uri.path(),
+            state.stripped_prefix_len,
+            &mut state.match_params,
+        );
+
+        let target = match matched {
+            Some(matched) => matched,
+            None => {
+                let inherited = req.state_mut().fallback.take();
+                let fallback = self.fallback.clone().or(inherited);
+                return match fallback {
+                    Some(fallback) => {
+                        strip_uri_prefix(&mut req);
+                        fallback.call(req).await
+                    }
+                    None => StatusCode::NOT_FOUND.into(),
+                };
             }
-            None => StatusCode::NOT_FOUND.into(),
+        };
+
+        if target.nested {
+            if let Some(fallback) = &self.fallback {
+                req.state_mut().fallback = Some(fallback.clone());
+            }
+        } else {
+            // the fallback is only used by nested routes, and must not be seen
+            // by the matched endpoint
+            req.state_mut().fallback = None;
+            strip_uri_prefix(&mut req);
         }
+        target.ep.call(req).await
     }
 }
 
+/// Removes the prefix stripped by nested routes from the request URI.
+fn strip_uri_prefix(req: &mut Request) {
+    let prefix_len = std::mem::take(&mut req.state_mut().stripped_prefix_len);
+    if prefix_len == 0 {
+        return;
+    }
+
+    let uri = std::mem::take(req.uri_mut());
+    let mut uri_parts = uri.into_parts();
+    let path_and_query = uri_parts.path_and_query.take().unwrap();
+    let path = &path_and_query.as_str()[prefix_len..];
+    let (new_path_and_query, offset) = if !path.starts_with('/') {
+        (PathAndQuery::from_str(&format!("/{}", path)).unwrap(), 1)
+    } else {
+        (PathAndQuery::from_str(path).unwrap(), 0)
+    };
+    req.state_mut()
+        .match_params
+        .strip_prefix(path_and_query.path(), prefix_len, offset);
+    uri_parts.path_and_query = Some(new_path_and_query);
+    *req.uri_mut() = Uri::from_parts(uri_parts).unwrap();
+}
+
 fn normalize_path(path: &str) -> String {
     let re = Regex::new("//+").unwrap();
     let mut path = re.replace_all(path, "/").to_string();
@@ -400,4 +859,204 @@ mod tests {
         assert_eq!(get(&r, "/a").await, "/");
         assert_eq!(get(&r, "/a?a=1").await, "/?a=1");
     }
+
+    #[tokio::test]
+    async fn nested_params() {
+        let r = Route::new().nest(
+            "/a/:x",
+            Route::new().nest(
+                "/b",
+                Route::new()
+                    .at(
+                        "/c/:y",
+                        make_sync(|req| {
+                            format!(
+                                "{} {} {}",
+                                req.uri(),
+                                req.path_param("x").unwrap(),
+                                req.path_param("y").unwrap()
+                            )
+                        }),
+                    )
+                    .nest("/d", make_sync(|req| req.uri().to_string())),
+            ),
+        );
+        assert_eq!(get(&r, "/a/1/b/c/2?k=v").await, "/c/2?k=v 1 2");
+        assert_eq!(get(&r, "/a/1/b/d/e").await, "/e");
+        assert_eq!(get(&r, "/a/1/b/d").await, "/");
+
+        // the URI is stripped before calling a wrapped route, which matches
+        // against the stripped path
+        let r = Route::new().nest(
+            "/a/:x",
+            Route::new()
+                .at(
+                    "/:y/*z",
+                    make_sync(|req| {
+                        format!(
+                            "{} {} {} {}",
+                            req.uri(),
+                            req.path_param("x").unwrap(),
+                            req.path_param("y").unwrap(),
+                            req.path_param("z").unwrap()
+                        )
+                    }),
+                )
+                .map_to_response(),
+        );
+        assert_eq!(get(&r, "/a/1/2/3/4").await, "/2/3/4 1 2 3/4");
+        assert_eq!(get(&r, "/a/1/2/").await, "/2/ 1 2 ");
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        let r = Route::new()
+            .at("/a", h)
+            .nest(
+                "/api",
+                Route::new()
+                    .at("/b", h)
+                    .nest("/v1", Route::new().at("/c", h))
+                    .fallback(make_sync(|req| format!("api {}", req.uri()))),
+            )
+            .nest("/other", Route::new().at("/d", h))
+            .fallback(make_sync(|req| format!("root {}", req.uri())));
+
+        assert_eq!(get(&r, "/a").await, "/a");
+        assert_eq!(get(&r, "/x").await, "root /x");
+        assert_eq!(get(&r, "/api/b").await, "/b");
+        assert_eq!(get(&r, "/api/x?k=v").await, "api /x?k=v");
+        assert_eq!(get(&r, "/api/v1/x").await, "api /x");
+        assert_eq!(get(&r, "/other/x").await, "root /x");
+    }
+
+    #[tokio::test]
+    async fn fallback_cleared_on_match() {
+        #[handler(internal)]
+        fn has_fallback(req: &Request) -> String {
+            req.state().fallback.is_some().to_string()
+        }
+
+        let r = Route::new()
+            .nest(
+                "/api",
+                Route::new()
+                    .at("/a", has_fallback)
+                    .nest("/b", Route::new().at("/c", has_fallback).map_to_response()),
+            )
+            .fallback(make_sync(|_| "root"));
+
+        assert_eq!(get(&r, "/api/a").await, "false");
+        assert_eq!(get(&r, "/api/x").await, "root");
+        // a nested endpoint may be a wrapped route, which still needs it
+        assert_eq!(get(&r, "/api/b/c").await, "false");
+        assert_eq!(get(&r, "/api/b/x").await, "root");
+    }
+
+    #[test]
+    fn conflicts() {
+        let r = Route::new().at("/a/:id", h).at("/b", h);
+
+        let err = r.try_at("/a/:name<\\d+>", h).err().unwrap();
+        assert!(matches!(
+            &err,
+            RouteError::Conflict { path, existing_path, .. }
+                if path == "/a/:name<\\d+>" && existing_path == "/a/:id"
+        ));
+
+        let r = Route::new().at("/a/:id", h).at("/b", h);
+        let err = r.try_at("/b", h).err().unwrap();
+        assert!(matches!(
+            &err,
+            RouteError::Conflict { path, existing_path, .. }
+                if path == "/b" && existing_path == "/b"
+        ));
+
+        let err = Route::new().try_at("/c/:", h).err().unwrap();
+        assert!(matches!(
+            &err,
+            RouteError::InvalidPath { path, .. } if path == "/c/:"
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "path `/a` at")]
+    fn conflict_panics() {
+        let _ = Route::new().at("/a", h).at("/a", h);
+    }
+
+    #[test]
+    fn conflict_location() {
+        let line = line!() + 2;
+        let err = Route::new()
+            .try_at("/a", h)
+            .unwrap()
+            .try_at("/a", h)
+            .err()
+            .unwrap();
+        match err {
+            RouteError::Conflict {
+                location,
+                existing_location,
+                ..
+            } => {
+                assert_eq!(location.file(), file!());
+                assert_eq!(existing_location.line(), line);
+                assert_eq!(location.line(), line + 2);
+            }
+            _ => panic!("expect a conflict"),
+        }
+    }
+
+    #[test]
+    fn nested_conflicts() {
+        let errors = Route::new()
+            .at("/api/b", h)
+            .at("/api/c/:id", h)
+            .try_nest(
+                "/api",
+                Route::new()
+                    .at("/a", h)
+                    .at("/b", h)
+                    .nest("/c", Route::new().at("/:name", h)),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[0],
+            RouteError::Conflict { path, existing_path, .. }
+                if path == "/api/b" && existing_path == "/api/b"
+        ));
+        assert!(matches!(
+            &errors[1],
+            RouteError::Conflict { path, existing_path, .. }
+                if path == "/api/c/:name" && existing_path == "/api/c/:id"
+        ));
+
+        // the routes added after a nested route are checked against its routes
+        let r = Route::new().nest_no_strip("/v2", Route::new().at("/v2/:x", h));
+        let err = r.try_at("/v2/:y", h).err().unwrap();
+        assert!(matches!(
+            &err,
+            RouteError::Conflict { path, existing_path, .. }
+                if path == "/v2/:y" && existing_path == "/v2/:x"
+        ));
+
+        let errors = Route::new()
+            .nest("/api", Route::new().at("/c", h))
+            .try_nest("/api", Route::new().at("/c", h))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &errors[0],
+            RouteError::Conflict { path, existing_path, .. }
+                if path == "/api" && existing_path == "/api"
+        ));
+
+        let r = Route::new()
+            .nest("/", Route::new().at("/a", h))
+            .nest("/files", make_sync(|_| "files"));
+        assert!(r.try_at("/b", h).is_ok());
+    }
 }
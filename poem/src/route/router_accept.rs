@@ -0,0 +1,159 @@
+use mime::Mime;
+
+use crate::{
+    endpoint::BoxEndpoint,
+    http::{header, HeaderValue, StatusCode},
+    route::internal::media_type::{accept_quality, parse_accept},
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response,
+};
+
+/// Routing object for `Accept` header
+///
+/// The endpoint whose media type has the highest quality value in the request
+/// `Accept` header is selected. If several media types have the same quality,
+/// the one that was added first wins. If the request has no `Accept` header,
+/// the first endpoint is used.
+///
+/// If none of the media types is acceptable, `406 Not Acceptable` is returned.
+/// All responses contain a `Vary: Accept` header.
+#[derive(Default)]
+pub struct RouteAccept {
+    items: Vec<(Mime, BoxEndpoint<'static, Response>)>,
+}
+
+impl RouteAccept {
+    /// Create a `RouteAccept` object.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add an [Endpoint] for the specified media type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `media_type` is not a valid media type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::make_sync,
+    ///     http::{header, StatusCode},
+    ///     Endpoint, Request, RouteAccept,
+    /// };
+    ///
+    /// let app = RouteAccept::new()
+    ///     .add("text/html", make_sync(|_| "<h1>hello</h1>"))
+    ///     .add("application/json", make_sync(|_| "\"hello\""));
+    ///
+    /// fn make_request(accept: &str) -> Request {
+    ///     Request::builder().header(header::ACCEPT, accept).finish()
+    /// }
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = app.call(make_request("application/json")).await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "\"hello\"");
+    ///
+    /// let resp = app
+    ///     .call(make_request("text/html;q=0.9, application/*;q=0.5"))
+    ///     .await;
+    /// assert_eq!(
+    ///     resp.into_body().into_string().await.unwrap(),
+    ///     "<h1>hello</h1>"
+    /// );
+    ///
+    /// let resp = app.call(make_request("image/png")).await;
+    /// assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    /// # });
+    /// ```
+    pub fn add<E>(mut self, media_type: impl AsRef<str>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let mime = media_type
+            .as_ref()
+            .parse::<Mime>()
+            .expect("invalid media type");
+        self.items
+            .push((mime, Box::new(ep.into_endpoint().map_to_response())));
+        self
+    }
+
+    fn select(&self, req: &Request) -> Option<&BoxEndpoint<'static, Response>> {
+        let accept = match req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(accept) => parse_accept(accept),
+            None => return self.items.first().map(|(_, ep)| ep),
+        };
+
+        let mut selected = None;
+        let mut max_quality = 0;
+        for (mime, ep) in &self.items {
+            let quality = accept_quality(&accept, mime);
+            if quality > max_quality {
+                selected = Some(ep);
+                max_quality = quality;
+            }
+        }
+        selected
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for RouteAccept {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let mut resp = match self.select(&req) {
+            Some(ep) => ep.call(req).await,
+            None => StatusCode::NOT_ACCEPTABLE.into(),
+        };
+        resp.headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::make_sync;
+
+    async fn check(r: &RouteAccept, accept: Option<&str>, value: Option<&str>) {
+        let mut req = Request::builder();
+        if let Some(accept) = accept {
+            req = req.header(header::ACCEPT, accept);
+        }
+        let resp = r.call(req.finish()).await;
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept");
+        match value {
+            Some(value) => {
+                assert_eq!(resp.status(), StatusCode::OK);
+                assert_eq!(resp.into_body().into_string().await.unwrap(), value);
+            }
+            None => assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE),
+        }
+    }
+
+    #[tokio::test]
+    async fn route_accept() {
+        let r = RouteAccept::new()
+            .add("text/html", make_sync(|_| "html"))
+            .add("application/json", make_sync(|_| "json"))
+            .add("text/plain", make_sync(|_| "plain"));
+
+        check(&r, None, Some("html")).await;
+        check(&r, Some("*/*"), Some("html")).await;
+        check(&r, Some("application/json"), Some("json")).await;
+        check(&r, Some("text/plain, text/html"), Some("html")).await;
+        check(&r, Some("text/*;q=0.5, text/plain"), Some("plain")).await;
+        check(&r, Some("text/html;q=0.1, */*;q=0.5"), Some("json")).await;
+        check(&r, Some("text/*;q=0, application/json"), Some("json")).await;
+        check(&r, Some("image/png"), None).await;
+        check(&r, Some("text/*;q=0, application/*;q=0"), None).await;
+    }
+}
@@ -0,0 +1,141 @@
+use mime::Mime;
+
+use crate::{
+    endpoint::BoxEndpoint, http::StatusCode, route::internal::media_type::match_specificity,
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response,
+};
+
+/// Routing object for `Content-Type` header
+///
+/// The endpoint whose media type matches the request `Content-Type` header
+/// most specifically is selected, parameters such as `charset` are ignored.
+/// Media types can contain wildcards, such as `text/*` or `*/*`.
+///
+/// If the request has no `Content-Type` header or none of the media types
+/// matches, `415 Unsupported Media Type` is returned.
+#[derive(Default)]
+pub struct RouteContentType {
+    items: Vec<(Mime, BoxEndpoint<'static, Response>)>,
+}
+
+impl RouteContentType {
+    /// Create a `RouteContentType` object.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add an [Endpoint] for the specified media type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `media_type` is not a valid media type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::make_sync, http::StatusCode, Endpoint, Request, RouteContentType};
+    ///
+    /// let app = RouteContentType::new()
+    ///     .add("application/json", make_sync(|_| "json"))
+    ///     .add("text/*", make_sync(|_| "text"));
+    ///
+    /// fn make_request(content_type: &str) -> Request {
+    ///     Request::builder().content_type(content_type).finish()
+    /// }
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = app.call(make_request("application/json")).await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "json");
+    ///
+    /// let resp = app.call(make_request("text/plain; charset=utf-8")).await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "text");
+    ///
+    /// let resp = app.call(make_request("image/png")).await;
+    /// assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    /// # });
+    /// ```
+    pub fn add<E>(mut self, media_type: impl AsRef<str>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let mime = media_type
+            .as_ref()
+            .parse::<Mime>()
+            .expect("invalid media type");
+        self.items
+            .push((mime, Box::new(ep.into_endpoint().map_to_response())));
+        self
+    }
+
+    fn select(&self, req: &Request) -> Option<&BoxEndpoint<'static, Response>> {
+        let content_type = req.content_type()?.parse::<Mime>().ok()?;
+        let mut selected: Option<(u8, &BoxEndpoint<'static, Response>)> = None;
+
+        for (mime, ep) in &self.items {
+            match (match_specificity(mime, &content_type), selected) {
+                (Some(specificity), Some((max, _))) if specificity > max => {
+                    selected = Some((specificity, ep))
+                }
+                (Some(specificity), None) => selected = Some((specificity, ep)),
+                _ => {}
+            }
+        }
+        selected.map(|(_, ep)| ep)
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for RouteContentType {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        match self.select(&req) {
+            Some(ep) => ep.call(req).await,
+            None => StatusCode::UNSUPPORTED_MEDIA_TYPE.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::make_sync;
+
+    async fn check(r: &RouteContentType, content_type: Option<&str>, value: Option<&str>) {
+        let mut req = Request::builder();
+        if let Some(content_type) = content_type {
+            req = req.content_type(content_type);
+        }
+        let resp = r.call(req.finish()).await;
+        match value {
+            Some(value) => {
+                assert_eq!(resp.status(), StatusCode::OK);
+                assert_eq!(resp.into_body().into_string().await.unwrap(), value);
+            }
+            None => assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        }
+    }
+
+    #[tokio::test]
+    async fn route_content_type() {
+        let r = RouteContentType::new()
+            .add("text/*", make_sync(|_| "text"))
+            .add("application/json", make_sync(|_| "json"))
+            .add("text/plain", make_sync(|_| "plain"));
+
+        check(&r, Some("application/json"), Some("json")).await;
+        check(&r, Some("application/json; charset=utf-8"), Some("json")).await;
+        check(&r, Some("text/plain"), Some("plain")).await;
+        check(&r, Some("text/html"), Some("text")).await;
+        check(&r, Some("image/png"), None).await;
+        check(&r, Some("invalid"), None).await;
+        check(&r, None, None).await;
+
+        let r = RouteContentType::new()
+            .add("application/json", make_sync(|_| "json"))
+            .add("*/*", make_sync(|_| "any"));
+        check(&r, Some("image/png"), Some("any")).await;
+        check(&r, Some("application/json"), Some("json")).await;
+    }
+}
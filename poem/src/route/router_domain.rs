@@ -1,14 +1,31 @@
+use std::sync::Arc;
+
 use crate::{
     endpoint::BoxEndpoint,
     http::{header, StatusCode},
-    route::internal::trie::Trie,
+    route::{internal::trie::Trie, ParamValue},
     Endpoint, EndpointExt, IntoEndpoint, Request, Response,
 };
 
+struct DomainTarget {
+    /// The parameter names of the `+` and `:name` labels, in the order of the
+    /// pattern.
+    names: Vec<Option<Arc<str>>>,
+    ep: BoxEndpoint<'static, Response>,
+}
+
 /// Routing object for `HOST` header
+///
+/// A pattern with a port, such as `localhost:3000`, only matches the requests
+/// to that port, and takes precedence over the patterns without a port, which
+/// match the requests to any port.
 #[derive(Default)]
 pub struct RouteDomain {
-    tree: Trie<BoxEndpoint<'static, Response>>,
+    tree: Trie<DomainTarget>,
+    /// The patterns with a port, which are matched against the whole `HOST`
+    /// header.
+    port_tree: Trie<DomainTarget>,
+    fallback: Option<BoxEndpoint<'static, Response>>,
 }
 
 impl RouteDomain {
@@ -19,6 +36,14 @@ impl RouteDomain {
 
     /// Add an [Endpoint] to the specified domain pattern.
     ///
+    /// In a pattern, `+` matches exactly one label, `*` matches the remaining
+    /// labels and `:name` matches one label and captures it. Captured labels
+    /// can be read with the [`Path`](crate::web::Path) extractor, and come
+    /// before the parameters of the path.
+    ///
+    /// The pattern may end with a port, such as `localhost:3000`, to only
+    /// match the requests to that port.
+    ///
     /// # Example
     ///
     /// ```
@@ -46,40 +71,171 @@ impl RouteDomain {
     /// assert_eq!(do_request(&app, Request::default()).await, "4");
     /// # });
     /// ```
+    ///
+    /// Capture a subdomain:
+    ///
+    /// ```
+    /// use poem::{handler, http::header, web::Path, Endpoint, Request, RouteDomain};
+    ///
+    /// #[handler]
+    /// fn tenant(Path(tenant): Path<String>) -> String {
+    ///     tenant
+    /// }
+    ///
+    /// let app = RouteDomain::new().add(":tenant.example.com", tenant);
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = app
+    ///     .call(
+    ///         Request::builder()
+    ///             .header(header::HOST, "acme.example.com:8080")
+    ///             .finish(),
+    ///     )
+    ///     .await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "acme");
+    /// # });
+    /// ```
     pub fn add<E>(mut self, pattern: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.tree.add(
-            pattern.as_ref(),
-            Box::new(ep.into_endpoint().map_to_response()),
+        let pattern = pattern.as_ref();
+        let mut names = Vec::new();
+        let mut labels = Vec::new();
+        for label in pattern.split('.') {
+            match label.strip_prefix(':') {
+                Some(name) => {
+                    names.push(Some(Arc::from(name)));
+                    labels.push("+");
+                }
+                None => {
+                    if label == "+" {
+                        names.push(None);
+                    }
+                    labels.push(label);
+                }
+            }
+        }
+
+        let tree = if strip_port(pattern) != pattern {
+            &mut self.port_tree
+        } else {
+            &mut self.tree
+        };
+        tree.add(
+            &labels.join("."),
+            DomainTarget {
+                names,
+                ep: Box::new(ep.into_endpoint().map_to_response()),
+            },
         );
         self
     }
+
+    /// Sets the endpoint used when no domain pattern matches, instead of
+    /// returning `404 Not Found`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::make_sync,
+    ///     http::{header, StatusCode},
+    ///     Endpoint, Request, RouteDomain,
+    /// };
+    ///
+    /// let app = RouteDomain::new()
+    ///     .add("example.com", make_sync(|_| "example"))
+    ///     .fallback(make_sync(|_| StatusCode::MISDIRECTED_REQUEST));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = app
+    ///     .call(
+    ///         Request::builder()
+    ///             .header(header::HOST, "rust-lang.org")
+    ///             .finish(),
+    ///     )
+    ///     .await;
+    /// assert_eq!(resp.status(), StatusCode::MISDIRECTED_REQUEST);
+    /// # });
+    /// ```
+    pub fn fallback<E>(mut self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.fallback = Some(Box::new(ep.into_endpoint().map_to_response()));
+        self
+    }
 }
 
 #[async_trait::async_trait]
 impl Endpoint for RouteDomain {
     type Output = Response;
 
-    async fn call(&self, req: Request) -> Self::Output {
-        let host = req
-            .headers()
-            .get(header::HOST)
-            .and_then(|host| host.to_str().ok())
-            .unwrap_or_default();
-        match self.tree.matches(host) {
-            Some(ep) => ep.call(req).await,
-            None => StatusCode::NOT_FOUND.into(),
+    async fn call(&self, mut req: Request) -> Self::Output {
+        let matched = {
+            let host = req
+                .headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .unwrap_or_default();
+            let name = strip_port(host);
+            let matches = if name != host {
+                self.port_tree.matches(host)
+            } else {
+                None
+            };
+            matches
+                .or_else(|| self.tree.matches(name))
+                .map(|(target, values)| {
+                    // the labels are matched from right to left
+                    let params = target
+                        .names
+                        .iter()
+                        .zip(values.into_iter().rev())
+                        .filter_map(|(name, value)| {
+                            Some((name.clone()?, ParamValue::Owned(value.to_string())))
+                        })
+                        .collect::<Vec<_>>();
+                    (&target.ep, params)
+                })
+        };
+
+        match matched {
+            Some((ep, params)) => {
+                for (name, value) in params {
+                    req.state_mut().match_params.push(name, value);
+                }
+                ep.call(req).await
+            }
+            None => match &self.fallback {
+                Some(fallback) => fallback.call(req).await,
+                None => StatusCode::NOT_FOUND.into(),
+            },
         }
     }
 }
 
+fn strip_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port))
+            if !name.is_empty()
+                && !port.is_empty()
+                && port.bytes().all(|c| c.is_ascii_digit())
+                && (!name.contains(':') || name.ends_with(']')) =>
+        {
+            name
+        }
+        _ => host,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{endpoint::make_sync, handler, http::HeaderMap};
+    use crate::{endpoint::make_sync, handler, http::HeaderMap, web::Path};
 
     async fn check(r: &RouteDomain, host: &str, value: &str) {
         let mut req = Request::builder();
@@ -145,5 +301,78 @@ mod tests {
             r.call(Request::default()).await.status(),
             StatusCode::NOT_FOUND,
         );
+
+        let r = r.fallback(make_sync(|_| "fallback"));
+        check(&r, "rust-lang.org", "fallback").await;
+        check(&r, "", "fallback").await;
+        check(&r, "www.example.com", "2").await;
+    }
+
+    #[test]
+    fn test_strip_port() {
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+    }
+
+    #[tokio::test]
+    async fn captures() {
+        #[handler(internal)]
+        fn h(req: &Request) -> String {
+            req.state()
+                .match_params
+                .iter(req.uri().path())
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+
+        let r = RouteDomain::new()
+            .add(":tenant.example.com", h)
+            .add(":a.+.:b.com", h)
+            .add("www.example.com", make_sync(|_| "www"));
+
+        check(&r, "acme.example.com", "tenant=acme").await;
+        check(&r, "acme.example.com:3000", "tenant=acme").await;
+        check(&r, "www.example.com", "www").await;
+        check(&r, "x.y.z.com", "a=x,b=z").await;
+    }
+
+    #[tokio::test]
+    async fn captures_order() {
+        #[handler(internal)]
+        fn h(Path((a, b)): Path<(String, String)>) -> String {
+            format!("{} {}", a, b)
+        }
+
+        let r = RouteDomain::new().add(":a.+.:b.com", h);
+        check(&r, "x.y.z.com", "x z").await;
+    }
+
+    #[tokio::test]
+    async fn port() {
+        let r = RouteDomain::new()
+            .add("example.com", make_sync(|_| "any"))
+            .add("example.com:8080", make_sync(|_| "8080"))
+            .add("+.example.com", make_sync(|_| "sub"))
+            .add("localhost:3000", make_sync(|_| "local"));
+
+        check(&r, "example.com", "any").await;
+        check(&r, "example.com:80", "any").await;
+        check(&r, "example.com:8080", "8080").await;
+        check(&r, "www.example.com:8080", "sub").await;
+        check(&r, "localhost:3000", "local").await;
+
+        assert_eq!(
+            r.call(
+                Request::builder()
+                    .header(header::HOST, "localhost:3001")
+                    .finish()
+            )
+            .await
+            .status(),
+            StatusCode::NOT_FOUND,
+        );
     }
 }
@@ -0,0 +1,333 @@
+use crate::{
+    endpoint::BoxEndpoint,
+    http::{
+        header::{self, HeaderName},
+        HeaderValue, StatusCode,
+    },
+    route::internal::media_type::parse_accept,
+    Endpoint, EndpointExt, IntoEndpoint, Request, Response,
+};
+
+enum HeaderMatcher {
+    Value,
+    MediaType,
+    MediaTypeParam(String),
+}
+
+/// Routing object for the value of a request header
+///
+/// This is typically used for API versioning via headers such as
+/// `Accept-Version`, `X-Api-Version` or vendor media types in the `Accept`
+/// header.
+///
+/// If the header is missing or no value matches, the endpoint specified by
+/// [`RouteHeader::fallback`] is used, or `404 Not Found` is returned if there
+/// is none. All responses contain a `Vary` header with the header name.
+pub struct RouteHeader {
+    header: HeaderName,
+    matcher: HeaderMatcher,
+    items: Vec<(String, BoxEndpoint<'static, Response>)>,
+    fallback: Option<BoxEndpoint<'static, Response>>,
+}
+
+impl RouteHeader {
+    fn with_matcher<K>(header: K, matcher: HeaderMatcher) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        let header = match header.try_into() {
+            Ok(header) => header,
+            Err(_) => panic!("invalid header name"),
+        };
+        Self {
+            header,
+            matcher,
+            items: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Create a `RouteHeader` object that matches the whole value of the
+    /// specified header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `header` is not a valid header name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::make_sync, Endpoint, Request, RouteHeader};
+    ///
+    /// let app = RouteHeader::new("X-Api-Version")
+    ///     .add("1", make_sync(|_| "v1"))
+    ///     .add("2", make_sync(|_| "v2"))
+    ///     .fallback(make_sync(|_| "v2"));
+    ///
+    /// async fn do_request(app: &RouteHeader, req: Request) -> String {
+    ///     app.call(req).await.into_body().into_string().await.unwrap()
+    /// }
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let req = Request::builder().header("X-Api-Version", "1").finish();
+    /// assert_eq!(do_request(&app, req).await, "v1");
+    ///
+    /// let req = Request::builder().header("X-Api-Version", "3").finish();
+    /// assert_eq!(do_request(&app, req).await, "v2");
+    ///
+    /// assert_eq!(do_request(&app, Request::default()).await, "v2");
+    /// # });
+    /// ```
+    pub fn new<K>(header: K) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        Self::with_matcher(header, HeaderMatcher::Value)
+    }
+
+    /// Create a `RouteHeader` object that matches the media types listed in
+    /// the specified header, such as `application/vnd.foo.v2+json`.
+    ///
+    /// Parameters are ignored, and if the header lists several media types,
+    /// they are tried in the order of their quality values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `header` is not a valid header name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::make_sync, http::header, Endpoint, Request, RouteHeader};
+    ///
+    /// let app = RouteHeader::media_type(header::ACCEPT)
+    ///     .add("application/vnd.foo.v1+json", make_sync(|_| "v1"))
+    ///     .add("application/vnd.foo.v2+json", make_sync(|_| "v2"));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let req = Request::builder()
+    ///     .header(header::ACCEPT, "application/vnd.foo.v2+json")
+    ///     .finish();
+    /// let resp = app.call(req).await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "v2");
+    /// # });
+    /// ```
+    pub fn media_type<K>(header: K) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        Self::with_matcher(header, HeaderMatcher::MediaType)
+    }
+
+    /// Create a `RouteHeader` object that matches a parameter of the media
+    /// types listed in the specified header, such as `version` in
+    /// `application/json; version=2`.
+    ///
+    /// If the header lists several media types, they are tried in the order
+    /// of their quality values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `header` is not a valid header name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{endpoint::make_sync, http::header, Endpoint, Request, RouteHeader};
+    ///
+    /// let app = RouteHeader::media_type_param(header::ACCEPT, "version")
+    ///     .add("1", make_sync(|_| "v1"))
+    ///     .add("2", make_sync(|_| "v2"));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let req = Request::builder()
+    ///     .header(header::ACCEPT, "application/json; version=1")
+    ///     .finish();
+    /// let resp = app.call(req).await;
+    /// assert_eq!(resp.into_body().into_string().await.unwrap(), "v1");
+    /// # });
+    /// ```
+    pub fn media_type_param<K>(header: K, param: impl Into<String>) -> Self
+    where
+        K: TryInto<HeaderName>,
+    {
+        Self::with_matcher(header, HeaderMatcher::MediaTypeParam(param.into()))
+    }
+
+    /// Add an [Endpoint] for the specified header value.
+    pub fn add<E>(mut self, value: impl Into<String>, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let mut value = value.into();
+        if let HeaderMatcher::MediaType = self.matcher {
+            value.make_ascii_lowercase();
+        }
+        self.items
+            .push((value, Box::new(ep.into_endpoint().map_to_response())));
+        self
+    }
+
+    /// Sets the endpoint used when the header is missing or no value matches.
+    pub fn fallback<E>(mut self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.fallback = Some(Box::new(ep.into_endpoint().map_to_response()));
+        self
+    }
+
+    fn find(&self, value: &str) -> Option<&BoxEndpoint<'static, Response>> {
+        self.items
+            .iter()
+            .find(|(item, _)| item == value)
+            .map(|(_, ep)| ep)
+    }
+
+    fn select(&self, req: &Request) -> Option<&BoxEndpoint<'static, Response>> {
+        let value = req
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())?;
+
+        match &self.matcher {
+            HeaderMatcher::Value => self.find(value.trim()),
+            HeaderMatcher::MediaType | HeaderMatcher::MediaTypeParam(_) => {
+                let mut media_types = parse_accept(value);
+                media_types.sort_by(|(_, a), (_, b)| b.cmp(a));
+                media_types
+                    .iter()
+                    .filter(|(_, quality)| *quality > 0)
+                    .find_map(|(mime, _)| match &self.matcher {
+                        HeaderMatcher::MediaTypeParam(param) => {
+                            self.find(mime.get_param(param.as_str())?.as_str())
+                        }
+                        _ => self.find(mime.essence_str()),
+                    })
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for RouteHeader {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Self::Output {
+        let mut resp = match self.select(&req).or_else(|| self.fallback.as_ref()) {
+            Some(ep) => ep.call(req).await,
+            None => StatusCode::NOT_FOUND.into(),
+        };
+        resp.headers_mut()
+            .append(header::VARY, HeaderValue::from(self.header.clone()));
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::make_sync;
+
+    async fn check(r: &RouteHeader, header: &str, value: Option<&str>, expect: Option<&str>) {
+        let mut req = Request::builder();
+        if let Some(value) = value {
+            req = req.header(header, value);
+        }
+        let resp = r.call(req.finish()).await;
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), header);
+        match expect {
+            Some(expect) => {
+                assert_eq!(resp.status(), StatusCode::OK);
+                assert_eq!(resp.into_body().into_string().await.unwrap(), expect);
+            }
+            None => assert_eq!(resp.status(), StatusCode::NOT_FOUND),
+        }
+    }
+
+    #[tokio::test]
+    async fn route_header_value() {
+        let r = RouteHeader::new("x-api-version")
+            .add("1", make_sync(|_| "v1"))
+            .add("2", make_sync(|_| "v2"));
+
+        check(&r, "x-api-version", Some("1"), Some("v1")).await;
+        check(&r, "x-api-version", Some(" 2 "), Some("v2")).await;
+        check(&r, "x-api-version", Some("3"), None).await;
+        check(&r, "x-api-version", None, None).await;
+
+        let r = r.fallback(make_sync(|_| "default"));
+        check(&r, "x-api-version", Some("3"), Some("default")).await;
+        check(&r, "x-api-version", None, Some("default")).await;
+    }
+
+    #[tokio::test]
+    async fn route_header_media_type() {
+        let r = RouteHeader::media_type(header::ACCEPT)
+            .add("application/vnd.foo.v1+json", make_sync(|_| "v1"))
+            .add("application/vnd.foo.V2+json", make_sync(|_| "v2"))
+            .fallback(make_sync(|_| "default"));
+
+        check(
+            &r,
+            "accept",
+            Some("application/vnd.foo.v1+json"),
+            Some("v1"),
+        )
+        .await;
+        check(
+            &r,
+            "accept",
+            Some("application/vnd.foo.v2+json; charset=utf-8"),
+            Some("v2"),
+        )
+        .await;
+        check(
+            &r,
+            "accept",
+            Some("application/vnd.foo.v1+json;q=0.5, application/vnd.foo.v2+json"),
+            Some("v2"),
+        )
+        .await;
+        check(
+            &r,
+            "accept",
+            Some("application/vnd.foo.v2+json;q=0, text/html"),
+            Some("default"),
+        )
+        .await;
+        check(&r, "accept", Some("application/json"), Some("default")).await;
+    }
+
+    #[tokio::test]
+    async fn route_header_media_type_param() {
+        let r = RouteHeader::media_type_param("content-type", "version")
+            .add("1", make_sync(|_| "v1"))
+            .add("2", make_sync(|_| "v2"));
+
+        check(
+            &r,
+            "content-type",
+            Some("application/json; version=1"),
+            Some("v1"),
+        )
+        .await;
+        check(
+            &r,
+            "content-type",
+            Some("application/json; version=\"2\""),
+            Some("v2"),
+        )
+        .await;
+        check(
+            &r,
+            "content-type",
+            Some("application/json; version=3"),
+            None,
+        )
+        .await;
+        check(&r, "content-type", Some("application/json"), None).await;
+    }
+}
@@ -1,13 +1,28 @@
 use crate::{
     endpoint::BoxEndpoint,
     http::{Method, StatusCode},
+    web::headers::Allow,
     Endpoint, EndpointExt, IntoEndpoint, Request, Response,
 };
 
 /// Routing object for HTTP methods
+///
+/// If no endpoint is registered for the request method, the following rules
+/// apply:
+///
+/// - `HEAD` requests are handled by the `GET` endpoint and the response body is
+///   discarded.
+/// - `OPTIONS` requests are answered with an empty response and an `Allow`
+///   header listing the registered methods.
+/// - Any other method gets a `405 Method Not Allowed` response with an `Allow`
+///   header.
+///
+/// If a fallback endpoint is set with [`RouteMethod::fallback`], it replaces
+/// the last two rules.
 #[derive(Default)]
 pub struct RouteMethod {
     methods: Vec<(Method, BoxEndpoint<'static, Response>)>,
+    fallback: Option<BoxEndpoint<'static, Response>>,
 }
 
 impl RouteMethod {
@@ -143,6 +158,65 @@ impl RouteMethod {
     {
         self.method(Method::TRACE, ep)
     }
+
+    /// Sets the endpoint used for the methods that have no endpoint.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     endpoint::make_sync,
+    ///     http::{Method, StatusCode},
+    ///     Endpoint, Request, RouteMethod,
+    /// };
+    ///
+    /// let route_method = RouteMethod::new()
+    ///     .get(make_sync(|_| "get"))
+    ///     .fallback(make_sync(|_| StatusCode::IM_A_TEAPOT));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = route_method
+    ///     .call(Request::builder().method(Method::POST).finish())
+    ///     .await;
+    /// assert_eq!(resp.status(), StatusCode::IM_A_TEAPOT);
+    /// # });
+    /// ```
+    pub fn fallback<E>(mut self, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.fallback = Some(Box::new(ep.into_endpoint().map_to_response()));
+        self
+    }
+}
+
+impl RouteMethod {
+    fn find(&self, method: &Method) -> Option<&BoxEndpoint<'static, Response>> {
+        self.methods
+            .iter()
+            .find(|(m, _)| m == method)
+            .map(|(_, ep)| ep)
+    }
+
+    fn allow_methods(&self) -> Allow {
+        let mut methods: Vec<Method> = Vec::with_capacity(self.methods.len() + 2);
+        let mut push = |method: &Method| {
+            if !methods.contains(method) {
+                methods.push(method.clone());
+            }
+        };
+
+        for (method, _) in &self.methods {
+            push(method);
+            if method == Method::GET {
+                push(&Method::HEAD);
+            }
+        }
+        push(&Method::OPTIONS);
+
+        methods.into_iter().collect()
+    }
 }
 
 #[async_trait::async_trait]
@@ -150,23 +224,33 @@ impl Endpoint for RouteMethod {
     type Output = Response;
 
     async fn call(&self, mut req: Request) -> Self::Output {
-        match self
-            .methods
-            .iter()
-            .find(|(method, _)| method == req.method())
-            .map(|(_, ep)| ep)
-        {
-            Some(ep) => ep.call(req).await,
-            None => {
-                if req.method() == Method::HEAD {
-                    req.set_method(Method::GET);
-                    let mut resp = self.call(req).await;
-                    resp.set_body(());
-                    return resp;
-                }
-                StatusCode::NOT_FOUND.into()
+        if let Some(ep) = self.find(req.method()) {
+            return ep.call(req).await;
+        }
+
+        if req.method() == Method::HEAD {
+            if let Some(ep) = self.find(&Method::GET) {
+                req.set_method(Method::GET);
+                let mut resp = ep.call(req).await;
+                resp.set_body(());
+                return resp;
             }
         }
+
+        if let Some(fallback) = &self.fallback {
+            return fallback.call(req).await;
+        }
+
+        if req.method() == Method::OPTIONS {
+            return Response::builder()
+                .typed_header(self.allow_methods())
+                .finish();
+        }
+
+        Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .typed_header(self.allow_methods())
+            .finish()
     }
 }
 
@@ -255,8 +339,9 @@ where
 mod tests {
     use super::*;
     use crate::{
+        endpoint::make_sync,
         handler,
-        http::{Method, StatusCode},
+        http::{header, Method, StatusCode},
         Request,
     };
 
@@ -326,4 +411,67 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
         assert!(resp.into_body().into_vec().await.unwrap().is_empty());
     }
+
+    #[tokio::test]
+    async fn method_not_allowed() {
+        #[handler(internal)]
+        fn index() -> &'static str {
+            "hello"
+        }
+
+        let route = RouteMethod::new().get(index).post(index);
+        let resp = route
+            .call(Request::builder().method(Method::DELETE).finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            "GET, HEAD, POST, OPTIONS"
+        );
+    }
+
+    #[tokio::test]
+    async fn options_method() {
+        #[handler(internal)]
+        fn index() -> &'static str {
+            "hello"
+        }
+
+        let route = RouteMethod::new().put(index).delete(index);
+        let resp = route
+            .call(Request::builder().method(Method::OPTIONS).finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            "PUT, DELETE, OPTIONS"
+        );
+        assert!(resp.into_body().into_vec().await.unwrap().is_empty());
+
+        let route = RouteMethod::new().put(index).options(index);
+        let resp = route
+            .call(Request::builder().method(Method::OPTIONS).finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::ALLOW).is_none());
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        let route = RouteMethod::new()
+            .get(make_sync(|_| "get"))
+            .fallback(make_sync(|req| req.method().to_string()));
+
+        for (method, body) in [
+            (Method::GET, "get"),
+            (Method::HEAD, ""),
+            (Method::POST, "POST"),
+            (Method::OPTIONS, "OPTIONS"),
+        ] {
+            let resp = route.call(Request::builder().method(method).finish()).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.into_body().into_string().await.unwrap(), body);
+        }
+    }
 }
@@ -2,7 +2,7 @@ use std::{
     convert::Infallible,
     future::Future,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
 };
@@ -20,6 +20,21 @@ use crate::{
     Endpoint, EndpointExt, IntoEndpoint, Response,
 };
 
+/// Tracks whether the server has started a graceful shutdown, it is added to
+/// the extensions of every request.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GracefulShutdown(Arc<AtomicBool>);
+
+impl GracefulShutdown {
+    pub(crate) fn start(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_started(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 enum Either<L, A> {
     Listener(L),
     Acceptor(A),
@@ -29,6 +44,7 @@ enum Either<L, A> {
 pub struct Server<L, A> {
     listener: Either<L, A>,
     name: Option<String>,
+    drain_delay: Duration,
 }
 
 impl<L: Listener> Server<L, Infallible> {
@@ -37,6 +53,7 @@ impl<L: Listener> Server<L, Infallible> {
         Self {
             listener: Either::Listener(listener),
             name: None,
+            drain_delay: Duration::default(),
         }
     }
 }
@@ -47,6 +64,7 @@ impl<A: Acceptor> Server<Infallible, A> {
         Self {
             listener: Either::Acceptor(acceptor),
             name: None,
+            drain_delay: Duration::default(),
         }
     }
 }
@@ -65,6 +83,20 @@ where
         }
     }
 
+    /// Sets how long the server keeps accepting new connections after the
+    /// graceful shutdown signal, defaults to zero.
+    ///
+    /// During this delay, the readiness route of
+    /// [`HealthCheck`](crate::endpoint::HealthCheck) reports `503 Service
+    /// Unavailable`, so that load balancers have time to stop sending new
+    /// connections to the server before it stops accepting them.
+    pub fn drain_delay(self, delay: Duration) -> Self {
+        Self {
+            drain_delay: delay,
+            ..self
+        }
+    }
+
     /// Run this server.
     pub async fn run<E>(self, ep: E) -> IoResult<()>
     where
@@ -87,18 +119,25 @@ where
         E::Endpoint: 'static,
     {
         let ep = Arc::new(ep.into_endpoint().map_to_response());
-        let Server { listener, name } = self;
+        let Server {
+            listener,
+            name,
+            drain_delay,
+        } = self;
         let name = name.as_deref();
         let alive_connections = Arc::new(AtomicUsize::new(0));
         let notify = Arc::new(Notify::new());
         let timeout_notify = Arc::new(Notify::new());
+        let graceful_shutdown = GracefulShutdown::default();
 
         let mut acceptor = match listener {
             Either::Listener(listener) => listener.into_acceptor().await?.boxed(),
             Either::Acceptor(acceptor) => acceptor.boxed(),
         };
 
-        tokio::pin!(signal);
+        let drain = tokio::time::sleep(drain_delay);
+        let mut draining = false;
+        tokio::pin!(signal, drain);
 
         for addr in acceptor.local_addr() {
             tracing::info!(name = name, addr = %addr, "listening");
@@ -107,41 +146,40 @@ where
 
         loop {
             tokio::select! {
-                _ = &mut signal => {
-                    if let Some(timeout) = timeout {
-                        tracing::info!(
-                            name = name,
-                            timeout_in_seconds = timeout.as_secs_f32(),
-                            "initiate graceful shutdown",
-                        );
-
-                        let timeout_notify = timeout_notify.clone();
-                        tokio::spawn(async move {
-                            tokio::time::sleep(timeout).await;
-                            timeout_notify.notify_waiters();
-                        });
-                    } else {
-                        tracing::info!(name = name, "initiate graceful shutdown");
+                _ = &mut signal, if !draining => {
+                    graceful_shutdown.start();
+                    if drain_delay.is_zero() {
+                        break;
                     }
-                    break;
+                    tracing::info!(
+                        name = name,
+                        delay_in_seconds = drain_delay.as_secs_f32(),
+                        "drain connections before graceful shutdown",
+                    );
+                    drain
+                        .as_mut()
+                        .reset(tokio::time::Instant::now() + drain_delay);
+                    draining = true;
                 },
+                _ = &mut drain, if draining => break,
                 res = acceptor.accept() => {
                     if let Ok((socket, local_addr, remote_addr)) = res {
                         let ep = ep.clone();
                         let alive_connections = alive_connections.clone();
                         let notify = notify.clone();
                         let timeout_notify = timeout_notify.clone();
+                        let graceful_shutdown = graceful_shutdown.clone();
 
                         tokio::spawn(async move {
                             alive_connections.fetch_add(1, Ordering::SeqCst);
 
                             if timeout.is_some() {
                                 tokio::select! {
-                                    _ = serve_connection(socket, local_addr, remote_addr, ep) => {}
+                                    _ = serve_connection(socket, local_addr, remote_addr, ep, graceful_shutdown) => {}
                                     _ = timeout_notify.notified() => {}
                                 }
                             } else {
-                                serve_connection(socket, local_addr, remote_addr, ep).await;
+                                serve_connection(socket, local_addr, remote_addr, ep, graceful_shutdown).await;
                             }
 
                             if alive_connections.fetch_sub(1, Ordering::SeqCst) == 1 {
@@ -154,6 +192,22 @@ where
         }
 
         drop(acceptor);
+        if let Some(timeout) = timeout {
+            tracing::info!(
+                name = name,
+                timeout_in_seconds = timeout.as_secs_f32(),
+                "initiate graceful shutdown",
+            );
+
+            let timeout_notify = timeout_notify.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                timeout_notify.notify_waiters();
+            });
+        } else {
+            tracing::info!(name = name, "initiate graceful shutdown");
+        }
+
         if alive_connections.load(Ordering::SeqCst) > 0 {
             tracing::info!(name = name, "wait for all connections to close.");
             notify.notified().await;
@@ -169,13 +223,16 @@ async fn serve_connection(
     local_addr: LocalAddr,
     remote_addr: RemoteAddr,
     ep: Arc<dyn Endpoint<Output = Response>>,
+    graceful_shutdown: GracefulShutdown,
 ) {
     let service = hyper::service::service_fn({
-        move |req: hyper::Request<hyper::Body>| {
+        move |mut req: hyper::Request<hyper::Body>| {
             let ep = ep.clone();
             let local_addr = local_addr.clone();
             let remote_addr = remote_addr.clone();
+            let graceful_shutdown = graceful_shutdown.clone();
             async move {
+                req.extensions_mut().insert(graceful_shutdown);
                 let resp = ep.call((req, local_addr, remote_addr).into()).await.into();
                 Ok::<_, Infallible>(resp)
             }
@@ -187,3 +244,69 @@ async fn serve_connection(
         .with_upgrades();
     let _ = conn.await;
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::oneshot,
+    };
+
+    use super::*;
+    use crate::{endpoint::HealthCheck, listener::TcpListener, Route};
+
+    async fn get_status(addr: std::net::SocketAddr, path: &str) -> Option<String> {
+        let mut stream = TcpStream::connect(addr).await.ok()?;
+        stream
+            .write_all(
+                format!(
+                    "GET {} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n",
+                    path
+                )
+                .as_bytes(),
+            )
+            .await
+            .ok()?;
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.ok()?;
+        resp.lines().next().map(ToString::to_string)
+    }
+
+    #[tokio::test]
+    async fn drain_delay() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        let (tx, rx) = oneshot::channel();
+        let server = tokio::spawn(
+            Server::new_with_acceptor(acceptor)
+                .drain_delay(Duration::from_millis(300))
+                .run_with_graceful_shutdown(
+                    Route::new().nest("/health", HealthCheck::new()),
+                    async move {
+                        let _ = rx.await;
+                    },
+                    None,
+                ),
+        );
+
+        assert_eq!(
+            get_status(addr, "/health/ready").await.as_deref(),
+            Some("HTTP/1.1 200 OK")
+        );
+
+        tx.send(()).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // new connections are still accepted during the delay
+        assert_eq!(
+            get_status(addr, "/health/ready").await.as_deref(),
+            Some("HTTP/1.1 503 Service Unavailable")
+        );
+
+        server.await.unwrap().unwrap();
+        assert_eq!(get_status(addr, "/health/ready").await, None);
+    }
+}
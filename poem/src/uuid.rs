@@ -0,0 +1,21 @@
+use rand::Rng;
+
+/// Generates a random UUID (version 4) in its hyphenated form, such as
+/// `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+pub(crate) fn uuid_v4(rng: &mut impl Rng) -> String {
+    let mut bytes = rng.gen::<[u8; 16]>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
@@ -24,6 +24,7 @@ mod template;
 #[doc(inline)]
 pub use headers;
 mod typed_header;
+pub(crate) mod typed_path;
 #[cfg(feature = "websocket")]
 #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
 pub mod websocket;
@@ -40,11 +41,13 @@ pub use json::Json;
 #[cfg(feature = "multipart")]
 pub use multipart::{Field, Multipart};
 pub use path::Path;
+pub use poem_derive::TypedPath;
 pub use query::Query;
 pub use redirect::Redirect;
 #[cfg(feature = "template")]
 pub use template::{HtmlTemplate, Template};
 pub use typed_header::TypedHeader;
+pub use typed_path::TypedPath;
 
 #[cfg(feature = "tempfile")]
 pub use self::tempfile::TempFile;
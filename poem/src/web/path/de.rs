@@ -65,8 +65,7 @@ macro_rules! parse_single_value {
             let value = self.url_params[0].1.parse().map_err(|_| {
                 PathDeserializerError::custom(format!(
                     "can not parse `{:?}` to a `{}`",
-                    self.url_params[0].1.as_str(),
-                    $tp
+                    self.url_params[0].1, $tp
                 ))
             })?;
             visitor.$visit_fn(value)
@@ -75,12 +74,12 @@ macro_rules! parse_single_value {
 }
 
 pub(crate) struct PathDeserializer<'de> {
-    url_params: &'de [(String, String)],
+    url_params: &'de [(&'de str, &'de str)],
 }
 
 impl<'de> PathDeserializer<'de> {
     #[inline]
-    pub(crate) fn new(url_params: &'de [(String, String)]) -> Self {
+    pub(crate) fn new(url_params: &'de [(&'de str, &'de str)]) -> Self {
         PathDeserializer { url_params }
     }
 }
@@ -119,7 +118,7 @@ impl<'de> Deserializer<'de> for PathDeserializer<'de> {
                 self.url_params.len()
             )));
         }
-        visitor.visit_str(&self.url_params[0].1)
+        visitor.visit_str(self.url_params[0].1)
     }
 
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -242,13 +241,13 @@ impl<'de> Deserializer<'de> for PathDeserializer<'de> {
         }
 
         visitor.visit_enum(EnumDeserializer {
-            value: &self.url_params[0].1,
+            value: self.url_params[0].1,
         })
     }
 }
 
 struct MapDeserializer<'de> {
-    params: &'de [(String, String)],
+    params: &'de [(&'de str, &'de str)],
     value: Option<&'de str>,
 }
 
@@ -518,7 +517,7 @@ impl<'de> VariantAccess<'de> for UnitVariant {
 }
 
 struct SeqDeserializer<'de> {
-    params: &'de [(String, String)],
+    params: &'de [(&'de str, &'de str)],
 }
 
 impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
@@ -546,7 +545,6 @@ mod tests {
     use serde::Deserialize;
 
     use super::*;
-    use crate::route::PathParams;
 
     #[derive(Debug, Deserialize, Eq, PartialEq)]
     enum MyEnum {
@@ -563,23 +561,11 @@ mod tests {
         a: i32,
     }
 
-    fn create_url_params<I, K, V>(values: I) -> PathParams
-    where
-        I: IntoIterator<Item = (K, V)>,
-        K: Into<String>,
-        V: Into<String>,
-    {
-        values
-            .into_iter()
-            .map(|(k, v)| (k.into(), v.into()))
-            .collect()
-    }
-
     macro_rules! check_single_value {
         ($ty:ty, $value_str:literal, $value:expr) => {
             #[allow(clippy::bool_assert_comparison)]
             {
-                let url_params = create_url_params(vec![("value", $value_str)]);
+                let url_params = vec![("value", $value_str)];
                 let deserializer = PathDeserializer::new(&url_params);
                 assert_eq!(<$ty>::deserialize(deserializer).unwrap(), $value);
             }
@@ -603,13 +589,13 @@ mod tests {
         check_single_value!(String, "abc", "abc");
         check_single_value!(char, "a", 'a');
 
-        let url_params = create_url_params(vec![("a", "B")]);
+        let url_params = vec![("a", "B")];
         assert_eq!(
             MyEnum::deserialize(PathDeserializer::new(&url_params)).unwrap(),
             MyEnum::B
         );
 
-        let url_params = create_url_params(vec![("a", "1"), ("b", "2")]);
+        let url_params = vec![("a", "1"), ("b", "2")];
         assert_eq!(
             i32::deserialize(PathDeserializer::new(&url_params)).unwrap_err(),
             PathDeserializerError::custom("wrong number of parameters: 2 expected 1".to_string())
@@ -618,7 +604,7 @@ mod tests {
 
     #[test]
     fn test_parse_seq() {
-        let url_params = create_url_params(vec![("a", "1"), ("b", "true"), ("c", "abc")]);
+        let url_params = vec![("a", "1"), ("b", "true"), ("c", "abc")];
         assert_eq!(
             <(i32, bool, String)>::deserialize(PathDeserializer::new(&url_params)).unwrap(),
             (1, true, "abc".to_string())
@@ -631,13 +617,13 @@ mod tests {
             TupleStruct(1, true, "abc".to_string())
         );
 
-        let url_params = create_url_params(vec![("a", "1"), ("b", "2"), ("c", "3")]);
+        let url_params = vec![("a", "1"), ("b", "2"), ("c", "3")];
         assert_eq!(
             <Vec<i32>>::deserialize(PathDeserializer::new(&url_params)).unwrap(),
             vec![1, 2, 3]
         );
 
-        let url_params = create_url_params(vec![("a", "c"), ("a", "B")]);
+        let url_params = vec![("a", "c"), ("a", "B")];
         assert_eq!(
             <Vec<MyEnum>>::deserialize(PathDeserializer::new(&url_params)).unwrap(),
             vec![MyEnum::C, MyEnum::B]
@@ -646,7 +632,7 @@ mod tests {
 
     #[test]
     fn test_parse_struct() {
-        let url_params = create_url_params(vec![("a", "1"), ("b", "true"), ("c", "abc")]);
+        let url_params = vec![("a", "1"), ("b", "true"), ("c", "abc")];
         assert_eq!(
             Struct::deserialize(PathDeserializer::new(&url_params)).unwrap(),
             Struct {
@@ -659,7 +645,7 @@ mod tests {
 
     #[test]
     fn test_parse_map() {
-        let url_params = create_url_params(vec![("a", "1"), ("b", "true"), ("c", "abc")]);
+        let url_params = vec![("a", "1"), ("b", "true"), ("c", "abc")];
         assert_eq!(
             <HashMap<String, String>>::deserialize(PathDeserializer::new(&url_params)).unwrap(),
             [("a", "1"), ("b", "true"), ("c", "abc")]
@@ -3,12 +3,16 @@ mod de;
 use std::ops::{Deref, DerefMut};
 
 use serde::de::DeserializeOwned;
+use smallvec::SmallVec;
 
 use crate::{error::ErrorInvalidPathParams, FromRequest, Request, RequestBody, Result};
 
 /// An extractor that will get captures from the URL and parse them using
 /// `serde`.
 ///
+/// The captures are not percent-decoded, unlike the parameters of a
+/// [`TypedPath`](crate::web::TypedPath).
+///
 /// # Example
 ///
 /// ```
@@ -125,8 +129,40 @@ impl<'a, T: DeserializeOwned> FromRequest<'a> for Path<T> {
     type Error = ErrorInvalidPathParams;
 
     async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self, Self::Error> {
-        T::deserialize(de::PathDeserializer::new(&req.state().match_params))
+        let params = req
+            .state()
+            .match_params
+            .iter(req.uri().path())
+            .collect::<SmallVec<[_; 8]>>();
+        T::deserialize(de::PathDeserializer::new(&params))
             .map_err(|_| ErrorInvalidPathParams)
             .map(Path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler, http::Uri, Endpoint, Route};
+
+    #[tokio::test]
+    async fn not_percent_decoded() {
+        #[handler(internal)]
+        fn name(Path(name): Path<String>) -> String {
+            name
+        }
+
+        let app = Route::new().at("/users/:name", name);
+        let resp = app
+            .call(
+                Request::builder()
+                    .uri(Uri::from_static("/users/hello%20w%C3%B6rld%2F"))
+                    .finish(),
+            )
+            .await;
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "hello%20w%C3%B6rld%2F"
+        );
+    }
+}
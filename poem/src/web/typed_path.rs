@@ -0,0 +1,133 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use percent_encoding::{percent_decode_str, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::{error::ErrorInvalidPathParams, Request};
+
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+const PATH: &AsciiSet = &PATH_SEGMENT.remove(b'/');
+
+/// A type-safe path.
+///
+/// A type that implements this trait is bound to a path pattern, can be used
+/// as an extractor for the path parameters, and renders back to a URL with
+/// its [`Display`] implementation. This trait is usually implemented with
+/// [`#[derive(TypedPath)]`](derive@TypedPath).
+///
+/// The pattern is specified with the `#[typed_path("...")]` attribute, and
+/// each parameter must have a field with the same name, whose type implements
+/// [`FromStr`] and [`Display`]. Mismatched parameter names are reported at
+/// compile time. The attribute is not named `#[path]`, because it would be
+/// ambiguous with the built-in `#[path]` attribute of Rust.
+///
+/// The parameters are percent-decoded, unlike the captures extracted with
+/// [`Path`](crate::web::Path).
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     get, handler,
+///     http::{StatusCode, Uri},
+///     web::TypedPath,
+///     Endpoint, Request, Route,
+/// };
+///
+/// #[derive(TypedPath)]
+/// #[typed_path("/users/:id/posts/:post_id")]
+/// struct UserPost {
+///     id: u64,
+///     post_id: String,
+/// }
+///
+/// #[handler]
+/// fn show(path: UserPost) -> String {
+///     format!("{}:{}", path.id, path.post_id)
+/// }
+///
+/// let app = Route::new().at(UserPost::PATH, get(show));
+///
+/// let url = UserPost {
+///     id: 100,
+///     post_id: "hello world".to_string(),
+/// }
+/// .to_string();
+/// assert_eq!(url, "/users/100/posts/hello%20world");
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = app
+///     .call(Request::builder().uri(url.parse::<Uri>().unwrap()).finish())
+///     .await;
+/// assert_eq!(resp.status(), StatusCode::OK);
+/// assert_eq!(
+///     resp.into_body().into_string().await.unwrap(),
+///     "100:hello world"
+/// );
+/// # });
+/// ```
+///
+/// Regular expressions and tail paths are supported too.
+///
+/// ```
+/// use poem::web::TypedPath;
+///
+/// #[derive(TypedPath)]
+/// #[typed_path("/files/:version<\\d+>/*path")]
+/// struct File {
+///     version: u32,
+///     path: String,
+/// }
+///
+/// let file = File {
+///     version: 1,
+///     path: "a/b c.txt".to_string(),
+/// };
+/// assert_eq!(file.to_string(), "/files/1/a/b%20c.txt");
+/// ```
+///
+/// A parameter without a corresponding field does not compile.
+///
+/// ```compile_fail
+/// use poem::web::TypedPath;
+///
+/// #[derive(TypedPath)]
+/// #[typed_path("/users/:user_id")]
+/// struct User {
+///     id: u64,
+/// }
+/// ```
+pub trait TypedPath: Display {
+    /// The path pattern.
+    const PATH: &'static str;
+}
+
+#[doc(hidden)]
+pub fn write_path_param(
+    f: &mut Formatter<'_>,
+    value: &impl Display,
+    catch_all: bool,
+) -> fmt::Result {
+    let value = value.to_string();
+    let set = if catch_all { PATH } else { PATH_SEGMENT };
+    write!(f, "{}", percent_encoding::utf8_percent_encode(&value, set))
+}
+
+#[doc(hidden)]
+pub fn parse_path_param<T: FromStr>(
+    req: &Request,
+    name: &str,
+) -> Result<T, ErrorInvalidPathParams> {
+    let value = req.path_param(name).ok_or(ErrorInvalidPathParams)?;
+    let value = percent_decode_str(value)
+        .decode_utf8()
+        .map_err(|_| ErrorInvalidPathParams)?;
+    value.parse().map_err(|_| ErrorInvalidPathParams)
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "embed")]
+
+use poem::{
+    endpoint::{Embed, EmbeddedFiles},
+    http::{header, StatusCode},
+    Endpoint, Request,
+};
+
+#[derive(Embed)]
+#[embed(folder = "tests/assets")]
+struct Assets;
+
+#[test]
+fn derive_embed() {
+    let file = Assets.get("hello.txt").unwrap();
+    assert_eq!(&*file.data, b"hello");
+    assert_eq!(file.etag, "a430d84680aabd0b-5");
+    assert!(file.br.is_none());
+    assert_eq!(
+        file.gzip.as_deref(),
+        Some(&include_bytes!("assets/hello.txt.gz")[..])
+    );
+
+    // the precompressed variants are not files of their own
+    assert!(Assets.get("hello.txt.gz").is_none());
+
+    let file = Assets.get("archive.gz").unwrap();
+    assert_eq!(&*file.data, b"data");
+    assert_eq!(file.etag, "855b556730a34a05-4");
+
+    let file = Assets.get("docs/index.html").unwrap();
+    assert_eq!(&*file.data, b"<h1>docs</h1>\n");
+    assert_eq!(file.etag, "7057dce2d6d4a4ab-e");
+
+    assert!(Assets.get("docs").is_none());
+    assert!(Assets.get("missing.txt").is_none());
+}
+
+#[tokio::test]
+async fn serve_derived() {
+    let ep = EmbeddedFiles::new(Assets).index_file("index.html");
+
+    let resp = ep
+        .call(Request::builder().uri("/docs/".parse().unwrap()).finish())
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::ETAG).unwrap(),
+        "\"7057dce2d6d4a4ab-e\""
+    );
+    assert_eq!(
+        resp.into_body().into_string().await.unwrap(),
+        "<h1>docs</h1>\n"
+    );
+
+    let resp = ep
+        .call(
+            Request::builder()
+                .uri("/hello.txt".parse().unwrap())
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .finish(),
+        )
+        .await;
+    assert_eq!(
+        resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "gzip"
+    );
+    assert_eq!(
+        resp.headers().get(header::ETAG).unwrap(),
+        "W/\"a430d84680aabd0b-5\""
+    );
+    assert_eq!(
+        resp.into_body().into_vec().await.unwrap(),
+        include_bytes!("assets/hello.txt.gz")
+    );
+}